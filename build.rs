@@ -1,4 +1,6 @@
 fn main() {
+  generate_header();
+
   #[cfg(target_os = "windows")]
   {
     let mut res = winres::WindowsResource::new();
@@ -26,3 +28,33 @@ fn main() {
     );
   }
 }
+
+// Generate the C header for the API in src/ffi.rs into OUT_DIR. The copy in
+// include/mew.h is only refreshed when MEW_UPDATE_HEADER is set, so builds
+// don't write to the source tree. Tests find the generated copy through
+// MEW_HEADER_DIR.
+fn generate_header() {
+  println!("cargo:rerun-if-changed=src/ffi.rs");
+  println!("cargo:rerun-if-changed=cbindgen.toml");
+  println!("cargo:rerun-if-env-changed=MEW_UPDATE_HEADER");
+
+  let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+  let out_dir = std::env::var("OUT_DIR").unwrap();
+  println!("cargo:rustc-env=MEW_HEADER_DIR={}", out_dir);
+  let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+    .expect("cbindgen.toml should be valid");
+
+  match cbindgen::Builder::new()
+    .with_crate(&crate_dir)
+    .with_config(config)
+    .generate()
+  {
+    Ok(bindings) => {
+      bindings.write_to_file(format!("{}/mew.h", out_dir));
+      if std::env::var_os("MEW_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(format!("{}/include/mew.h", crate_dir));
+      }
+    }
+    Err(err) => println!("cargo:warning=Could not generate mew.h: {}", err),
+  }
+}
@@ -0,0 +1,105 @@
+/* C API for the Mew programming language. Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#ifndef MEW_H
+#define MEW_H
+
+#include <stddef.h>
+
+// Whether a call succeeded
+typedef enum MewStatus {
+  MEW_STATUS_OK = 0,
+  MEW_STATUS_ERROR = 1,
+} MewStatus;
+
+// An interpreter together with the outcome of the last evaluation
+typedef struct MewInterpreter MewInterpreter;
+
+// A native function implemented in C.
+//
+// `args_json` is a JSON array holding the arguments. The callback returns
+// the result as a JSON string, which Mew copies before the callback is
+// invoked again, or NULL to make the call fail.
+typedef const char *(*MewCallback)(const char *args_json, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a new interpreter. Free it with `mew_interpreter_free`.
+struct MewInterpreter *mew_interpreter_new(void);
+
+// Free an interpreter created by `mew_interpreter_new`. NULL is ignored.
+//
+// # Safety
+//
+// `mew` must be NULL or a pointer returned by `mew_interpreter_new` that has
+// not been freed yet.
+void mew_interpreter_free(struct MewInterpreter *mew);
+
+// Run NUL-terminated Mew source in the interpreter's global scope.
+//
+// On success the value of the last statement is available from
+// `mew_result_json`. On failure the error is available from
+// `mew_error_kind`, `mew_error_message`, `mew_error_line` and
+// `mew_error_column`. A result that can't be represented as JSON, such as a
+// function, is reported as an error.
+//
+// # Safety
+//
+// `mew` must be a live interpreter and `source` a NUL-terminated string.
+enum MewStatus mew_eval(struct MewInterpreter *mew, const char *source);
+
+// The result of the last successful `mew_eval` as JSON, or NULL if it
+// failed
+//
+// # Safety
+//
+// `mew` must be a live interpreter.
+const char *mew_result_json(const struct MewInterpreter *mew);
+
+// The kind of the last error (e.g. "TypeError"), or NULL if there is none
+//
+// # Safety
+//
+// `mew` must be a live interpreter.
+const char *mew_error_kind(const struct MewInterpreter *mew);
+
+// The message of the last error, or NULL if there is none
+//
+// # Safety
+//
+// `mew` must be a live interpreter.
+const char *mew_error_message(const struct MewInterpreter *mew);
+
+// The line of the last error, or 0 if it has no location
+//
+// # Safety
+//
+// `mew` must be a live interpreter.
+size_t mew_error_line(const struct MewInterpreter *mew);
+
+// The column of the last error, or 0 if it has no location
+//
+// # Safety
+//
+// `mew` must be a live interpreter.
+size_t mew_error_column(const struct MewInterpreter *mew);
+
+// Expose `callback` to Mew code as the global function `name`.
+// `user_data` is passed back to every call unchanged.
+//
+// # Safety
+//
+// `mew` must be a live interpreter and `name` a NUL-terminated string.
+// `callback` and `user_data` must stay valid for as long as the interpreter
+// is alive.
+enum MewStatus mew_register_function(struct MewInterpreter *mew,
+                                     const char *name,
+                                     MewCallback callback,
+                                     void *user_data);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MEW_H */
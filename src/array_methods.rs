@@ -0,0 +1,490 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Methods available on array values, e.g. `[3, 1, 2].sort()`.
+//!
+//! Like string methods, every method receives the array it was called on as
+//! its first argument. Methods taking a callback call it through the
+//! interpreter with `(item, index, array)`, dropping any arguments the
+//! callback does not declare.
+
+use crate::error::{MewError, MewResult};
+use crate::interpreter::Interpreter;
+use crate::value::{NativeFunctionType, Value};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+type Array = Rc<RefCell<Vec<Value>>>;
+
+/// Array methods by name
+pub const METHODS: &[(&str, NativeFunctionType)] = &[
+  ("push", push),
+  ("pop", pop),
+  ("shift", shift),
+  ("unshift", unshift),
+  ("slice", slice),
+  ("splice", splice),
+  ("concat", concat),
+  ("indexOf", index_of),
+  ("includes", includes),
+  ("join", join),
+  ("reverse", reverse),
+  ("map", map),
+  ("filter", filter),
+  ("reduce", reduce),
+  ("find", find),
+  ("some", some),
+  ("every", every),
+  ("forEach", for_each),
+  ("sort", sort),
+];
+
+/// Find the native implementing the array method `name`
+pub fn lookup(name: &str) -> Option<NativeFunctionType> {
+  METHODS
+    .iter()
+    .find(|(method, _)| *method == name)
+    .map(|(_, function)| *function)
+}
+
+// Split the receiving array off the front of a method's arguments
+fn receiver(args: Vec<Value>, method: &str) -> MewResult<(Array, Vec<Value>)> {
+  let mut args = args.into_iter();
+
+  match args.next() {
+    Some(Value::Array(ref array)) => Ok((array.clone(), args.collect())),
+    _ => Err(MewError::type_error(format!(
+      "Array method '{}' must be called on an array",
+      method
+    ))),
+  }
+}
+
+fn callback_arg(args: &[Value], method: &str) -> MewResult<Value> {
+  match args.first() {
+    Some(callback @ (Value::Function(_) | Value::NativeFunction(_) | Value::Closure(_))) => {
+      Ok(callback.clone())
+    }
+    Some(other) => Err(MewError::type_error(format!(
+      "{} expects a function argument, got {}",
+      method,
+      other.type_name()
+    ))),
+    None => Err(MewError::runtime(format!(
+      "{} requires a function argument",
+      method
+    ))),
+  }
+}
+
+// A numeric argument, or `default` when it is missing or undefined
+fn number_arg(args: &[Value], index: usize, method: &str, default: f64) -> MewResult<f64> {
+  match args.get(index) {
+    None | Some(Value::Undefined) => Ok(default),
+    Some(Value::Number(n)) => Ok(if n.is_nan() { 0.0 } else { n.trunc() }),
+    Some(other) => Err(MewError::type_error(format!(
+      "{} expects a number argument, got {}",
+      method,
+      other.type_name()
+    ))),
+  }
+}
+
+// Clamp a position into `0..=len`, negative positions counting from the end
+fn relative(position: f64, len: usize) -> usize {
+  let position = if position < 0.0 {
+    len as f64 + position
+  } else {
+    position
+  };
+
+  position.max(0.0).min(len as f64) as usize
+}
+
+fn call_back(
+  interpreter: &mut Interpreter,
+  callback: &Value,
+  mut args: Vec<Value>,
+) -> MewResult<Value> {
+  match callback {
+    Value::Function(function) => args.truncate(function.parameters.len()),
+    Value::Closure(closure) => args.truncate(closure.proto.arity),
+    _ => {}
+  }
+
+  interpreter.call_function(callback.clone(), args)
+}
+
+// Element `index` of a live array. The borrow is released before returning
+// so callbacks are free to modify the array.
+fn element(array: &Array, index: usize) -> Option<Value> {
+  array.borrow().get(index).cloned()
+}
+
+// The elements a callback method visits, with their indexes. As in
+// JavaScript, elements added by the callback aren't visited, and the
+// iteration stops early if the array shrinks.
+fn elements(array: &Array) -> impl Iterator<Item = (usize, Value)> + '_ {
+  let length = array.borrow().len();
+  (0..length).map_while(move |index| element(array, index).map(|item| (index, item)))
+}
+
+fn push(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, items) = receiver(args, "push")?;
+  let mut array = array.borrow_mut();
+  array.extend(items);
+  Ok(Value::Number(array.len() as f64))
+}
+
+fn pop(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, _) = receiver(args, "pop")?;
+  let last = array.borrow_mut().pop();
+  Ok(last.unwrap_or(Value::Undefined))
+}
+
+fn shift(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, _) = receiver(args, "shift")?;
+  let mut array = array.borrow_mut();
+
+  if array.is_empty() {
+    return Ok(Value::Undefined);
+  }
+  Ok(array.remove(0))
+}
+
+fn unshift(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, items) = receiver(args, "unshift")?;
+  let mut array = array.borrow_mut();
+  array.splice(0..0, items);
+  Ok(Value::Number(array.len() as f64))
+}
+
+fn slice(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "slice")?;
+  let array = array.borrow();
+
+  let start = relative(number_arg(&args, 0, "slice", 0.0)?, array.len());
+  let end = relative(
+    number_arg(&args, 1, "slice", array.len() as f64)?,
+    array.len(),
+  );
+
+  if start >= end {
+    return Ok(Value::array(Vec::new()));
+  }
+  Ok(Value::array(array[start..end].to_vec()))
+}
+
+// splice(start, deleteCount?, ...items) removes and inserts in place and
+// returns the removed elements
+fn splice(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "splice")?;
+  let mut array = array.borrow_mut();
+
+  let start = relative(number_arg(&args, 0, "splice", 0.0)?, array.len());
+  let delete_count = number_arg(&args, 1, "splice", f64::INFINITY)?
+    .max(0.0)
+    .min((array.len() - start) as f64) as usize;
+  let items = args.into_iter().skip(2);
+
+  let removed: Vec<Value> = array.splice(start..start + delete_count, items).collect();
+  Ok(Value::array(removed))
+}
+
+fn concat(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "concat")?;
+  let mut result = array.borrow().clone();
+
+  for arg in args {
+    match &arg {
+      Value::Array(other) => result.extend(other.borrow().iter().cloned()),
+      _ => result.push(arg),
+    }
+  }
+
+  Ok(Value::array(result))
+}
+
+fn index_of(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "indexOf")?;
+  let search = args.first().cloned().unwrap_or(Value::Undefined);
+  let array = array.borrow();
+  let from = relative(number_arg(&args, 1, "indexOf", 0.0)?, array.len());
+
+  let position = array[from..]
+    .iter()
+    .position(|item| interpreter.is_equal(item, &search));
+
+  Ok(Value::Number(match position {
+    Some(offset) => (from + offset) as f64,
+    None => -1.0,
+  }))
+}
+
+fn includes(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "includes")?;
+  let search = args.first().cloned().unwrap_or(Value::Undefined);
+
+  // Unlike `==`, includes finds NaN
+  let found = array.borrow().iter().any(|item| match (item, &search) {
+    (Value::Number(a), Value::Number(b)) if a.is_nan() && b.is_nan() => true,
+    _ => interpreter.is_equal(item, &search),
+  });
+
+  Ok(Value::Bool(found))
+}
+
+fn join(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "join")?;
+  let separator = match args.first() {
+    None | Some(Value::Undefined) => ",".to_string(),
+    Some(Value::String(separator)) => separator.clone(),
+    Some(other) => {
+      return Err(MewError::type_error(format!(
+        "join expects a string separator, got {}",
+        other.type_name()
+      )))
+    }
+  };
+
+  let items: Vec<String> = array
+    .borrow()
+    .iter()
+    .map(|item| match item {
+      Value::Null | Value::Undefined => String::new(),
+      other => other.to_string(),
+    })
+    .collect();
+
+  Ok(Value::String(items.join(&separator)))
+}
+
+fn reverse(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, _) = receiver(args, "reverse")?;
+  array.borrow_mut().reverse();
+  Ok(Value::Array(array))
+}
+
+fn map(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "map")?;
+  let callback = callback_arg(&args, "map")?;
+
+  let mut result = Vec::new();
+  for (index, item) in elements(&array) {
+    let args = vec![
+      item,
+      Value::Number(index as f64),
+      Value::Array(array.clone()),
+    ];
+    result.push(call_back(interpreter, &callback, args)?);
+  }
+
+  Ok(Value::array(result))
+}
+
+fn filter(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "filter")?;
+  let callback = callback_arg(&args, "filter")?;
+
+  let mut result = Vec::new();
+  for (index, item) in elements(&array) {
+    let args = vec![
+      item.clone(),
+      Value::Number(index as f64),
+      Value::Array(array.clone()),
+    ];
+    if call_back(interpreter, &callback, args)?.is_truthy() {
+      result.push(item);
+    }
+  }
+
+  Ok(Value::array(result))
+}
+
+// reduce(callback, initial?) calls callback(accumulator, item, index, array)
+fn reduce(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "reduce")?;
+  let callback = callback_arg(&args, "reduce")?;
+
+  let mut items = elements(&array);
+  let mut accumulator = match args.get(1) {
+    Some(initial) => initial.clone(),
+    None => match items.next() {
+      Some((_, first)) => first,
+      None => {
+        return Err(MewError::type_error(
+          "reduce of empty array with no initial value",
+        ))
+      }
+    },
+  };
+
+  for (index, item) in items {
+    let args = vec![
+      accumulator,
+      item,
+      Value::Number(index as f64),
+      Value::Array(array.clone()),
+    ];
+    accumulator = call_back(interpreter, &callback, args)?;
+  }
+
+  Ok(accumulator)
+}
+
+// Index and value of the first element the callback accepts
+fn find_first(
+  interpreter: &mut Interpreter,
+  array: &Array,
+  callback: &Value,
+) -> MewResult<Option<(usize, Value)>> {
+  for (index, item) in elements(array) {
+    let args = vec![
+      item.clone(),
+      Value::Number(index as f64),
+      Value::Array(array.clone()),
+    ];
+    if call_back(interpreter, callback, args)?.is_truthy() {
+      return Ok(Some((index, item)));
+    }
+  }
+
+  Ok(None)
+}
+
+fn find(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "find")?;
+  let callback = callback_arg(&args, "find")?;
+
+  let found = find_first(interpreter, &array, &callback)?;
+  Ok(found.map_or(Value::Undefined, |(_, item)| item))
+}
+
+fn some(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "some")?;
+  let callback = callback_arg(&args, "some")?;
+
+  let found = find_first(interpreter, &array, &callback)?;
+  Ok(Value::Bool(found.is_some()))
+}
+
+fn every(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "every")?;
+  let callback = callback_arg(&args, "every")?;
+
+  for (index, item) in elements(&array) {
+    let args = vec![
+      item,
+      Value::Number(index as f64),
+      Value::Array(array.clone()),
+    ];
+    if !call_back(interpreter, &callback, args)?.is_truthy() {
+      return Ok(Value::Bool(false));
+    }
+  }
+
+  Ok(Value::Bool(true))
+}
+
+fn for_each(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "forEach")?;
+  let callback = callback_arg(&args, "forEach")?;
+
+  for (index, item) in elements(&array) {
+    let args = vec![
+      item,
+      Value::Number(index as f64),
+      Value::Array(array.clone()),
+    ];
+    call_back(interpreter, &callback, args)?;
+  }
+
+  Ok(Value::Undefined)
+}
+
+// Sorts in place and returns the array. The comparator follows the usual
+// convention of returning a negative, zero or positive number. Without one,
+// numbers are compared numerically and anything else by its text.
+fn sort(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (array, args) = receiver(args, "sort")?;
+  let comparator = match args.first() {
+    None | Some(Value::Undefined) => None,
+    Some(_) => Some(callback_arg(&args, "sort")?),
+  };
+
+  let mut compare = |a: &Value, b: &Value| -> MewResult<Ordering> {
+    let Some(comparator) = &comparator else {
+      return Ok(default_order(a, b));
+    };
+
+    match call_back(interpreter, comparator, vec![a.clone(), b.clone()])? {
+      Value::Number(n) if n < 0.0 => Ok(Ordering::Less),
+      Value::Number(n) if n > 0.0 => Ok(Ordering::Greater),
+      Value::Number(_) => Ok(Ordering::Equal),
+      other => Err(MewError::type_error(format!(
+        "sort comparator must return a number, got {}",
+        other.type_name()
+      ))),
+    }
+  };
+
+  // Sort a snapshot so the comparator never sees a half-sorted array
+  let items = array.borrow().clone();
+  let sorted = merge_sort(items, &mut compare)?;
+  *array.borrow_mut() = sorted;
+
+  Ok(Value::Array(array))
+}
+
+fn default_order(a: &Value, b: &Value) -> Ordering {
+  match (a, b) {
+    (Value::Number(x), Value::Number(y)) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+    _ => a.to_string().cmp(&b.to_string()),
+  }
+}
+
+// A stable merge sort whose comparator may fail. Unlike `slice::sort_by`, an
+// inconsistent user comparator can't cause a panic here.
+fn merge_sort(
+  mut items: Vec<Value>,
+  compare: &mut dyn FnMut(&Value, &Value) -> MewResult<Ordering>,
+) -> MewResult<Vec<Value>> {
+  if items.len() <= 1 {
+    return Ok(items);
+  }
+
+  let right = items.split_off(items.len() / 2);
+  let left = merge_sort(items, compare)?;
+  let right = merge_sort(right, compare)?;
+
+  let mut merged = Vec::with_capacity(left.len() + right.len());
+  let mut left = left.into_iter().peekable();
+  let mut right = right.into_iter().peekable();
+
+  while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+    // Take from the right only when strictly smaller, keeping equal
+    // elements in their original order
+    if compare(r, l)? == Ordering::Less {
+      merged.extend(right.next());
+    } else {
+      merged.extend(left.next());
+    }
+  }
+  merged.extend(left);
+  merged.extend(right);
+
+  Ok(merged)
+}
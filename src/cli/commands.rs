@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use mew_lib::interpreter::Engine;
 
 #[derive(Parser)]
 #[command(name = "mew")]
@@ -7,6 +8,10 @@ pub struct Cli {
   #[arg(value_name = "FILE")]
   pub file_path: Option<String>,
 
+  /// How to run code
+  #[arg(long, value_enum, global = true, default_value_t = EngineName::Tree)]
+  pub engine: EngineName,
+
   #[command(subcommand)]
   pub command: Option<Commands>,
 }
@@ -29,3 +34,20 @@ pub enum Commands {
   /// Run the start script defined in mew.toml
   Start,
 }
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum EngineName {
+  /// Walk the syntax tree
+  Tree,
+  /// Compile to bytecode and run it on a VM (faster)
+  Vm,
+}
+
+impl From<EngineName> for Engine {
+  fn from(name: EngineName) -> Self {
+    match name {
+      EngineName::Tree => Engine::Tree,
+      EngineName::Vm => Engine::Vm,
+    }
+  }
+}
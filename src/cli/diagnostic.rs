@@ -0,0 +1,252 @@
+use crate::cli::traceback::display_path;
+use mew_lib::error::{Diagnostic, MewError, Span};
+use std::env;
+use std::io::Write;
+use std::path::Path;
+
+const ERROR_COLOR: &str = "\x1b[1;31m";
+const NOTE_COLOR: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// Spans longer than this many lines only show their first and last lines
+const MAX_SPAN_LINES: usize = 6;
+
+/// Print an error the way a compiler would: what went wrong, where, the
+/// source around it with the spot underlined and a hint on how to fix it.
+/// `source` is the code the error points into, if it is at hand. Colors are
+/// only used when `terminal` says `out` is one.
+pub fn print_error(
+  out: &mut dyn Write,
+  terminal: bool,
+  error: &MewError,
+  source: Option<&str>,
+  file: Option<&Path>,
+) {
+  let report = Report {
+    out,
+    source,
+    file,
+    color: terminal && env::var_os("NO_COLOR").is_none(),
+  };
+  report.print(error);
+}
+
+struct Report<'a> {
+  out: &'a mut dyn Write,
+  source: Option<&'a str>,
+  file: Option<&'a Path>,
+  color: bool,
+}
+
+// A span to underline, with `^` for the error itself and `-` for labels
+struct Mark<'a> {
+  span: Span,
+  message: Option<&'a str>,
+  primary: bool,
+}
+
+impl Report<'_> {
+  fn print(mut self, error: &MewError) {
+    if let MewError::Parse(diagnostics) = error {
+      for (i, diagnostic) in diagnostics.iter().enumerate() {
+        if i > 0 {
+          let _ = writeln!(self.out);
+        }
+        self.diagnostic("Syntax error", diagnostic);
+      }
+
+      if diagnostics.len() > 1 {
+        let summary = format!("hiss! Found {} syntax errors", diagnostics.len());
+        let _ = writeln!(self.out, "\n{}", self.paint(ERROR_COLOR, &summary));
+      }
+      return;
+    }
+
+    // Stopping on request isn't a mistake in the code, so there is nothing
+    // to point at
+    if let MewError::Interrupted = error {
+      let _ = writeln!(self.out, "{}", self.paint(ERROR_COLOR, "hiss! interrupted"));
+      return;
+    }
+
+    match error.span() {
+      Some(span) if span.start.line > 0 => {
+        let diagnostic = Diagnostic::new(error.message(), span);
+        self.diagnostic(title(error), &diagnostic);
+      }
+      _ => {
+        let _ = writeln!(
+          self.out,
+          "{}",
+          self.paint(ERROR_COLOR, &format!("hiss! Error: {}", error))
+        );
+      }
+    }
+  }
+
+  fn diagnostic(&mut self, title: &str, diagnostic: &Diagnostic) {
+    let heading = self.paint(ERROR_COLOR, &format!("hiss! {}", title));
+    let message = self.paint(BOLD, &format!(": {}", diagnostic.message));
+    let _ = writeln!(self.out, "{}{}", heading, message);
+
+    let lines: Vec<&str> = self.source.unwrap_or_default().lines().collect();
+    let start = diagnostic.span.start;
+
+    let mut marks = vec![Mark {
+      span: diagnostic.span,
+      message: None,
+      primary: true,
+    }];
+    marks.extend(diagnostic.labels.iter().map(|label| Mark {
+      span: label.span,
+      message: Some(label.message.as_str()),
+      primary: false,
+    }));
+    marks.retain(|mark| mark.span.start.line > 0 && mark.span.start.line <= lines.len());
+
+    // Lines to show, in order, with long spans cut down to their ends
+    let mut shown: Vec<usize> = Vec::new();
+    for mark in &marks {
+      let (first, last) = line_range(mark.span, lines.len());
+      for line in first..=last {
+        if last - first < MAX_SPAN_LINES || line < first + 2 || line > last - 2 {
+          shown.push(line);
+        }
+      }
+    }
+    shown.sort_unstable();
+    shown.dedup();
+
+    // A single line between two shown ones is shown rather than elided
+    let gaps: Vec<usize> = shown
+      .windows(2)
+      .filter(|pair| pair[1] == pair[0] + 2)
+      .map(|pair| pair[0] + 1)
+      .collect();
+    shown.extend(gaps);
+    shown.sort_unstable();
+
+    let width = shown.last().map_or(1, |line| line.to_string().len());
+    let gutter = self.paint(NOTE_COLOR, &format!("{} |", " ".repeat(width)));
+
+    let file = self.file.map_or("<input>".to_string(), display_path);
+    if start.line > 0 {
+      let arrow = self.paint(NOTE_COLOR, &format!("{}-->", " ".repeat(width)));
+      let _ = writeln!(
+        self.out,
+        "{} {}:{}:{}",
+        arrow, file, start.line, start.column
+      );
+    }
+
+    if !shown.is_empty() {
+      let _ = writeln!(self.out, "{}", gutter);
+    }
+
+    let mut previous = None;
+    for &line in &shown {
+      if previous.is_some_and(|previous| line > previous + 1) {
+        let _ = writeln!(self.out, "{}", self.paint(NOTE_COLOR, "..."));
+      }
+      previous = Some(line);
+
+      let text = lines[line - 1].trim_end();
+      let number = self.paint(NOTE_COLOR, &format!("{:>width$} |", line, width = width));
+      let _ = writeln!(self.out, "{} {}", number, text);
+
+      for mark in &marks {
+        if let Some(underline) = self.underline(mark, line, text, lines.len()) {
+          let _ = writeln!(self.out, "{} {}", gutter, underline);
+        }
+      }
+    }
+
+    if let Some(help) = &diagnostic.help {
+      if !shown.is_empty() {
+        let _ = writeln!(self.out, "{}", gutter);
+      }
+      let equals = self.paint(NOTE_COLOR, &format!("{} =", " ".repeat(width)));
+      let _ = writeln!(
+        self.out,
+        "{} {}: {}",
+        equals,
+        self.paint(BOLD, "help"),
+        help
+      );
+    }
+  }
+
+  // The marker line under `line` for the part of the mark's span on it, with
+  // the mark's message after the span's last line
+  fn underline(&self, mark: &Mark, line: usize, text: &str, count: usize) -> Option<String> {
+    let (first, last) = line_range(mark.span, count);
+    if line < first || line > last {
+      return None;
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let from = if line == mark.span.start.line {
+      mark.span.start.column.max(1)
+    } else {
+      chars.iter().take_while(|c| c.is_whitespace()).count() + 1
+    };
+    let to = if line == mark.span.end.line {
+      mark.span.end.column
+    } else {
+      chars.len() + 1
+    };
+
+    // Keep tabs so the marker lines up with the text above it
+    let indent: String = (1..from)
+      .map(|column| match chars.get(column - 1) {
+        Some('\t') => '\t',
+        _ => ' ',
+      })
+      .collect();
+    let symbol = if mark.primary { "^" } else { "-" };
+    let mut marker = symbol.repeat(to.saturating_sub(from).max(1));
+    if line == last {
+      if let Some(message) = mark.message {
+        marker = format!("{} {}", marker, message);
+      }
+    }
+
+    let color = if mark.primary {
+      ERROR_COLOR
+    } else {
+      NOTE_COLOR
+    };
+    Some(format!("{}{}", indent, self.paint(color, &marker)))
+  }
+
+  fn paint(&self, color: &str, text: &str) -> String {
+    if self.color {
+      format!("{}{}{}", color, text, RESET)
+    } else {
+      text.to_string()
+    }
+  }
+}
+
+// First and last line a span covers. A span ending at the very start of a
+// line doesn't cover that line.
+fn line_range(span: Span, count: usize) -> (usize, usize) {
+  let first = span.start.line;
+  let mut last = span.end.line.max(first);
+  if last > first && span.end.column <= 1 {
+    last -= 1;
+  }
+  (first, last.min(count))
+}
+
+fn title(error: &MewError) -> &'static str {
+  match error {
+    MewError::Syntax(..) | MewError::Parse(_) => "Syntax error",
+    MewError::Runtime(..) => "Runtime error",
+    MewError::Type(..) => "Type error",
+    MewError::Name(..) => "Name error",
+    MewError::Thrown(..) => "Uncaught hairball",
+    _ => "Error",
+  }
+}
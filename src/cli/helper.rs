@@ -0,0 +1,339 @@
+use mew_lib::array_methods;
+use mew_lib::interpreter::Interpreter;
+use mew_lib::lexer::{MewLexer, TokenKind};
+use mew_lib::parser::Parser;
+use mew_lib::string_methods;
+use mew_lib::value::Value;
+use rustyline::completion::Completer;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::{Hinter, HistoryHinter};
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+use std::borrow::Cow::{self, Borrowed, Owned};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+const KEYWORDS: &[&str] = &[
+  "catst",
+  "catlt",
+  "catv",
+  "meow?",
+  "meowse?",
+  "hiss",
+  "fur",
+  "mewhile",
+  "mewdo",
+  "clawt",
+  "meownext",
+  "catwalk",
+  "claw",
+  "default",
+  "cat",
+  "in",
+  "of",
+  "return",
+  "purr",
+  "pub",
+  "import",
+  "from",
+  "purrhaps",
+  "catch",
+  "furever",
+  "hairball",
+  "breed",
+  "static",
+  "self",
+  "super",
+  "breedof",
+  "null",
+  "undefined",
+  "NaN",
+  "Infinity",
+  "true",
+  "false",
+];
+
+// Built-in objects, highlighted differently from user variables
+const BUILTIN_OBJECTS: &[&str] = &["Mewth", "CatTime", "MewJ", "Object"];
+
+const KEYWORD_COLOR: &str = "\x1b[35m";
+const STRING_COLOR: &str = "\x1b[32m";
+const NUMBER_COLOR: &str = "\x1b[33m";
+const CONSTANT_COLOR: &str = "\x1b[36m";
+const BUILTIN_COLOR: &str = "\x1b[34m";
+const HINT_COLOR: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Line editor support for the REPL: highlighting, completion of keywords
+/// and live variables, and hints from history
+pub struct MewHelper {
+  interpreter: Rc<RefCell<Interpreter>>,
+  hinter: HistoryHinter,
+  // Whether Enter last continued an unfinished input on a new line
+  continuing: Cell<bool>,
+}
+
+impl MewHelper {
+  pub fn new(interpreter: Rc<RefCell<Interpreter>>) -> Self {
+    Self {
+      interpreter,
+      hinter: HistoryHinter::new(),
+      continuing: Cell::new(false),
+    }
+  }
+
+  /// Whether `source` stops partway through a statement, like an open
+  /// block, bracket or string, so the REPL should keep reading lines before
+  /// running it
+  fn is_incomplete(&self, source: &str) -> bool {
+    let result = MewLexer::new(source)
+      .scan_tokens()
+      .and_then(|tokens| Parser::new(tokens).parse());
+    result.is_err_and(|error| error.is_incomplete())
+  }
+
+  /// Whether the last input was left unfinished, so Ctrl+C should discard it
+  /// rather than leave the REPL. Reading it resets it.
+  pub fn take_continuing(&self) -> bool {
+    self.continuing.replace(false)
+  }
+
+  // Everything that can follow `path.`, where path is a chain of names like
+  // `Mewth` or `tom.owner`
+  fn member_names(&self, path: &str) -> Vec<String> {
+    let Ok(interpreter) = self.interpreter.try_borrow() else {
+      return Vec::new();
+    };
+
+    let mut names = path.split('.').map(|name| name.trim_end_matches('?'));
+    let Some(mut value) = names.next().and_then(|name| interpreter.get_global(name)) else {
+      return Vec::new();
+    };
+
+    for name in names {
+      let property = match &value {
+        Value::Object(obj) => obj.borrow().get(name).cloned(),
+        _ => None,
+      };
+      match property {
+        Some(property) => value = property,
+        None => return Vec::new(),
+      }
+    }
+
+    match &value {
+      Value::String(_) => with_length(string_methods::METHODS),
+      Value::Array(_) => with_length(array_methods::METHODS),
+      Value::Object(obj) => {
+        let obj = obj.borrow();
+        let mut names: Vec<String> = obj.keys().cloned().collect();
+
+        // Instances also offer the methods of their breed and its parents,
+        // breeds the static members of their parents and their own
+        let (mut breed, is_instance) = match obj.instance_of() {
+          Some(breed) => (Some(breed.clone()), true),
+          None => (Some(value.clone()), false),
+        };
+        while let Some(Value::Object(current)) = &breed {
+          let parent = {
+            let current = current.borrow();
+            let Some(info) = current.breed() else { break };
+            if is_instance {
+              names.extend(info.methods.keys().cloned());
+            } else {
+              names.extend(current.keys().cloned());
+              names.extend(info.statics.keys().cloned());
+            }
+            info.parent.clone()
+          };
+          breed = parent;
+        }
+
+        names
+      }
+      _ => Vec::new(),
+    }
+  }
+}
+
+fn with_length<T>(methods: &[(&str, T)]) -> Vec<String> {
+  methods
+    .iter()
+    .map(|(name, _)| name.to_string())
+    .chain(std::iter::once("length".to_string()))
+    .collect()
+}
+
+fn is_word_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn token_color(kind: &TokenKind) -> Option<&'static str> {
+  match kind {
+    TokenKind::String(_) => Some(STRING_COLOR),
+    TokenKind::Number(_) => Some(NUMBER_COLOR),
+    TokenKind::Boolean(_)
+    | TokenKind::Null
+    | TokenKind::Undefined
+    | TokenKind::NaN
+    | TokenKind::Infinity => Some(CONSTANT_COLOR),
+    TokenKind::Identifier(name) if BUILTIN_OBJECTS.contains(&name.as_str()) => Some(BUILTIN_COLOR),
+    TokenKind::Const
+    | TokenKind::Let
+    | TokenKind::Var
+    | TokenKind::If
+    | TokenKind::ElseIf
+    | TokenKind::Else
+    | TokenKind::For
+    | TokenKind::While
+    | TokenKind::Do
+    | TokenKind::Break
+    | TokenKind::Continue
+    | TokenKind::Switch
+    | TokenKind::Case
+    | TokenKind::Default
+    | TokenKind::Function
+    | TokenKind::In
+    | TokenKind::Of
+    | TokenKind::Return
+    | TokenKind::Print
+    | TokenKind::Public
+    | TokenKind::Import
+    | TokenKind::From
+    | TokenKind::Try
+    | TokenKind::Catch
+    | TokenKind::Finally
+    | TokenKind::Throw
+    | TokenKind::Class
+    | TokenKind::Static
+    | TokenKind::SelfKw
+    | TokenKind::Super
+    | TokenKind::InstanceOf => Some(KEYWORD_COLOR),
+    _ => None,
+  }
+}
+
+impl Completer for MewHelper {
+  type Candidate = String;
+
+  fn complete(
+    &self,
+    line: &str,
+    pos: usize,
+    _ctx: &Context<'_>,
+  ) -> rustyline::Result<(usize, Vec<String>)> {
+    let before = &line[..pos];
+    let start = before
+      .char_indices()
+      .rev()
+      .take_while(|(_, c)| is_word_char(*c))
+      .last()
+      .map_or(pos, |(i, _)| i);
+    let word = &before[start..];
+
+    let candidates = match before[..start].strip_suffix('.') {
+      // A member of whatever precedes the dot
+      Some(object) => {
+        let path_start = object
+          .char_indices()
+          .rev()
+          .take_while(|(_, c)| is_word_char(*c) || *c == '.' || *c == '?')
+          .last()
+          .map_or(object.len(), |(i, _)| i);
+        self.member_names(&object[path_start..])
+      }
+      None => {
+        let mut names: Vec<String> = KEYWORDS.iter().map(|k| k.to_string()).collect();
+        if let Ok(interpreter) = self.interpreter.try_borrow() {
+          names.extend(interpreter.global_names());
+        }
+        names
+      }
+    };
+
+    let mut matches: Vec<String> = candidates
+      .into_iter()
+      .filter(|candidate| candidate.starts_with(word))
+      .collect();
+    matches.sort();
+    matches.dedup();
+
+    Ok((start, matches))
+  }
+}
+
+impl Hinter for MewHelper {
+  type Hint = String;
+
+  fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<String> {
+    self.hinter.hint(line, pos, ctx)
+  }
+}
+
+impl Highlighter for MewHelper {
+  fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+    // Half-typed input like an open string is shown as is
+    let Ok(tokens) = MewLexer::new(line).scan_tokens() else {
+      return Borrowed(line);
+    };
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut line_starts = vec![0];
+    for (i, c) in chars.iter().enumerate() {
+      if *c == '\n' {
+        line_starts.push(i + 1);
+      }
+    }
+
+    let mut highlighted = String::with_capacity(line.len() * 2);
+    let mut copied = 0;
+
+    for token in &tokens {
+      let Some(color) = token_color(&token.kind) else {
+        continue;
+      };
+      let Some(line_start) = line_starts.get(token.location.line.wrapping_sub(1)) else {
+        continue;
+      };
+
+      let start = line_start + token.location.column.saturating_sub(1);
+      let end = start + token.lexeme.chars().count();
+      if start < copied || end > chars.len() {
+        continue;
+      }
+
+      highlighted.extend(&chars[copied..start]);
+      highlighted.push_str(color);
+      highlighted.extend(&chars[start..end]);
+      highlighted.push_str(RESET);
+      copied = end;
+    }
+
+    highlighted.extend(&chars[copied..]);
+    Owned(highlighted)
+  }
+
+  fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+    Owned(format!("{}{}{}", HINT_COLOR, hint, RESET))
+  }
+
+  fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+    true
+  }
+}
+
+impl Validator for MewHelper {
+  // Enter on an unfinished input starts a new line instead of running it
+  fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+    let input = ctx.input();
+    let incomplete = !input.trim_start().starts_with(':') && self.is_incomplete(input);
+    self.continuing.set(incomplete);
+    if incomplete {
+      Ok(ValidationResult::Incomplete)
+    } else {
+      Ok(ValidationResult::Valid(None))
+    }
+  }
+}
+
+impl Helper for MewHelper {}
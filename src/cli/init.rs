@@ -2,7 +2,7 @@ use std::fs;
 use std::io::{self, Write};
 use std::path::Path;
 
-use crate::error::MewResult;
+use mew_lib::error::MewResult;
 
 pub fn handle_init(provided_name: Option<String>) -> MewResult<()> {
   let project_name = if let Some(name) = provided_name {
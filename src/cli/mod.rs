@@ -1,13 +1,18 @@
 mod commands;
+mod diagnostic;
+mod helper;
 mod init;
+mod repl;
 mod run;
 mod start;
+mod traceback;
 mod upgrade;
 mod version;
 
 pub use commands::{Cli, Commands};
 pub use init::handle_init;
-pub use run::{run_file, run_repl};
+pub use repl::run_repl;
+pub use run::run_file;
 pub use start::handle_start;
 pub use upgrade::handle_upgrade;
 pub use version::handle_version;
@@ -0,0 +1,313 @@
+use crate::cli::diagnostic::print_error;
+use crate::cli::helper::MewHelper;
+use crate::cli::traceback::print_traceback;
+use mew_lib::error::{MewError, MewResult};
+use mew_lib::interpreter::{Engine, Interpreter, InterruptHandle};
+use mew_lib::lexer::MewLexer;
+use mew_lib::parser::Parser;
+use mew_lib::value::Value;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Instant;
+
+const EXIT_COMMANDS: [&str; 4] = ["exit", "quit", "bye", "q"];
+const PROMPT: &str = "🐾 > ";
+const HISTORY_FILE: &str = ".mew_history";
+
+// Holds the value of the last input
+const LAST_RESULT: &str = "_";
+
+const HELP: &str = "\
+Commands:
+  :load <file>    Run a file in this session
+  :save <file>    Save the code entered so far to a file
+  :env            List your variables and their types
+  :type <expr>    Show the type of an expression
+  :ast <code>     Show the syntax tree of some code
+  :tokens <code>  Show the tokens of some code
+  :time <code>    Run some code and show how long it took
+  :reset          Forget all variables and start over
+  :help           Show this help
+
+The value of the last input is available as `_`.";
+
+/// State of one REPL session
+struct Session {
+  // Shared with the line editor so it can complete variable names
+  interpreter: Rc<RefCell<Interpreter>>,
+  // Inputs that ran successfully, for `:save`
+  transcript: Vec<String>,
+  // Globals every interpreter starts with, left out of `:env`
+  builtins: HashSet<String>,
+  // Wired to Ctrl+C, and kept when the session is reset
+  interrupt: InterruptHandle,
+  engine: Engine,
+}
+
+pub fn run_repl(engine: Engine) -> MewResult<()> {
+  println!("🐱 Mew Programming Language v{}", env!("CARGO_PKG_VERSION"));
+  println!("\nType 'exit' or press Ctrl+C to exit, ':help' for commands");
+
+  // One interpreter for the whole session, so definitions carry over
+  // between inputs without running earlier inputs again
+  let mut interpreter = Interpreter::new();
+  interpreter.set_engine(engine);
+  let mut session = Session {
+    builtins: interpreter.global_names().into_iter().collect(),
+    interrupt: interpreter.interrupt_handle(),
+    interpreter: Rc::new(RefCell::new(interpreter)),
+    transcript: Vec::new(),
+    engine,
+  };
+
+  // The line editor reads Ctrl+C at the prompt itself, so the signal only
+  // arrives while code is running and stops just that code
+  let interrupt = session.interrupt.clone();
+  if let Err(err) = ctrlc::set_handler(move || interrupt.interrupt()) {
+    eprintln!("hiss! Ctrl+C won't stop running code: {}", err);
+  }
+
+  let mut rl: Editor<MewHelper, DefaultHistory> = Editor::new().unwrap();
+  rl.set_helper(Some(MewHelper::new(session.interpreter.clone())));
+
+  let history = history_path();
+  if let Some(path) = &history {
+    // There is no history file before the first session
+    let _ = rl.load_history(path);
+  }
+
+  // The helper's validator keeps reading lines while the input is
+  // unfinished, so each read is a whole input
+  loop {
+    match rl.readline(PROMPT) {
+      Ok(line) => {
+        let input = line.trim();
+        if EXIT_COMMANDS.contains(&input) {
+          println!("Goodbye!");
+          break;
+        }
+
+        if input.is_empty() {
+          continue;
+        }
+
+        if let Some(command) = input.strip_prefix(':') {
+          let _ = rl.add_history_entry(input);
+          session.run_command(command);
+          continue;
+        }
+
+        let _ = rl.add_history_entry(line.trim_end());
+        session.evaluate(&line);
+      }
+      // Ctrl+C abandons a partly typed input instead of leaving the REPL
+      Err(ReadlineError::Interrupted)
+        if rl.helper().is_some_and(|helper| helper.take_continuing()) => {}
+      Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+        println!("Goodbye!");
+        break;
+      }
+      Err(err) => {
+        eprintln!("hiss! Error: {}", err);
+        break;
+      }
+    }
+  }
+
+  if let Some(path) = &history {
+    if let Err(err) = rl.save_history(path) {
+      eprintln!("hiss! Could not save history: {}", err);
+    }
+  }
+
+  Ok(())
+}
+
+// History is kept in the user's home directory, if there is one
+fn history_path() -> Option<PathBuf> {
+  env::var_os("HOME")
+    .or_else(|| env::var_os("USERPROFILE"))
+    .map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+impl Session {
+  // Run one input and echo its value, or report the error it raised
+  fn evaluate(&mut self, source: &str) {
+    let mut interpreter = self.interpreter.borrow_mut();
+    match interpreter.eval(source) {
+      Ok(value) => {
+        // Semicolons are optional at the end of an input but not between
+        // the statements of a saved file
+        let mut statement = source.trim_end().to_string();
+        if !statement.ends_with([';', '}']) {
+          statement.push(';');
+        }
+        self.transcript.push(statement);
+        if !matches!(value, Value::Undefined) {
+          let _ = writeln!(interpreter.stdout(), "{}", value);
+          interpreter.set_global(LAST_RESULT, value);
+        }
+      }
+      Err(e) => report_error(&mut interpreter, source, &e),
+    }
+  }
+
+  // Run a meta-command, given without its leading colon
+  fn run_command(&mut self, command: &str) {
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+      Some((name, argument)) => (name, argument.trim()),
+      None => (command, ""),
+    };
+
+    let parameter = match name {
+      "load" | "save" => Some("<file>"),
+      "type" => Some("<expr>"),
+      "ast" | "tokens" | "time" => Some("<code>"),
+      _ => None,
+    };
+    if let (Some(parameter), true) = (parameter, argument.is_empty()) {
+      self.complain(&format!("Usage: :{} {}", name, parameter));
+      return;
+    }
+
+    let result = match name {
+      "load" => self.load(argument),
+      "save" => self.save(argument),
+      "env" => self.env(),
+      "type" => self.type_of(argument),
+      "ast" => self.ast(argument),
+      "tokens" => self.tokens(argument),
+      "time" => self.time(argument),
+      "reset" => self.reset(),
+      "help" => self.print(HELP),
+      _ => {
+        self.complain(&format!(
+          "Unknown command ':{}', type ':help' for a list of commands",
+          name
+        ));
+        Ok(())
+      }
+    };
+
+    if let Err(e) = result {
+      report_error(&mut self.interpreter.borrow_mut(), argument, &e);
+    }
+  }
+
+  fn print(&self, text: &str) -> MewResult<()> {
+    writeln!(self.interpreter.borrow_mut().stdout(), "{}", text)?;
+    Ok(())
+  }
+
+  fn complain(&self, text: &str) {
+    let _ = writeln!(self.interpreter.borrow_mut().stderr(), "hiss! {}", text);
+  }
+
+  fn load(&mut self, path: &str) -> MewResult<()> {
+    let source = fs::read_to_string(path)?;
+    self.evaluate(&source);
+    Ok(())
+  }
+
+  fn save(&self, path: &str) -> MewResult<()> {
+    let mut source = self.transcript.join("\n");
+    source.push('\n');
+    fs::write(path, source)?;
+    self.print(&format!(
+      "Saved {} input(s) to {}",
+      self.transcript.len(),
+      path
+    ))
+  }
+
+  fn env(&self) -> MewResult<()> {
+    let mut interpreter = self.interpreter.borrow_mut();
+
+    let bindings: Vec<(String, Value)> = interpreter
+      .global_names()
+      .into_iter()
+      .filter(|name| !self.builtins.contains(name))
+      .filter_map(|name| interpreter.get_global(&name).map(|value| (name, value)))
+      .collect();
+
+    let out = interpreter.stdout();
+    if bindings.is_empty() {
+      writeln!(out, "No variables defined yet")?;
+    }
+    for (name, value) in bindings {
+      writeln!(out, "{}: {} = {}", name, value.type_name(), value)?;
+    }
+    Ok(())
+  }
+
+  fn type_of(&self, expression: &str) -> MewResult<()> {
+    let mut interpreter = self.interpreter.borrow_mut();
+    let value = interpreter.eval(expression)?;
+    writeln!(interpreter.stdout(), "{}", value.type_name())?;
+    Ok(())
+  }
+
+  fn ast(&self, source: &str) -> MewResult<()> {
+    let tokens = MewLexer::new(source).scan_tokens()?;
+    let statements = Parser::new(tokens).parse()?;
+
+    let mut interpreter = self.interpreter.borrow_mut();
+    for statement in statements {
+      writeln!(interpreter.stdout(), "{:#?}", statement.borrow())?;
+    }
+    Ok(())
+  }
+
+  fn tokens(&self, source: &str) -> MewResult<()> {
+    let tokens = MewLexer::new(source).scan_tokens()?;
+
+    let mut interpreter = self.interpreter.borrow_mut();
+    for token in tokens {
+      writeln!(
+        interpreter.stdout(),
+        "{}:{}\t{:?}\t{:?}",
+        token.location.line,
+        token.location.column,
+        token.kind,
+        token.lexeme
+      )?;
+    }
+    Ok(())
+  }
+
+  fn time(&mut self, source: &str) -> MewResult<()> {
+    let start = Instant::now();
+    self.evaluate(source);
+    self.print(&format!("Took {:.3?}", start.elapsed()))
+  }
+
+  fn reset(&mut self) -> MewResult<()> {
+    // Replaced in place, since the line editor holds the same interpreter
+    let mut interpreter = Interpreter::new();
+    interpreter.set_interrupt_handle(self.interrupt.clone());
+    interpreter.set_engine(self.engine);
+    *self.interpreter.borrow_mut() = interpreter;
+    self.transcript.clear();
+    self.print("Session reset")
+  }
+}
+
+fn report_error(interpreter: &mut Interpreter, source: &str, error: &MewError) {
+  let traceback = interpreter.traceback().to_vec();
+  let terminal = interpreter.stderr_is_terminal();
+  let stderr = interpreter.stderr();
+
+  // Errors inside a function may point into an earlier input, so those only
+  // get the traceback
+  let source = Some(source).filter(|_| traceback.len() <= 1);
+  print_error(stderr, terminal, error, source, None);
+
+  print_traceback(stderr, &traceback);
+}
@@ -2,14 +2,15 @@ use std::fs;
 use std::path::Path;
 
 use crate::cli::run_file;
-use crate::error::{MewError, MewResult};
+use mew_lib::error::{MewError, MewResult};
+use mew_lib::interpreter::Engine;
 
-pub fn handle_start() -> MewResult<()> {
+pub fn handle_start(engine: Engine) -> MewResult<()> {
   let config_path = Path::new("mew.toml");
   if !config_path.exists() {
-    return Err(MewError::runtime(format!(
-      "Could not find mew.toml in current directory"
-    )));
+    return Err(MewError::runtime(
+      "Could not find mew.toml in current directory",
+    ));
   }
 
   let config_content = fs::read_to_string(config_path)?;
@@ -17,7 +18,7 @@ pub fn handle_start() -> MewResult<()> {
   let start_path = extract_start_path(&config_content)
     .ok_or_else(|| MewError::runtime("Start path not defined in mew.toml"))?;
 
-  run_file(&start_path)
+  run_file(&start_path, engine)
 }
 
 fn extract_start_path(toml_content: &str) -> Option<String> {
@@ -0,0 +1,55 @@
+use mew_lib::error::StackFrame;
+use std::env;
+use std::io::Write;
+use std::path::Path;
+
+// Frames shown at each end of a long traceback, like one from runaway
+// recursion
+const EDGE_FRAMES: usize = 10;
+
+/// List the calls that led to an error, most recent last. A traceback with
+/// only the top level adds nothing to the error's own location, so it is
+/// left out.
+pub fn print_traceback(out: &mut dyn Write, frames: &[StackFrame]) {
+  if frames.len() < 2 {
+    return;
+  }
+
+  let _ = writeln!(
+    out,
+    "\n🐾 Pawprints leading to the error (most recent call last):"
+  );
+
+  let skipped = frames.len().saturating_sub(2 * EDGE_FRAMES);
+  for (i, frame) in frames.iter().enumerate() {
+    if skipped > 0 && i >= EDGE_FRAMES && i < EDGE_FRAMES + skipped {
+      if i == EDGE_FRAMES {
+        let _ = writeln!(
+          out,
+          "  ... {} more pawprints, the cat chased its tail ...",
+          skipped
+        );
+      }
+      continue;
+    }
+
+    let function = frame.function.as_deref().unwrap_or("<top level>");
+    let file = match &frame.file {
+      Some(file) => display_path(file),
+      None => "<input>".to_string(),
+    };
+    let _ = writeln!(
+      out,
+      "  in {} at {}:{}:{}",
+      function, file, frame.location.line, frame.location.column
+    );
+  }
+}
+
+// Paths under the working directory are shown relative to it
+pub fn display_path(path: &Path) -> String {
+  let relative = env::current_dir()
+    .ok()
+    .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf));
+  relative.as_deref().unwrap_or(path).display().to_string()
+}
@@ -13,7 +13,6 @@
 // You should have received a copy of the GNU General Public License
 // along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
 
-use reqwest;
 use semver::Version;
 use serde::Deserialize;
 use std::{process, time::Duration};
@@ -88,38 +87,24 @@ pub fn handle_upgrade(force: bool) -> Result<(), Box<dyn std::error::Error>> {
   }
 
   #[cfg(target_os = "windows")]
-  {
-    let mut command = process::Command::new("powershell");
-    command.arg("-c");
-    command.arg(format!("irm mewis.me/install.ps1 | iex"));
-    let output = command.status()?;
-
-    if !output.success() {
-      return Err(Box::new(std::io::Error::new(
-        std::io::ErrorKind::Other,
-        format!("Installation failed with exit code: {:?}", output.code()),
-      )));
-    }
-
-    return Ok(());
-  }
+  let (shell, script) = ("powershell", "irm mewis.me/install.ps1 | iex");
 
   #[cfg(not(target_os = "windows"))]
-  {
-    let mut command = process::Command::new("bash");
-    command.arg("-c");
-    command.arg(format!("curl -fsSL https://mewis.me/install.sh | bash"));
-    let output = command.status()?;
-
-    if !output.success() {
-      return Err(Box::new(std::io::Error::new(
-        std::io::ErrorKind::Other,
-        format!("Installation failed with exit code: {:?}", output.code()),
-      )));
-    }
-
-    return Ok(());
+  let (shell, script) = ("bash", "curl -fsSL https://mewis.me/install.sh | bash");
+
+  let output = process::Command::new(shell)
+    .arg("-c")
+    .arg(script)
+    .status()?;
+
+  if !output.success() {
+    return Err(Box::new(std::io::Error::other(format!(
+      "Installation failed with exit code: {:?}",
+      output.code()
+    ))));
   }
+
+  Ok(())
 }
 
 pub fn check_for_updates(
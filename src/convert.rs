@@ -0,0 +1,906 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversions between Rust types and Mew values using serde.
+//!
+//! [`to_value`] turns anything implementing `Serialize` into a [`Value`], and
+//! [`from_value`] deserializes a `Value` into any `Deserialize` type. `Value`
+//! itself also implements `Serialize` and `Deserialize`, so it can be read
+//! from or written to any serde format.
+//!
+//! Mew numbers are `f64`, so integers beyond ±2^53 are rejected instead of
+//! silently losing precision. Functions and circular structures cannot be
+//! converted.
+
+use crate::error::{MewError, MewResult};
+use crate::interpreter::{STACK_RED_ZONE, STACK_SEGMENT};
+use crate::value::Value;
+use serde::de::value::{MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{
+  self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+  VariantAccess, Visitor,
+};
+use serde::ser::{self, Serialize, SerializeMap as _, SerializeSeq as _};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer, Serializer};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+// Largest integer magnitude an f64 represents exactly
+const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+/// Convert any serializable Rust value into a Mew value
+pub fn to_value<T>(value: &T) -> MewResult<Value>
+where
+  T: Serialize + ?Sized,
+{
+  value.serialize(ValueSerializer)
+}
+
+/// Deserialize a Mew value into a Rust type
+pub fn from_value<T>(value: Value) -> MewResult<T>
+where
+  T: DeserializeOwned,
+{
+  T::deserialize(value)
+}
+
+impl ser::Error for MewError {
+  fn custom<T: fmt::Display>(msg: T) -> Self {
+    MewError::type_error(msg.to_string())
+  }
+}
+
+impl de::Error for MewError {
+  fn custom<T: fmt::Display>(msg: T) -> Self {
+    MewError::type_error(msg.to_string())
+  }
+}
+
+fn integer(n: i128) -> MewResult<Value> {
+  if n.unsigned_abs() > MAX_SAFE_INTEGER {
+    return Err(MewError::type_error(format!(
+      "Integer {} cannot be represented exactly as a Mew number",
+      n
+    )));
+  }
+
+  Ok(Value::Number(n as f64))
+}
+
+fn unsigned(n: u128) -> MewResult<Value> {
+  match i128::try_from(n) {
+    Ok(n) => integer(n),
+    Err(_) => integer(i128::MAX),
+  }
+}
+
+// Whether a number should be presented to serde as an integer
+fn as_integer(n: f64) -> Option<i64> {
+  if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER as f64 {
+    Some(n as i64)
+  } else {
+    None
+  }
+}
+
+// Serializing `Value`
+
+impl Serialize for Value {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    Guarded {
+      value: self,
+      seen: &RefCell::new(HashSet::new()),
+    }
+    .serialize(serializer)
+  }
+}
+
+// A value being serialized, along with the containers enclosing it so that
+// circular structures fail instead of recursing forever
+struct Guarded<'a> {
+  value: &'a Value,
+  seen: &'a RefCell<HashSet<*const ()>>,
+}
+
+impl Serialize for Guarded<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+      self.serialize_value(serializer)
+    })
+  }
+}
+
+impl Guarded<'_> {
+  fn enter<E: ser::Error>(&self, ptr: *const ()) -> Result<(), E> {
+    if !self.seen.borrow_mut().insert(ptr) {
+      return Err(E::custom("Cannot serialize a circular structure"));
+    }
+    Ok(())
+  }
+
+  fn serialize_value<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self.value {
+      Value::Null | Value::Undefined => serializer.serialize_unit(),
+      Value::Bool(b) => serializer.serialize_bool(*b),
+      Value::Number(n) => match as_integer(*n) {
+        Some(i) => serializer.serialize_i64(i),
+        None => serializer.serialize_f64(*n),
+      },
+      Value::String(s) => serializer.serialize_str(s),
+      Value::Array(items) => {
+        let ptr = Rc::as_ptr(items) as *const ();
+        self.enter(ptr)?;
+
+        let items = items.borrow();
+        let mut seq = serializer.serialize_seq(Some(items.len()))?;
+        for item in items.iter() {
+          seq.serialize_element(&Guarded {
+            value: item,
+            seen: self.seen,
+          })?;
+        }
+
+        self.seen.borrow_mut().remove(&ptr);
+        seq.end()
+      }
+      Value::Object(obj) => {
+        let ptr = Rc::as_ptr(obj) as *const ();
+        self.enter(ptr)?;
+
+        // Sorted so that output doesn't depend on hash order
+        let obj = obj.borrow();
+        let mut entries: Vec<_> = obj.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (key, value) in entries {
+          map.serialize_entry(
+            key,
+            &Guarded {
+              value,
+              seen: self.seen,
+            },
+          )?;
+        }
+
+        self.seen.borrow_mut().remove(&ptr);
+        map.end()
+      }
+      Value::Function(_) | Value::NativeFunction(_) | Value::Closure(_) => {
+        Err(ser::Error::custom("Functions cannot be serialized"))
+      }
+    }
+  }
+}
+
+// Deserializing `Value`
+
+impl<'de> Deserialize<'de> for Value {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+    ValueSeed { exact: true }.deserialize(deserializer)
+  }
+}
+
+/// Parse JSON text the way `JSON.parse` does, rounding integers to the
+/// nearest Mew number instead of rejecting those it can't hold exactly
+pub(crate) fn parse_json(json: &str) -> serde_json::Result<Value> {
+  let mut deserializer = serde_json::Deserializer::from_str(json);
+  let value = ValueSeed { exact: false }.deserialize(&mut deserializer)?;
+  deserializer.end()?;
+  Ok(value)
+}
+
+// Deserializes a `Value`. Unless `exact` is set, integers beyond ±2^53 are
+// rounded instead of rejected.
+#[derive(Clone, Copy)]
+struct ValueSeed {
+  exact: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for ValueSeed {
+  type Value = Value;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+    deserializer.deserialize_any(ValueVisitor { exact: self.exact })
+  }
+}
+
+struct ValueVisitor {
+  exact: bool,
+}
+
+impl ValueVisitor {
+  fn integer<E: de::Error>(&self, n: i128) -> Result<Value, E> {
+    if !self.exact {
+      return Ok(Value::Number(n as f64));
+    }
+    integer(n).map_err(|e| E::custom(e.message()))
+  }
+}
+
+impl<'de> Visitor<'de> for ValueVisitor {
+  type Value = Value;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a Mew value")
+  }
+
+  fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+    Ok(Value::Bool(b))
+  }
+
+  fn visit_i64<E: de::Error>(self, n: i64) -> Result<Value, E> {
+    self.integer(n as i128)
+  }
+
+  fn visit_i128<E: de::Error>(self, n: i128) -> Result<Value, E> {
+    self.integer(n)
+  }
+
+  fn visit_u64<E: de::Error>(self, n: u64) -> Result<Value, E> {
+    self.integer(n as i128)
+  }
+
+  fn visit_u128<E: de::Error>(self, n: u128) -> Result<Value, E> {
+    match i128::try_from(n) {
+      Ok(n) => self.integer(n),
+      Err(_) if !self.exact => Ok(Value::Number(n as f64)),
+      Err(_) => unsigned(n).map_err(|e| E::custom(e.message())),
+    }
+  }
+
+  fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+    Ok(Value::Number(n))
+  }
+
+  fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+    Ok(Value::String(s.to_string()))
+  }
+
+  fn visit_string<E>(self, s: String) -> Result<Value, E> {
+    Ok(Value::String(s))
+  }
+
+  fn visit_unit<E>(self) -> Result<Value, E> {
+    Ok(Value::Null)
+  }
+
+  fn visit_none<E>(self) -> Result<Value, E> {
+    Ok(Value::Null)
+  }
+
+  fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+    ValueSeed { exact: self.exact }.deserialize(deserializer)
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+    let seed = ValueSeed { exact: self.exact };
+    let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+    while let Some(item) = seq.next_element_seed(seed)? {
+      items.push(item);
+    }
+    Ok(Value::array(items))
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+    let seed = ValueSeed { exact: self.exact };
+    let mut properties = HashMap::new();
+    while let Some(key) = map.next_key::<String>()? {
+      properties.insert(key, map.next_value_seed(seed)?);
+    }
+    Ok(Value::object(properties))
+  }
+}
+
+// Rust to `Value`
+
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+  type Ok = Value;
+  type Error = MewError;
+
+  type SerializeSeq = SerializeArray;
+  type SerializeTuple = SerializeArray;
+  type SerializeTupleStruct = SerializeArray;
+  type SerializeTupleVariant = SerializeTupleVariant;
+  type SerializeMap = SerializeObject;
+  type SerializeStruct = SerializeObject;
+  type SerializeStructVariant = SerializeStructVariant;
+
+  fn serialize_bool(self, v: bool) -> MewResult<Value> {
+    Ok(Value::Bool(v))
+  }
+
+  fn serialize_i8(self, v: i8) -> MewResult<Value> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn serialize_i16(self, v: i16) -> MewResult<Value> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn serialize_i32(self, v: i32) -> MewResult<Value> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn serialize_i64(self, v: i64) -> MewResult<Value> {
+    integer(v as i128)
+  }
+
+  fn serialize_i128(self, v: i128) -> MewResult<Value> {
+    integer(v)
+  }
+
+  fn serialize_u8(self, v: u8) -> MewResult<Value> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn serialize_u16(self, v: u16) -> MewResult<Value> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn serialize_u32(self, v: u32) -> MewResult<Value> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn serialize_u64(self, v: u64) -> MewResult<Value> {
+    unsigned(v as u128)
+  }
+
+  fn serialize_u128(self, v: u128) -> MewResult<Value> {
+    unsigned(v)
+  }
+
+  fn serialize_f32(self, v: f32) -> MewResult<Value> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn serialize_f64(self, v: f64) -> MewResult<Value> {
+    Ok(Value::Number(v))
+  }
+
+  fn serialize_char(self, v: char) -> MewResult<Value> {
+    Ok(Value::String(v.to_string()))
+  }
+
+  fn serialize_str(self, v: &str) -> MewResult<Value> {
+    Ok(Value::String(v.to_string()))
+  }
+
+  fn serialize_bytes(self, v: &[u8]) -> MewResult<Value> {
+    Ok(Value::array(
+      v.iter().map(|b| Value::Number(*b as f64)).collect(),
+    ))
+  }
+
+  fn serialize_none(self) -> MewResult<Value> {
+    Ok(Value::Null)
+  }
+
+  fn serialize_some<T>(self, value: &T) -> MewResult<Value>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> MewResult<Value> {
+    Ok(Value::Null)
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> MewResult<Value> {
+    Ok(Value::Null)
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+  ) -> MewResult<Value> {
+    Ok(Value::String(variant.to_string()))
+  }
+
+  fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> MewResult<Value>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(self)
+  }
+
+  // Enum variants with data become `{ Variant: data }`
+  fn serialize_newtype_variant<T>(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+    value: &T,
+  ) -> MewResult<Value>
+  where
+    T: Serialize + ?Sized,
+  {
+    Ok(tagged(variant, to_value(value)?))
+  }
+
+  fn serialize_seq(self, len: Option<usize>) -> MewResult<SerializeArray> {
+    Ok(SerializeArray {
+      items: Vec::with_capacity(len.unwrap_or(0)),
+    })
+  }
+
+  fn serialize_tuple(self, len: usize) -> MewResult<SerializeArray> {
+    self.serialize_seq(Some(len))
+  }
+
+  fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> MewResult<SerializeArray> {
+    self.serialize_seq(Some(len))
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+    len: usize,
+  ) -> MewResult<SerializeTupleVariant> {
+    Ok(SerializeTupleVariant {
+      variant,
+      items: Vec::with_capacity(len),
+    })
+  }
+
+  fn serialize_map(self, _len: Option<usize>) -> MewResult<SerializeObject> {
+    Ok(SerializeObject {
+      properties: HashMap::new(),
+      next_key: None,
+    })
+  }
+
+  fn serialize_struct(self, _name: &'static str, len: usize) -> MewResult<SerializeObject> {
+    self.serialize_map(Some(len))
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+    _len: usize,
+  ) -> MewResult<SerializeStructVariant> {
+    Ok(SerializeStructVariant {
+      variant,
+      properties: HashMap::new(),
+    })
+  }
+}
+
+fn tagged(variant: &str, value: Value) -> Value {
+  let mut properties = HashMap::new();
+  properties.insert(variant.to_string(), value);
+  Value::object(properties)
+}
+
+struct SerializeArray {
+  items: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+  type Ok = Value;
+  type Error = MewError;
+
+  fn serialize_element<T>(&mut self, value: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    self.items.push(to_value(value)?);
+    Ok(())
+  }
+
+  fn end(self) -> MewResult<Value> {
+    Ok(Value::array(self.items))
+  }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+  type Ok = Value;
+  type Error = MewError;
+
+  fn serialize_element<T>(&mut self, value: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    ser::SerializeSeq::serialize_element(self, value)
+  }
+
+  fn end(self) -> MewResult<Value> {
+    ser::SerializeSeq::end(self)
+  }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+  type Ok = Value;
+  type Error = MewError;
+
+  fn serialize_field<T>(&mut self, value: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    ser::SerializeSeq::serialize_element(self, value)
+  }
+
+  fn end(self) -> MewResult<Value> {
+    ser::SerializeSeq::end(self)
+  }
+}
+
+struct SerializeTupleVariant {
+  variant: &'static str,
+  items: Vec<Value>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+  type Ok = Value;
+  type Error = MewError;
+
+  fn serialize_field<T>(&mut self, value: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    self.items.push(to_value(value)?);
+    Ok(())
+  }
+
+  fn end(self) -> MewResult<Value> {
+    Ok(tagged(self.variant, Value::array(self.items)))
+  }
+}
+
+struct SerializeObject {
+  properties: HashMap<String, Value>,
+  next_key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeObject {
+  type Ok = Value;
+  type Error = MewError;
+
+  fn serialize_key<T>(&mut self, key: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    let key = match to_value(key)? {
+      Value::String(ref mut s) => std::mem::take(s),
+      key @ (Value::Number(_) | Value::Bool(_)) => key.to_string(),
+      other => {
+        return Err(MewError::type_error(format!(
+          "Object keys must be strings, got {}",
+          other.type_name()
+        )))
+      }
+    };
+
+    self.next_key = Some(key);
+    Ok(())
+  }
+
+  fn serialize_value<T>(&mut self, value: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    let key = self
+      .next_key
+      .take()
+      .ok_or_else(|| MewError::type_error("serialize_value called before serialize_key"))?;
+
+    self.properties.insert(key, to_value(value)?);
+    Ok(())
+  }
+
+  fn end(self) -> MewResult<Value> {
+    Ok(Value::object(self.properties))
+  }
+}
+
+impl ser::SerializeStruct for SerializeObject {
+  type Ok = Value;
+  type Error = MewError;
+
+  fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    self.properties.insert(key.to_string(), to_value(value)?);
+    Ok(())
+  }
+
+  fn end(self) -> MewResult<Value> {
+    Ok(Value::object(self.properties))
+  }
+}
+
+struct SerializeStructVariant {
+  variant: &'static str,
+  properties: HashMap<String, Value>,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+  type Ok = Value;
+  type Error = MewError;
+
+  fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> MewResult<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    self.properties.insert(key.to_string(), to_value(value)?);
+    Ok(())
+  }
+
+  fn end(self) -> MewResult<Value> {
+    Ok(tagged(self.variant, Value::object(self.properties)))
+  }
+}
+
+// `Value` to Rust
+
+impl<'de> IntoDeserializer<'de, MewError> for Value {
+  type Deserializer = Value;
+
+  fn into_deserializer(self) -> Value {
+    self
+  }
+}
+
+impl<'de> Deserializer<'de> for Value {
+  type Error = MewError;
+
+  fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> MewResult<V::Value> {
+    Visiting::new(self).deserialize_any(visitor)
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> MewResult<V::Value> {
+    Visiting::new(self).deserialize_option(visitor)
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    visitor: V,
+  ) -> MewResult<V::Value> {
+    Visiting::new(self).deserialize_newtype_struct(name, visitor)
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    variants: &'static [&'static str],
+    visitor: V,
+  ) -> MewResult<V::Value> {
+    Visiting::new(self).deserialize_enum(name, variants, visitor)
+  }
+
+  forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+    identifier ignored_any
+  }
+}
+
+// A value being deserialized, along with the containers enclosing it so that
+// circular structures fail instead of being unrolled forever
+struct Visiting {
+  value: Value,
+  seen: Rc<RefCell<HashSet<*const ()>>>,
+}
+
+impl Visiting {
+  fn new(value: Value) -> Self {
+    Self {
+      value,
+      seen: Rc::new(RefCell::new(HashSet::new())),
+    }
+  }
+
+  fn child(&self, value: Value) -> Self {
+    Self {
+      value,
+      seen: self.seen.clone(),
+    }
+  }
+
+  // Run `visit` with the container at `ptr` marked as being visited
+  fn enter<T>(&self, ptr: *const (), visit: impl FnOnce() -> MewResult<T>) -> MewResult<T> {
+    if !self.seen.borrow_mut().insert(ptr) {
+      return Err(MewError::type_error(
+        "Cannot deserialize a circular structure",
+      ));
+    }
+
+    let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, visit);
+    self.seen.borrow_mut().remove(&ptr);
+    result
+  }
+}
+
+impl<'de> IntoDeserializer<'de, MewError> for Visiting {
+  type Deserializer = Visiting;
+
+  fn into_deserializer(self) -> Visiting {
+    self
+  }
+}
+
+impl<'de> Deserializer<'de> for Visiting {
+  type Error = MewError;
+
+  fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> MewResult<V::Value> {
+    match self.value {
+      Value::Null | Value::Undefined => visitor.visit_unit(),
+      Value::Bool(b) => visitor.visit_bool(b),
+      Value::Number(n) => match as_integer(n) {
+        Some(i) => visitor.visit_i64(i),
+        None => visitor.visit_f64(n),
+      },
+      Value::String(ref mut s) => visitor.visit_string(std::mem::take(s)),
+      Value::Array(ref items) => {
+        let ptr = Rc::as_ptr(items) as *const ();
+        let items: Vec<Visiting> = items
+          .borrow()
+          .iter()
+          .map(|item| self.child(item.clone()))
+          .collect();
+        let mut seq = SeqDeserializer::new(items.into_iter());
+
+        self.enter(ptr, || {
+          let value = visitor.visit_seq(&mut seq)?;
+          seq.end()?;
+          Ok(value)
+        })
+      }
+      Value::Object(ref obj) => {
+        let ptr = Rc::as_ptr(obj) as *const ();
+        let properties: Vec<(String, Visiting)> = obj
+          .borrow()
+          .iter()
+          .map(|(key, value)| (key.clone(), self.child(value.clone())))
+          .collect();
+        let mut map = MapDeserializer::new(properties.into_iter());
+
+        self.enter(ptr, || {
+          let value = visitor.visit_map(&mut map)?;
+          map.end()?;
+          Ok(value)
+        })
+      }
+      Value::Function(_) | Value::NativeFunction(_) | Value::Closure(_) => Err(
+        MewError::type_error("Functions cannot be converted to Rust values"),
+      ),
+    }
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> MewResult<V::Value> {
+    match self.value {
+      Value::Null | Value::Undefined => visitor.visit_none(),
+      _ => visitor.visit_some(self),
+    }
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> MewResult<V::Value> {
+    visitor.visit_newtype_struct(self)
+  }
+
+  // Enums are either a variant name or a `{ Variant: data }` object
+  fn deserialize_enum<V: Visitor<'de>>(
+    mut self,
+    name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> MewResult<V::Value> {
+    match self.value {
+      Value::String(ref mut variant) => {
+        let variant: StringDeserializer<MewError> = std::mem::take(variant).into_deserializer();
+        visitor.visit_enum(variant)
+      }
+      Value::Object(ref obj) => {
+        let ptr = Rc::as_ptr(obj) as *const ();
+        let mut entries = obj
+          .borrow()
+          .iter()
+          .map(|(key, value)| (key.clone(), value.clone()))
+          .collect::<Vec<_>>();
+
+        match entries.pop() {
+          Some((variant, value)) if entries.is_empty() => {
+            let value = self.child(value);
+            self.enter(ptr, || {
+              visitor.visit_enum(EnumDeserializer { variant, value })
+            })
+          }
+          _ => Err(MewError::type_error(format!(
+            "Expected an object with a single key for enum {}",
+            name
+          ))),
+        }
+      }
+      ref other => Err(MewError::type_error(format!(
+        "Expected a string or object for enum {}, got {}",
+        name,
+        other.type_name()
+      ))),
+    }
+  }
+
+  forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+    identifier ignored_any
+  }
+}
+
+struct EnumDeserializer {
+  variant: String,
+  value: Visiting,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+  type Error = MewError;
+  type Variant = VariantDeserializer;
+
+  fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> MewResult<(V::Value, Self::Variant)> {
+    let variant: StringDeserializer<MewError> = self.variant.into_deserializer();
+    let variant = seed.deserialize(variant)?;
+    Ok((variant, VariantDeserializer { value: self.value }))
+  }
+}
+
+struct VariantDeserializer {
+  value: Visiting,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+  type Error = MewError;
+
+  fn unit_variant(self) -> MewResult<()> {
+    match self.value.value {
+      Value::Null | Value::Undefined => Ok(()),
+      other => Err(MewError::type_error(format!(
+        "Expected no data for unit variant, got {}",
+        other.type_name()
+      ))),
+    }
+  }
+
+  fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> MewResult<T::Value> {
+    seed.deserialize(self.value)
+  }
+
+  fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> MewResult<V::Value> {
+    self.value.deserialize_seq(visitor)
+  }
+
+  fn struct_variant<V: Visitor<'de>>(
+    self,
+    _fields: &'static [&'static str],
+    visitor: V,
+  ) -> MewResult<V::Value> {
+    self.value.deserialize_map(visitor)
+  }
+}
@@ -13,22 +13,38 @@
 // You should have received a copy of the GNU General Public License
 // along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::value::Value;
 use std::fmt;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum MewError {
   #[error("Syntax error at {1}: {0}")]
-  Syntax(String, Location),
+  Syntax(String, Span),
 
   #[error("Runtime error at {1}: {0}")]
-  Runtime(String, Location),
+  Runtime(String, Span),
 
   #[error("Type error at {1}: {0}")]
-  Type(String, Location),
+  Type(String, Span),
 
   #[error("Name error at {1}: {0}")]
-  Name(String, Location),
+  Name(String, Span),
+
+  #[error("Uncaught hairball at {1}: {0}")]
+  Thrown(Value, Span),
+
+  /// Every syntax error the parser found in a file, in source order. There is
+  /// always at least one.
+  #[error("{}", describe_syntax_errors(.0))]
+  Parse(Vec<Diagnostic>),
+
+  #[error("interrupted")]
+  Interrupted,
+
+  #[error("Limit exceeded: {0}")]
+  LimitExceeded(Limit),
 
   #[error("IO error: {0}")]
   IO(#[from] std::io::Error),
@@ -36,53 +52,156 @@ pub enum MewError {
 
 impl MewError {
   pub fn syntax<T: Into<String>>(message: T) -> Self {
-    MewError::Syntax(message.into(), Location::unknown())
+    MewError::Syntax(message.into(), Span::unknown())
   }
 
   pub fn syntax_at<T: Into<String>>(message: T, location: Location) -> Self {
-    MewError::Syntax(message.into(), location)
+    MewError::Syntax(message.into(), Span::at(location))
   }
 
   pub fn runtime<T: Into<String>>(message: T) -> Self {
-    MewError::Runtime(message.into(), Location::unknown())
+    MewError::Runtime(message.into(), Span::unknown())
   }
 
   #[allow(dead_code)]
   pub fn runtime_at<T: Into<String>>(message: T, location: Location) -> Self {
-    MewError::Runtime(message.into(), location)
+    MewError::Runtime(message.into(), Span::at(location))
   }
 
   pub fn type_error<T: Into<String>>(message: T) -> Self {
-    MewError::Type(message.into(), Location::unknown())
+    MewError::Type(message.into(), Span::unknown())
   }
 
   #[allow(dead_code)]
   pub fn type_error_at<T: Into<String>>(message: T, location: Location) -> Self {
-    MewError::Type(message.into(), location)
+    MewError::Type(message.into(), Span::at(location))
   }
 
   pub fn name<T: Into<String>>(message: T) -> Self {
-    MewError::Name(message.into(), Location::unknown())
+    MewError::Name(message.into(), Span::unknown())
   }
 
   #[allow(dead_code)]
   pub fn name_at<T: Into<String>>(message: T, location: Location) -> Self {
-    MewError::Name(message.into(), location)
+    MewError::Name(message.into(), Span::at(location))
+  }
+
+  /// Give the error a span if it doesn't have one yet
+  pub fn at(self, span: Span) -> Self {
+    let unknown = Span::unknown();
+    match self {
+      MewError::Syntax(msg, old) if old == unknown => MewError::Syntax(msg, span),
+      MewError::Runtime(msg, old) if old == unknown => MewError::Runtime(msg, span),
+      MewError::Type(msg, old) if old == unknown => MewError::Type(msg, span),
+      MewError::Name(msg, old) if old == unknown => MewError::Name(msg, span),
+      MewError::Thrown(value, old) if old == unknown => MewError::Thrown(value, span),
+      error => error,
+    }
+  }
+
+  /// Name of the error kind as seen by a Mew `catch` block
+  pub fn kind_name(&self) -> &'static str {
+    match self {
+      MewError::Syntax(..) | MewError::Parse(_) => "SyntaxError",
+      MewError::Runtime(..) => "RuntimeError",
+      MewError::Type(..) => "TypeError",
+      MewError::Name(..) => "NameError",
+      MewError::Thrown(..) => "Hairball",
+      MewError::Interrupted => "Interrupted",
+      MewError::LimitExceeded(_) => "LimitExceeded",
+      MewError::IO(_) => "IOError",
+    }
+  }
+
+  /// The error message without the kind and location prefix
+  pub fn message(&self) -> String {
+    match self {
+      MewError::Syntax(msg, _)
+      | MewError::Runtime(msg, _)
+      | MewError::Type(msg, _)
+      | MewError::Name(msg, _) => msg.clone(),
+      MewError::Thrown(value, _) => value.to_string(),
+      MewError::Parse(diagnostics) => diagnostics
+        .first()
+        .map_or_else(|| "Invalid syntax".to_string(), |d| d.message.clone()),
+      MewError::Interrupted => "interrupted".to_string(),
+      MewError::LimitExceeded(limit) => limit.to_string(),
+      MewError::IO(err) => err.to_string(),
+    }
+  }
+
+  /// Whether the error only says the source stops too soon, as with an
+  /// open block or string, so that more input could complete it
+  pub fn is_incomplete(&self) -> bool {
+    match self {
+      MewError::Parse(diagnostics) => {
+        !diagnostics.is_empty() && diagnostics.iter().all(|d| d.at_eof)
+      }
+      _ => false,
+    }
   }
 
   pub fn location(&self) -> Option<Location> {
+    self.span().map(|span| span.start)
+  }
+
+  /// The part of the source the error points at. For a file with several
+  /// syntax errors, this is the first one.
+  pub fn span(&self) -> Option<Span> {
     match self {
-      MewError::Syntax(_, loc) => Some(*loc),
-      MewError::Runtime(_, loc) => Some(*loc),
-      MewError::Type(_, loc) => Some(*loc),
-      MewError::Name(_, loc) => Some(*loc),
-      MewError::IO(_) => None,
+      MewError::Syntax(_, span)
+      | MewError::Runtime(_, span)
+      | MewError::Type(_, span)
+      | MewError::Name(_, span)
+      | MewError::Thrown(_, span) => Some(*span),
+      MewError::Parse(diagnostics) => diagnostics.first().map(|d| d.span),
+      MewError::Interrupted | MewError::LimitExceeded(_) | MewError::IO(_) => None,
     }
   }
+
+  /// Whether a `catch` block may handle the error. Interrupts and exceeded
+  /// limits always stop the program.
+  pub fn is_catchable(&self) -> bool {
+    !matches!(self, MewError::Interrupted | MewError::LimitExceeded(_))
+  }
 }
 
 pub type MewResult<T> = Result<T, MewError>;
 
+fn describe_syntax_errors(diagnostics: &[Diagnostic]) -> String {
+  let Some(first) = diagnostics.first() else {
+    return "Syntax error".to_string();
+  };
+  match diagnostics.len() {
+    1 => format!("Syntax error at {}: {}", first.span, first.message),
+    n => format!(
+      "Syntax error at {}: {} (and {} more)",
+      first.span,
+      first.message,
+      n - 1
+    ),
+  }
+}
+
+/// A resource limit a program ran into, with the configured maximum
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+  #[error("more than {0} steps")]
+  Steps(u64),
+
+  #[error("more than {0} nested calls")]
+  CallDepth(usize),
+
+  #[error("ran past the deadline")]
+  Deadline,
+
+  #[error("more than {0} elements in a collection")]
+  CollectionSize(usize),
+
+  #[error("more than {0} characters in a string")]
+  StringLength(usize),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Location {
   pub line: usize,
@@ -108,3 +227,93 @@ impl fmt::Display for Location {
     }
   }
 }
+
+/// A call that was running when an error happened, and where it had got to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+  /// Name of the function, or `None` for the top level of a file or of
+  /// evaluated code
+  pub function: Option<String>,
+  /// `None` for code that didn't come from a file, like REPL input
+  pub file: Option<PathBuf>,
+  pub location: Location,
+}
+
+/// The part of the source a syntax node was parsed from, from the start of
+/// its first token to just past its last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+  pub start: Location,
+  pub end: Location,
+}
+
+impl Span {
+  pub fn new(start: Location, end: Location) -> Self {
+    Self { start, end }
+  }
+
+  pub fn unknown() -> Self {
+    Self::new(Location::unknown(), Location::unknown())
+  }
+
+  /// An empty span at a single point, for errors found before there was a
+  /// whole node to point at
+  pub fn at(location: Location) -> Self {
+    Self::new(location, location)
+  }
+}
+
+impl fmt::Display for Span {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.start.fmt(f)
+  }
+}
+
+/// A syntax error with what's needed to show it next to the source: the span
+/// it is about, other places that explain it and a hint on how to fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  pub message: String,
+  pub span: Span,
+  pub labels: Vec<Label>,
+  pub help: Option<String>,
+  /// The input ended before the construct did, so more input might fix it
+  pub at_eof: bool,
+}
+
+impl Diagnostic {
+  pub fn new<T: Into<String>>(message: T, span: Span) -> Self {
+    Self {
+      message: message.into(),
+      span,
+      labels: Vec::new(),
+      help: None,
+      at_eof: false,
+    }
+  }
+
+  pub fn with_label<T: Into<String>>(mut self, span: Span, message: T) -> Self {
+    self.labels.push(Label {
+      span,
+      message: message.into(),
+    });
+    self
+  }
+
+  pub fn with_help<T: Into<String>>(mut self, help: T) -> Self {
+    self.help = Some(help.into());
+    self
+  }
+
+  pub fn at_end_of_input(mut self) -> Self {
+    self.at_eof = true;
+    self
+  }
+}
+
+/// A secondary note on a diagnostic, like where an unclosed block was opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+  pub span: Span,
+  pub message: String,
+}
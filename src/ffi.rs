@@ -0,0 +1,272 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+//! C API for embedding Mew through the `mew_lib` shared library.
+//!
+//! Values cross the boundary as JSON strings. Every string returned by the
+//! library is owned by the interpreter and stays valid until the next
+//! `mew_eval` or `mew_register_function` on it, or until it is freed. The
+//! header `include/mew.h` is generated from this file by `build.rs`.
+
+use crate::error::MewError;
+use crate::interpreter::Interpreter;
+use crate::value::Value;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+
+/// Whether a call succeeded
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MewStatus {
+  Ok = 0,
+  Error = 1,
+}
+
+/// A native function implemented in C.
+///
+/// `args_json` is a JSON array holding the arguments. The callback returns
+/// the result as a JSON string, which Mew copies before the callback is
+/// invoked again, or NULL to make the call fail.
+pub type MewCallback =
+  Option<unsafe extern "C" fn(args_json: *const c_char, user_data: *mut c_void) -> *const c_char>;
+
+/// An interpreter together with the outcome of the last evaluation
+pub struct MewInterpreter {
+  interpreter: Interpreter,
+  result: Option<CString>,
+  error: Option<LastError>,
+}
+
+struct LastError {
+  kind: CString,
+  message: CString,
+  line: usize,
+  column: usize,
+}
+
+impl MewInterpreter {
+  fn fail(&mut self, error: &MewError) -> MewStatus {
+    let location = error.location();
+    self.error = Some(LastError {
+      kind: c_string(error.kind_name()),
+      message: c_string(&error.message()),
+      line: location.map_or(0, |l| l.line),
+      column: location.map_or(0, |l| l.column),
+    });
+    MewStatus::Error
+  }
+}
+
+// Interior NUL bytes can't be represented in a C string, so they are dropped
+fn c_string(s: &str) -> CString {
+  CString::new(s.replace('\0', "")).expect("NUL bytes removed")
+}
+
+unsafe fn read_str<'a>(s: *const c_char, what: &str) -> Result<&'a str, MewError> {
+  if s.is_null() {
+    return Err(MewError::type_error(format!("{} must not be NULL", what)));
+  }
+
+  CStr::from_ptr(s)
+    .to_str()
+    .map_err(|_| MewError::type_error(format!("{} is not valid UTF-8", what)))
+}
+
+/// Create a new interpreter. Free it with `mew_interpreter_free`.
+#[no_mangle]
+pub extern "C" fn mew_interpreter_new() -> *mut MewInterpreter {
+  Box::into_raw(Box::new(MewInterpreter {
+    interpreter: Interpreter::new(),
+    result: None,
+    error: None,
+  }))
+}
+
+/// Free an interpreter created by `mew_interpreter_new`. NULL is ignored.
+///
+/// # Safety
+///
+/// `mew` must be NULL or a pointer returned by `mew_interpreter_new` that has
+/// not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn mew_interpreter_free(mew: *mut MewInterpreter) {
+  if !mew.is_null() {
+    drop(Box::from_raw(mew));
+  }
+}
+
+/// Run NUL-terminated Mew source in the interpreter's global scope.
+///
+/// On success the value of the last statement is available from
+/// `mew_result_json`. On failure the error is available from
+/// `mew_error_kind`, `mew_error_message`, `mew_error_line` and
+/// `mew_error_column`. A result that can't be represented as JSON, such as a
+/// function, is reported as an error.
+///
+/// # Safety
+///
+/// `mew` must be a live interpreter and `source` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mew_eval(mew: *mut MewInterpreter, source: *const c_char) -> MewStatus {
+  let Some(mew) = mew.as_mut() else {
+    return MewStatus::Error;
+  };
+
+  let result = read_str(source, "source")
+    .and_then(|source| mew.interpreter.eval(source))
+    .and_then(|value| {
+      serde_json::to_string(&value).map_err(|e| MewError::type_error(e.to_string()))
+    });
+
+  match result {
+    Ok(json) => {
+      mew.result = Some(c_string(&json));
+      mew.error = None;
+      MewStatus::Ok
+    }
+    Err(error) => {
+      mew.result = None;
+      mew.fail(&error)
+    }
+  }
+}
+
+/// The result of the last successful `mew_eval` as JSON, or NULL if it
+/// failed
+///
+/// # Safety
+///
+/// `mew` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn mew_result_json(mew: *const MewInterpreter) -> *const c_char {
+  match mew.as_ref().and_then(|mew| mew.result.as_ref()) {
+    Some(json) => json.as_ptr(),
+    None => ptr::null(),
+  }
+}
+
+/// The kind of the last error (e.g. "TypeError"), or NULL if there is none
+///
+/// # Safety
+///
+/// `mew` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn mew_error_kind(mew: *const MewInterpreter) -> *const c_char {
+  match mew.as_ref().and_then(|mew| mew.error.as_ref()) {
+    Some(error) => error.kind.as_ptr(),
+    None => ptr::null(),
+  }
+}
+
+/// The message of the last error, or NULL if there is none
+///
+/// # Safety
+///
+/// `mew` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn mew_error_message(mew: *const MewInterpreter) -> *const c_char {
+  match mew.as_ref().and_then(|mew| mew.error.as_ref()) {
+    Some(error) => error.message.as_ptr(),
+    None => ptr::null(),
+  }
+}
+
+/// The line of the last error, or 0 if it has no location
+///
+/// # Safety
+///
+/// `mew` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn mew_error_line(mew: *const MewInterpreter) -> usize {
+  mew
+    .as_ref()
+    .and_then(|mew| mew.error.as_ref())
+    .map_or(0, |error| error.line)
+}
+
+/// The column of the last error, or 0 if it has no location
+///
+/// # Safety
+///
+/// `mew` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn mew_error_column(mew: *const MewInterpreter) -> usize {
+  mew
+    .as_ref()
+    .and_then(|mew| mew.error.as_ref())
+    .map_or(0, |error| error.column)
+}
+
+/// Expose `callback` to Mew code as the global function `name`.
+/// `user_data` is passed back to every call unchanged.
+///
+/// # Safety
+///
+/// `mew` must be a live interpreter and `name` a NUL-terminated string.
+/// `callback` and `user_data` must stay valid for as long as the interpreter
+/// is alive.
+#[no_mangle]
+pub unsafe extern "C" fn mew_register_function(
+  mew: *mut MewInterpreter,
+  name: *const c_char,
+  callback: MewCallback,
+  user_data: *mut c_void,
+) -> MewStatus {
+  let Some(mew) = mew.as_mut() else {
+    return MewStatus::Error;
+  };
+
+  let name = match read_str(name, "name") {
+    Ok(name) => name.to_string(),
+    Err(error) => return mew.fail(&error),
+  };
+
+  let Some(callback) = callback else {
+    return mew.fail(&MewError::type_error("callback must not be NULL"));
+  };
+
+  let function_name = name.clone();
+  mew
+    .interpreter
+    .register_function(&name, move |_interpreter, args| {
+      let args_json = serde_json::to_string(&Value::array(args))
+        .map_err(|e| MewError::type_error(e.to_string()))?;
+      let args_json = c_string(&args_json);
+
+      let result = callback(args_json.as_ptr(), user_data);
+      if result.is_null() {
+        return Err(MewError::runtime(format!(
+          "Native function '{}' failed",
+          function_name
+        )));
+      }
+
+      let result = CStr::from_ptr(result).to_str().map_err(|_| {
+        MewError::type_error(format!(
+          "Native function '{}' returned invalid UTF-8",
+          function_name
+        ))
+      })?;
+
+      serde_json::from_str(result).map_err(|e| {
+        MewError::type_error(format!(
+          "Native function '{}' returned invalid JSON: {}",
+          function_name, e
+        ))
+      })
+    });
+
+  MewStatus::Ok
+}
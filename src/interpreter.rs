@@ -13,16 +13,293 @@
 // You should have received a copy of the GNU General Public License
 // along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::{MewError, MewResult};
-use crate::value::{BinaryOp, Environment, Expr, Function, NativeFunction, Stmt, UnaryOp, Value};
+use crate::array_methods;
+use crate::convert;
+use crate::error::{Limit, Location, MewError, MewResult, Span, StackFrame};
+use crate::lexer::MewLexer;
+use crate::module::{self, Exports, ModuleState};
+use crate::parser::Parser;
+use crate::resolver::{self, Resolver};
+use crate::string_methods;
+use crate::value::{
+  BinaryOp, Breed, ClassMember, Environment, Expr, ExprKind, Function, NativeFunctionType, Object,
+  ObjectKind, Slot, Stmt, StmtKind, UnaryOp, Value, VarKind,
+};
+use crate::vm::{self, Closure, Machine};
 use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// Calls deeper than this fail unless the limits say otherwise
+const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+// Function calls move to a fresh stack segment of STACK_SEGMENT bytes when
+// less than STACK_RED_ZONE is left, so deep recursion can't overflow
+pub(crate) const STACK_RED_ZONE: usize = 256 * 1024;
+pub(crate) const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+// How many steps run between checks of the clock
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// The outcome of executing a statement. Anything other than `Normal`
+/// unwinds through enclosing statements until a function call or loop
+/// consumes it.
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+  Normal(Value),
+  Return(Value),
+  Break(Option<String>),
+  Continue(Option<String>),
+}
+
+// An assignment target with its container already evaluated
+enum Place {
+  Variable(String, Option<Slot>),
+  Property(Value, String),
+  Index(Value, Value),
+}
+
+enum LoopSignal {
+  Next(Value),
+  Exit,
+  Propagate(ControlFlow),
+}
+
+// A module whose top-level code is currently executing
+struct ModuleFrame {
+  path: PathBuf,
+  exports: Vec<String>,
+}
+
+// A function call or module in progress, kept for tracebacks
+pub(crate) struct CallFrame {
+  code: Code,
+  // Where the caller was when it made the call
+  call_site: Location,
+}
+
+pub(crate) enum Code {
+  Module(Rc<Path>),
+  Function(Rc<Function>),
+  Closure(Rc<Closure>),
+}
+
+impl CallFrame {
+  pub(crate) fn new(code: Code, call_site: Location) -> Self {
+    Self { code, call_site }
+  }
+
+  fn file(&self) -> Option<&Rc<Path>> {
+    match &self.code {
+      Code::Module(path) => Some(path),
+      Code::Function(function) => function.file.as_ref(),
+      Code::Closure(closure) => closure.file.as_ref(),
+    }
+  }
+
+  fn stack_frame(&self, location: Location) -> StackFrame {
+    let function = match &self.code {
+      Code::Module(_) => None,
+      Code::Function(function) => Some(function.name.as_deref().unwrap_or("<anonymous>")),
+      Code::Closure(closure) => Some(closure.name().unwrap_or("<anonymous>")),
+    };
+
+    StackFrame {
+      function: function.map(str::to_string),
+      file: self.file().map(|file| file.to_path_buf()),
+      location,
+    }
+  }
+}
+
+/// Aborts whatever an [`Interpreter`] is running, from another thread or a
+/// signal handler. The evaluation fails with [`MewError::Interrupted`] and
+/// the interpreter keeps its state, so it can be used again afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Ask the running evaluation to stop at the next statement
+  pub fn interrupt(&self) {
+    self.0.store(true, Ordering::Relaxed);
+  }
+
+  // Whether an interrupt is pending, clearing it so the next evaluation
+  // runs normally
+  fn take(&self) -> bool {
+    self.0.load(Ordering::Relaxed) && self.0.swap(false, Ordering::Relaxed)
+  }
+
+  fn clear(&self) {
+    self.0.store(false, Ordering::Relaxed);
+  }
+}
+
+/// Bounds on the work a program may do, for running untrusted code. `None`
+/// means unlimited. A program exceeding a limit fails with
+/// [`MewError::LimitExceeded`], which `catch` blocks can't intercept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+  /// Statements executed, counted from when the limits are set
+  pub max_steps: Option<u64>,
+  /// Function calls in progress at once
+  pub max_call_depth: Option<usize>,
+  /// When to stop running
+  pub deadline: Option<Instant>,
+  /// Elements of an array or properties of an object
+  pub max_collection_size: Option<usize>,
+  /// Characters in a string
+  pub max_string_length: Option<usize>,
+}
+
+impl Default for Limits {
+  /// Only the call depth is limited, to catch runaway recursion
+  fn default() -> Self {
+    Self {
+      max_steps: None,
+      max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+      deadline: None,
+      max_collection_size: None,
+      max_string_length: None,
+    }
+  }
+}
+
+// Output that stops growing once it is longer than the string length limit,
+// so an oversized result fails before all of it is built
+struct BoundedOutput {
+  bytes: Vec<u8>,
+  chars: usize,
+  max: Option<usize>,
+}
+
+impl BoundedOutput {
+  fn new(max: Option<usize>) -> Self {
+    Self {
+      bytes: Vec::new(),
+      chars: 0,
+      max,
+    }
+  }
+
+  // Append `bytes`, or return false if that would pass the limit
+  fn push(&mut self, bytes: &[u8]) -> bool {
+    if let Some(max) = self.max {
+      // Every byte of UTF-8 except continuation bytes starts a character
+      self.chars += bytes.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+      if self.chars > max {
+        return false;
+      }
+    }
+    self.bytes.extend_from_slice(bytes);
+    true
+  }
+
+  // The output, or the error to report if it was cut off by the limit
+  fn finish<E>(
+    self,
+    result: Result<(), E>,
+    error: impl FnOnce(E) -> MewError,
+  ) -> MewResult<String> {
+    if let Some(max) = self.max {
+      if self.chars > max {
+        return Err(MewError::LimitExceeded(Limit::StringLength(max)));
+      }
+    }
+    result.map_err(error)?;
+    Ok(String::from_utf8(self.bytes).expect("only UTF-8 is written"))
+  }
+}
+
+impl Write for BoundedOutput {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if self.push(buf) {
+      Ok(buf.len())
+    } else {
+      Err(io::Error::other("string length limit exceeded"))
+    }
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl fmt::Write for BoundedOutput {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    if self.push(s.as_bytes()) {
+      Ok(())
+    } else {
+      Err(fmt::Error)
+    }
+  }
+}
+
+// An array the way `toString` shows it, as `[1,2,3]`
+fn write_array(out: &mut dyn fmt::Write, elements: &[Value]) -> fmt::Result {
+  out.write_str("[")?;
+  for (i, element) in elements.iter().enumerate() {
+    if i > 0 {
+      out.write_str(",")?;
+    }
+    out.write_fmt(format_args!("{}", element))?;
+  }
+  out.write_str("]")
+}
+
+/// How an [`Interpreter`] runs programs. Both give the same results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+  /// Walk the syntax tree
+  #[default]
+  Tree,
+  /// Compile to bytecode and run it on a stack VM, which is faster
+  Vm,
+}
 
 pub struct Interpreter {
-  environment: Rc<RefCell<Environment>>,
+  pub(crate) environment: Rc<RefCell<Environment>>,
   globals: Rc<RefCell<Environment>>,
+  modules: HashMap<PathBuf, ModuleState>,
+  module_stack: Vec<ModuleFrame>,
+  stdout: Box<dyn Write>,
+  stderr: Box<dyn Write>,
+  // Whether `stderr` is the process stderr and that is a terminal
+  stderr_is_terminal: bool,
+  stdin: Box<dyn BufRead>,
+  interrupt: InterruptHandle,
+  // Whether the host has started an evaluation that hasn't finished yet
+  running: bool,
+  limits: Limits,
+  steps: u64,
+  pub(crate) call_depth: usize,
+  pub(crate) calls: Vec<CallFrame>,
+  // Location of the call being made, which becomes the call site of the
+  // next frame
+  pub(crate) call_site: Location,
+  traceback: Vec<StackFrame>,
+  engine: Engine,
+  pub(crate) vm: Machine,
+}
+
+impl Default for Interpreter {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl Interpreter {
@@ -33,6 +310,22 @@ impl Interpreter {
     let mut interp = Self {
       globals: globals.clone(),
       environment: globals.clone(),
+      modules: HashMap::new(),
+      module_stack: Vec::new(),
+      stdout: Box::new(io::stdout()),
+      stderr: Box::new(io::stderr()),
+      stderr_is_terminal: io::stderr().is_terminal(),
+      stdin: Box::new(BufReader::new(io::stdin())),
+      interrupt: InterruptHandle::new(),
+      running: false,
+      limits: Limits::default(),
+      steps: 0,
+      call_depth: 0,
+      calls: Vec::new(),
+      call_site: Location::unknown(),
+      traceback: Vec::new(),
+      engine: Engine::default(),
+      vm: Machine::default(),
     };
 
     interp.define_native_functions();
@@ -44,30 +337,28 @@ impl Interpreter {
     // Native print function
     self.globals.borrow_mut().define(
       "print",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "print".to_string(),
-        function: Self::native_print,
-      })),
+      Value::native_function("print", Self::native_print),
       true,
     );
 
     // Define toString for all value types
     self.globals.borrow_mut().define(
       "toString",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "toString".to_string(),
-        function: Self::native_to_string,
-      })),
+      Value::native_function("toString", Self::native_to_string),
       true,
     );
 
     // Define purr as alias for print (cat-themed print function)
     self.globals.borrow_mut().define(
       "purr",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "purr".to_string(),
-        function: Self::native_print,
-      })),
+      Value::native_function("purr", Self::native_print),
+      true,
+    );
+
+    // Read a line of input
+    self.globals.borrow_mut().define(
+      "listen",
+      Value::native_function("listen", Self::native_listen),
       true,
     );
 
@@ -77,107 +368,74 @@ impl Interpreter {
     // Add Object.keys method
     object_methods.insert(
       "keys".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "keys".to_string(),
-        function: Self::native_object_keys,
-      })),
+      Value::native_function("keys", Self::native_object_keys),
     );
 
     // Add Object.values method
     object_methods.insert(
       "values".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "values".to_string(),
-        function: Self::native_object_values,
-      })),
+      Value::native_function("values", Self::native_object_values),
     );
 
     // Define the Object global
     self
       .globals
       .borrow_mut()
-      .define("Object", Value::Object(object_methods), true);
+      .define("Object", Value::object(object_methods), true);
 
     // Native time function
     self.globals.borrow_mut().define(
       "time",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "time".to_string(),
-        function: Self::native_time,
-      })),
+      Value::native_function("time", Self::native_time),
       true,
     );
 
     // Type checking functions
     self.globals.borrow_mut().define(
       "isNumber",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isNumber".to_string(),
-        function: Self::native_is_number,
-      })),
+      Value::native_function("isNumber", Self::native_is_number),
       true,
     );
 
     self.globals.borrow_mut().define(
       "isString",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isString".to_string(),
-        function: Self::native_is_string,
-      })),
+      Value::native_function("isString", Self::native_is_string),
       true,
     );
 
     self.globals.borrow_mut().define(
       "isBoolean",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isBoolean".to_string(),
-        function: Self::native_is_boolean,
-      })),
+      Value::native_function("isBoolean", Self::native_is_boolean),
       true,
     );
 
     self.globals.borrow_mut().define(
       "isNull",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isNull".to_string(),
-        function: Self::native_is_null,
-      })),
+      Value::native_function("isNull", Self::native_is_null),
       true,
     );
 
     self.globals.borrow_mut().define(
       "isUndefined",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isUndefined".to_string(),
-        function: Self::native_is_undefined,
-      })),
+      Value::native_function("isUndefined", Self::native_is_undefined),
       true,
     );
 
     self.globals.borrow_mut().define(
       "isArray",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isArray".to_string(),
-        function: Self::native_is_array,
-      })),
+      Value::native_function("isArray", Self::native_is_array),
       true,
     );
 
     self.globals.borrow_mut().define(
       "isObject",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isObject".to_string(),
-        function: Self::native_is_object,
-      })),
+      Value::native_function("isObject", Self::native_is_object),
       true,
     );
 
     self.globals.borrow_mut().define(
       "isFunction",
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "isFunction".to_string(),
-        function: Self::native_is_function,
-      })),
+      Value::native_function("isFunction", Self::native_is_function),
       true,
     );
 
@@ -186,91 +444,61 @@ impl Interpreter {
     // Mewth.pounce (floor)
     mewth_methods.insert(
       "pounce".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "pounce".to_string(),
-        function: Self::native_mewth_pounce,
-      })),
+      Value::native_function("pounce", Self::native_mewth_pounce),
     );
 
     // Mewth.leap (ceil)
     mewth_methods.insert(
       "leap".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "leap".to_string(),
-        function: Self::native_mewth_leap,
-      })),
+      Value::native_function("leap", Self::native_mewth_leap),
     );
 
     // Mewth.curl (round)
     mewth_methods.insert(
       "curl".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "curl".to_string(),
-        function: Self::native_mewth_curl,
-      })),
+      Value::native_function("curl", Self::native_mewth_curl),
     );
 
     // Mewth.lick (abs)
     mewth_methods.insert(
       "lick".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "lick".to_string(),
-        function: Self::native_mewth_lick,
-      })),
+      Value::native_function("lick", Self::native_mewth_lick),
     );
 
     // Mewth.alpha (max)
     mewth_methods.insert(
       "alpha".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "alpha".to_string(),
-        function: Self::native_mewth_alpha,
-      })),
+      Value::native_function("alpha", Self::native_mewth_alpha),
     );
 
     // Mewth.kitten (min)
     mewth_methods.insert(
       "kitten".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "kitten".to_string(),
-        function: Self::native_mewth_kitten,
-      })),
+      Value::native_function("kitten", Self::native_mewth_kitten),
     );
 
     // Mewth.chase (random)
     mewth_methods.insert(
       "chase".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "chase".to_string(),
-        function: Self::native_mewth_chase,
-      })),
+      Value::native_function("chase", Self::native_mewth_chase),
     );
 
     // Mewth.hiss (sqrt)
     mewth_methods.insert(
       "dig".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "dig".to_string(),
-        function: Self::native_mewth_dig,
-      })),
+      Value::native_function("dig", Self::native_mewth_dig),
     );
 
     // Mewth.scratch (pow)
     mewth_methods.insert(
       "scratch".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "scratch".to_string(),
-        function: Self::native_mewth_scratch,
-      })),
+      Value::native_function("scratch", Self::native_mewth_scratch),
     );
 
     // Mewth.tailDirection (sign)
     mewth_methods.insert(
       "tailDirection".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "tailDirection".to_string(),
-        function: Self::native_mewth_tail_direction,
-      })),
+      Value::native_function("tailDirection", Self::native_mewth_tail_direction),
     );
 
     // Add Mewth.PI constant (equivalent to Math.PI)
@@ -280,102 +508,69 @@ impl Interpreter {
     self
       .globals
       .borrow_mut()
-      .define("Mewth", Value::Object(mewth_methods), true);
+      .define("Mewth", Value::object(mewth_methods), true);
 
     let mut cat_time_methods = HashMap::new();
 
     cat_time_methods.insert(
       "now".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "now".to_string(),
-        function: Self::native_cat_time_now,
-      })),
+      Value::native_function("now", Self::native_cat_time_now),
     );
 
     cat_time_methods.insert(
       "wakeUp".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "wakeUp".to_string(),
-        function: Self::native_cat_time_wake_up,
-      })),
+      Value::native_function("wakeUp", Self::native_cat_time_wake_up),
     );
 
     cat_time_methods.insert(
       "fullYear".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "fullYear".to_string(),
-        function: Self::native_cat_time_full_year,
-      })),
+      Value::native_function("fullYear", Self::native_cat_time_full_year),
     );
 
     cat_time_methods.insert(
       "month".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "month".to_string(),
-        function: Self::native_cat_time_month,
-      })),
+      Value::native_function("month", Self::native_cat_time_month),
     );
 
     cat_time_methods.insert(
       "day".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "day".to_string(),
-        function: Self::native_cat_time_day,
-      })),
+      Value::native_function("day", Self::native_cat_time_day),
     );
 
     cat_time_methods.insert(
       "weekday".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "weekday".to_string(),
-        function: Self::native_cat_time_weekday,
-      })),
+      Value::native_function("weekday", Self::native_cat_time_weekday),
     );
 
     cat_time_methods.insert(
       "hours".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "hours".to_string(),
-        function: Self::native_cat_time_hours,
-      })),
+      Value::native_function("hours", Self::native_cat_time_hours),
     );
 
     cat_time_methods.insert(
       "minutes".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "minutes".to_string(),
-        function: Self::native_cat_time_minutes,
-      })),
+      Value::native_function("minutes", Self::native_cat_time_minutes),
     );
 
     cat_time_methods.insert(
       "seconds".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "seconds".to_string(),
-        function: Self::native_cat_time_seconds,
-      })),
+      Value::native_function("seconds", Self::native_cat_time_seconds),
     );
 
     cat_time_methods.insert(
       "milliseconds".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "milliseconds".to_string(),
-        function: Self::native_cat_time_milliseconds,
-      })),
+      Value::native_function("milliseconds", Self::native_cat_time_milliseconds),
     );
 
     cat_time_methods.insert(
       "toMeow".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "toMeow".to_string(),
-        function: Self::native_cat_time_to_meow,
-      })),
+      Value::native_function("toMeow", Self::native_cat_time_to_meow),
     );
 
     self
       .globals
       .borrow_mut()
-      .define("CatTime", Value::Object(cat_time_methods), true);
+      .define("CatTime", Value::object(cat_time_methods), true);
 
     // Add MewJ (JSON equivalent) object with methods
     let mut mewj_methods = HashMap::new();
@@ -383,41 +578,52 @@ impl Interpreter {
     // MewJ.sniff (JSON.parse)
     mewj_methods.insert(
       "sniff".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "sniff".to_string(),
-        function: Self::native_mewj_sniff,
-      })),
+      Value::native_function("sniff", Self::native_mewj_sniff),
     );
 
     // MewJ.mewify (JSON.stringify)
     mewj_methods.insert(
       "mewify".to_string(),
-      Value::NativeFunction(Rc::new(NativeFunction {
-        name: "mewify".to_string(),
-        function: Self::native_mewj_mewify,
-      })),
+      Value::native_function("mewify", Self::native_mewj_mewify),
     );
 
     // Define the MewJ global object
     self
       .globals
       .borrow_mut()
-      .define("MewJ", Value::Object(mewj_methods), true);
+      .define("MewJ", Value::object(mewj_methods), true);
   }
 
   // Static native function implementations
-  fn native_print(args: Vec<Value>) -> MewResult<Value> {
-    for (i, arg) in args.iter().enumerate() {
-      if i > 0 {
-        print!(" ");
-      }
-      print!("{}", arg);
-    }
-    println!();
+  fn native_print(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+    let line = args
+      .iter()
+      .map(|arg| arg.to_string())
+      .collect::<Vec<_>>()
+      .join(" ");
+    writeln!(interpreter.stdout, "{}", line)?;
     Ok(Value::Undefined)
   }
 
-  fn native_time(_args: Vec<Value>) -> MewResult<Value> {
+  // listen(prompt?) returns the next line of input without its line ending,
+  // or null once the input is exhausted
+  fn native_listen(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+    if let Some(prompt) = args.first() {
+      write!(interpreter.stdout, "{}", prompt)?;
+      interpreter.stdout.flush()?;
+    }
+
+    let mut line = String::new();
+    if interpreter.stdin.read_line(&mut line)? == 0 {
+      return Ok(Value::Null);
+    }
+
+    let trimmed = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(trimmed);
+    Ok(Value::String(line))
+  }
+
+  fn native_time(_interpreter: &mut Interpreter, _args: Vec<Value>) -> MewResult<Value> {
     let now = std::time::SystemTime::now()
       .duration_since(std::time::UNIX_EPOCH)
       .unwrap()
@@ -425,35 +631,35 @@ impl Interpreter {
     Ok(Value::Number(now as f64))
   }
 
-  fn native_is_number(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_number(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime("isNumber requires exactly one argument"));
     }
     Ok(Value::Bool(matches!(args[0], Value::Number(_))))
   }
 
-  fn native_is_string(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_string(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime("isString requires exactly one argument"));
     }
     Ok(Value::Bool(matches!(args[0], Value::String(_))))
   }
 
-  fn native_is_boolean(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_boolean(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime("isBoolean requires exactly one argument"));
     }
     Ok(Value::Bool(matches!(args[0], Value::Bool(_))))
   }
 
-  fn native_is_null(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_null(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime("isNull requires exactly one argument"));
     }
     Ok(Value::Bool(matches!(args[0], Value::Null)))
   }
 
-  fn native_is_undefined(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_undefined(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "isUndefined requires exactly one argument",
@@ -462,21 +668,21 @@ impl Interpreter {
     Ok(Value::Bool(matches!(args[0], Value::Undefined)))
   }
 
-  fn native_is_array(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_array(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime("isArray requires exactly one argument"));
     }
     Ok(Value::Bool(matches!(args[0], Value::Array(_))))
   }
 
-  fn native_is_object(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_object(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime("isObject requires exactly one argument"));
     }
     Ok(Value::Bool(matches!(args[0], Value::Object(_))))
   }
 
-  fn native_is_function(args: Vec<Value>) -> MewResult<Value> {
+  fn native_is_function(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "isFunction requires exactly one argument",
@@ -484,11 +690,14 @@ impl Interpreter {
     }
     Ok(Value::Bool(matches!(
       args[0],
-      Value::Function(_) | Value::NativeFunction(_)
+      Value::Function(_) | Value::NativeFunction(_) | Value::Closure(_)
     )))
   }
 
-  fn native_object_keys(args: Vec<Value>) -> MewResult<Value> {
+  pub(crate) fn native_object_keys(
+    _interpreter: &mut Interpreter,
+    args: Vec<Value>,
+  ) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Object.keys requires exactly one argument",
@@ -497,14 +706,20 @@ impl Interpreter {
 
     match &args[0] {
       Value::Object(obj) => {
-        let keys: Vec<Value> = obj.keys().map(|k| Value::String(k.clone())).collect();
+        let keys: Vec<Value> = obj
+          .borrow()
+          .keys()
+          .map(|k| Value::String(k.clone()))
+          .collect();
 
-        Ok(Value::Array(keys))
+        Ok(Value::array(keys))
       }
       Value::Array(arr) => {
-        let keys: Vec<Value> = (0..arr.len()).map(|i| Value::Number(i as f64)).collect();
+        let keys: Vec<Value> = (0..arr.borrow().len())
+          .map(|i| Value::Number(i as f64))
+          .collect();
 
-        Ok(Value::Array(keys))
+        Ok(Value::array(keys))
       }
       _ => Err(MewError::type_error(format!(
         "Object.keys requires an object or array, got {}",
@@ -513,7 +728,10 @@ impl Interpreter {
     }
   }
 
-  fn native_object_values(args: Vec<Value>) -> MewResult<Value> {
+  pub(crate) fn native_object_values(
+    _interpreter: &mut Interpreter,
+    args: Vec<Value>,
+  ) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Object.values requires exactly one argument",
@@ -522,11 +740,11 @@ impl Interpreter {
 
     match &args[0] {
       Value::Object(obj) => {
-        let values: Vec<Value> = obj.values().cloned().collect();
+        let values: Vec<Value> = obj.borrow().values().cloned().collect();
 
-        Ok(Value::Array(values))
+        Ok(Value::array(values))
       }
-      Value::Array(arr) => Ok(Value::Array(arr.clone())),
+      Value::Array(arr) => Ok(Value::array(arr.borrow().clone())),
       _ => Err(MewError::type_error(format!(
         "Object.values requires an object or array, got {}",
         args[0].type_name()
@@ -535,66 +753,495 @@ impl Interpreter {
   }
 
   pub fn interpret(&mut self, statements: &[Rc<RefCell<Stmt>>]) -> MewResult<Value> {
+    Resolver::new()
+      .resolve(statements)
+      .map_err(|error| self.record(error))?;
+
+    // Top-level `catv` variables exist from the start, even when they are
+    // declared in a block
+    for name in resolver::hoisted_vars(statements) {
+      self.environment.borrow_mut().hoist(&name);
+    }
+    // and those declared with `catlt`, `catst` or `breed` can't be used
+    // before their declaration, even from a function called earlier
+    for name in resolver::top_level_lexicals(statements) {
+      self.environment.borrow_mut().reserve(&name);
+    }
+
+    if self.engine == Engine::Vm {
+      let script = vm::compile(statements).map_err(|error| self.record(error))?;
+      return self.run_script(script);
+    }
+
+    self.hoist_functions(statements)?;
+
     let mut result = Value::Null;
 
     for statement in statements {
-      result = self.execute(&statement.borrow())?;
+      let statement = statement.borrow();
+      if statement.declares_function() {
+        result = Value::Undefined;
+        continue;
+      }
+
+      match self.execute(&statement)? {
+        ControlFlow::Normal(value) => result = value,
+        flow => return Err(Self::stray_control_flow(flow)),
+      }
     }
 
     Ok(result)
   }
 
-  fn execute(&mut self, stmt: &Stmt) -> MewResult<Value> {
-    match stmt {
-      Stmt::Expression(expr) => self.evaluate(expr),
-      Stmt::Print(expr) => {
+  /// Run a file as the entry module of a program. Imports inside it are
+  /// resolved relative to the file.
+  pub fn run_file(&mut self, path: &Path) -> MewResult<Value> {
+    self.host_run(|interpreter| {
+      let path = path.canonicalize()?;
+      let (value, _) = interpreter.load_module(&path)?;
+      Ok(value)
+    })
+  }
+
+  /// Run Mew source in the global scope and return the value of its last
+  /// statement. Variables and functions it declares stay defined, so later
+  /// calls can build on earlier ones.
+  pub fn eval(&mut self, source: &str) -> MewResult<Value> {
+    self.host_run(|interpreter| {
+      let tokens = MewLexer::new(source).scan_tokens()?;
+      let statements = Parser::new(tokens).parse()?;
+      interpreter.interpret(&statements)
+    })
+  }
+
+  // Run an evaluation the host asked for. An interrupt sent while nothing
+  // was running is dropped so it can't stop this one, unless the host is
+  // calling in from a native function while code is already running.
+  fn host_run(&mut self, f: impl FnOnce(&mut Self) -> MewResult<Value>) -> MewResult<Value> {
+    self.traceback.clear();
+    if self.running {
+      return f(self);
+    }
+
+    self.interrupt.clear();
+    self.running = true;
+    let result = f(self);
+    self.running = false;
+    result
+  }
+
+  /// The calls that were running when the last error was raised, outermost
+  /// first, each with the location it had got to. The last frame holds the
+  /// location of the error itself. Empty when the error happened outside of
+  /// any code, e.g. when a file couldn't be read.
+  pub fn traceback(&self) -> &[StackFrame] {
+    &self.traceback
+  }
+
+  /// Look up a global variable, including ones declared by [`eval`](Self::eval)
+  pub fn get_global(&self, name: &str) -> Option<Value> {
+    self.globals.borrow().get(name).ok()
+  }
+
+  /// Names of all global variables, including built-ins like `Mewth`
+  pub fn global_names(&self) -> Vec<String> {
+    let mut names = self.globals.borrow().names();
+    names.sort();
+    names
+  }
+
+  /// Define or overwrite a global variable
+  pub fn set_global(&mut self, name: &str, value: Value) {
+    self.globals.borrow_mut().define(name, value, false);
+  }
+
+  /// Call a Mew function (or breed, or native function) with the given
+  /// arguments
+  pub fn call(&mut self, function: &Value, arguments: Vec<Value>) -> MewResult<Value> {
+    self.host_run(|interpreter| {
+      interpreter.call_site = Location::unknown();
+      interpreter.call_function(function.clone(), arguments)
+    })
+  }
+
+  /// Call the global function `name`
+  pub fn call_global(&mut self, name: &str, arguments: Vec<Value>) -> MewResult<Value> {
+    self.host_run(|interpreter| {
+      interpreter.call_site = Location::unknown();
+      let function = interpreter.globals.borrow().get(name)?;
+      interpreter.call_function(function, arguments)
+    })
+  }
+
+  /// Expose a Rust function or closure to Mew code as the global `name`.
+  /// Closures may capture host state, e.g. through `Rc<RefCell<_>>`.
+  pub fn register_function<F>(&mut self, name: &str, function: F)
+  where
+    F: Fn(&mut Interpreter, Vec<Value>) -> MewResult<Value> + 'static,
+  {
+    self
+      .globals
+      .borrow_mut()
+      .define(name, Value::native_function(name, function), true);
+  }
+
+  /// Expose a native object, such as a map of [`Value::native_function`]
+  /// closures sharing host state, as the global `name`
+  pub fn register_object(&mut self, name: &str, members: HashMap<String, Value>) {
+    self
+      .globals
+      .borrow_mut()
+      .define(name, Value::object(members), true);
+  }
+
+  /// A handle that aborts the running evaluation, e.g. when the user
+  /// presses Ctrl+C
+  pub fn interrupt_handle(&self) -> InterruptHandle {
+    self.interrupt.clone()
+  }
+
+  /// Listen for interrupts on an existing handle, such as one already wired
+  /// to a signal handler, instead of this interpreter's own
+  pub fn set_interrupt_handle(&mut self, handle: InterruptHandle) {
+    self.interrupt = handle;
+  }
+
+  /// Choose how later evaluations run. Functions defined under one engine
+  /// can still be called under the other.
+  pub fn set_engine(&mut self, engine: Engine) {
+    self.engine = engine;
+  }
+
+  pub fn engine(&self) -> Engine {
+    self.engine
+  }
+
+  /// Bound the work later evaluations may do. The step count starts over.
+  pub fn set_limits(&mut self, limits: Limits) {
+    self.limits = limits;
+    self.steps = 0;
+  }
+
+  pub fn limits(&self) -> &Limits {
+    &self.limits
+  }
+
+  /// Fail if `value` is a string or collection larger than the limits allow
+  pub(crate) fn check_size(&self, value: &Value) -> MewResult<()> {
+    match value {
+      Value::String(s) => self.check_string_length(s.len(), || s.chars().count()),
+      Value::Array(arr) => self.check_collection_size(arr.borrow().len()),
+      Value::Object(obj) => self.check_collection_size(obj.borrow().len()),
+      _ => Ok(()),
+    }
+  }
+
+  /// Fail if a string of `bytes` bytes would be longer than the limit
+  /// allows, before it is built. `chars` counts its characters exactly
+  /// when the byte count alone doesn't settle it.
+  pub(crate) fn check_string_length(
+    &self,
+    bytes: usize,
+    chars: impl FnOnce() -> usize,
+  ) -> MewResult<()> {
+    match self.limits.max_string_length {
+      Some(max) if bytes > max && chars() > max => {
+        Err(MewError::LimitExceeded(Limit::StringLength(max)))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  pub(crate) fn check_collection_size(&self, size: usize) -> MewResult<()> {
+    match self.limits.max_collection_size {
+      Some(max) if size > max => Err(MewError::LimitExceeded(Limit::CollectionSize(max))),
+      _ => Ok(()),
+    }
+  }
+
+  /// Fail if any array or object in `value`, which must not be circular, is
+  /// larger than the limit allows
+  pub(crate) fn check_nested_sizes(&self, value: &Value) -> MewResult<()> {
+    if self.limits.max_collection_size.is_none() {
+      return Ok(());
+    }
+
+    let mut pending = vec![value.clone()];
+    while let Some(value) = pending.pop() {
+      let children: Vec<Value> = match &value {
+        Value::Array(items) => items.borrow().clone(),
+        Value::Object(obj) => obj.borrow().values().cloned().collect(),
+        _ => continue,
+      };
+      self.check_collection_size(children.len())?;
+      pending.extend(
+        children
+          .into_iter()
+          .filter(|child| matches!(child, Value::Array(_) | Value::Object(_))),
+      );
+    }
+    Ok(())
+  }
+
+  /// Format a string, stopping as soon as it is longer than the limit allows
+  pub(crate) fn format_bounded(&self, args: fmt::Arguments) -> MewResult<String> {
+    if self.limits.max_string_length.is_none() {
+      return Ok(fmt::format(args));
+    }
+
+    let mut output = BoundedOutput::new(self.limits.max_string_length);
+    let result = fmt::Write::write_fmt(&mut output, args);
+    output.finish(result, |_| MewError::runtime("Could not format the string"))
+  }
+
+  // Account for one more statement, stopping the program if it was
+  // interrupted or is out of steps or time
+  pub(crate) fn step(&mut self) -> MewResult<()> {
+    if self.interrupt.take() {
+      return Err(MewError::Interrupted);
+    }
+
+    self.steps += 1;
+    if let Some(max) = self.limits.max_steps {
+      if self.steps > max {
+        return Err(MewError::LimitExceeded(Limit::Steps(max)));
+      }
+    }
+
+    if let Some(deadline) = self.limits.deadline {
+      if self.steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+        return Err(MewError::LimitExceeded(Limit::Deadline));
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Send `purr`/`print` output to `writer` instead of the process stdout
+  pub fn set_stdout(&mut self, writer: impl Write + 'static) {
+    self.stdout = Box::new(writer);
+  }
+
+  /// Send error output to `writer` instead of the process stderr
+  pub fn set_stderr(&mut self, writer: impl Write + 'static) {
+    self.stderr = Box::new(writer);
+    self.stderr_is_terminal = false;
+  }
+
+  /// Read `listen` input from `reader` instead of the process stdin
+  pub fn set_stdin(&mut self, reader: impl BufRead + 'static) {
+    self.stdin = Box::new(reader);
+  }
+
+  /// The writer program output goes to
+  pub fn stdout(&mut self) -> &mut dyn Write {
+    self.stdout.as_mut()
+  }
+
+  /// The writer error output goes to
+  pub fn stderr(&mut self) -> &mut dyn Write {
+    self.stderr.as_mut()
+  }
+
+  /// Whether error output goes to a terminal. Writers passed to
+  /// [`set_stderr`](Self::set_stderr) never count as one.
+  pub fn stderr_is_terminal(&self) -> bool {
+    self.stderr_is_terminal
+  }
+
+  // Execute a module once in its own global scope and collect its exports
+  fn load_module(&mut self, path: &Path) -> MewResult<(Value, Exports)> {
+    self
+      .modules
+      .insert(path.to_path_buf(), ModuleState::Loading);
+
+    let result = self.execute_module(path);
+
+    match &result {
+      Ok((_, exports)) => {
+        self
+          .modules
+          .insert(path.to_path_buf(), ModuleState::Loaded(exports.clone()));
+      }
+      Err(_) => {
+        self.modules.remove(path);
+      }
+    }
+
+    result
+  }
+
+  fn execute_module(&mut self, path: &Path) -> MewResult<(Value, Exports)> {
+    let source = fs::read_to_string(path)?;
+
+    self
+      .calls
+      .push(CallFrame::new(Code::Module(Rc::from(path)), self.call_site));
+    let result = self.execute_module_source(path, &source);
+    self.calls.pop();
+
+    result
+  }
+
+  fn execute_module_source(&mut self, path: &Path, source: &str) -> MewResult<(Value, Exports)> {
+    let statements = MewLexer::new(source)
+      .scan_tokens()
+      .and_then(|tokens| Parser::new(tokens).parse())
+      .map_err(|error| self.record(error))?;
+
+    let previous = self.environment.clone();
+    let module_env = Rc::new(RefCell::new(Environment::with_enclosing(
+      self.globals.clone(),
+    )));
+    self.environment = module_env.clone();
+    self.module_stack.push(ModuleFrame {
+      path: path.to_path_buf(),
+      exports: Vec::new(),
+    });
+
+    let result = self.interpret(&statements);
+
+    let frame = self.module_stack.pop().expect("module frame");
+    self.environment = previous;
+    let value = result?;
+
+    let mut exports = HashMap::new();
+    for name in frame.exports {
+      let exported = module_env.borrow().get(&name)?;
+      exports.insert(name, exported);
+    }
+
+    Ok((value, Rc::new(exports)))
+  }
+
+  // The value a module exports as `name`, loading the module if needed
+  pub(crate) fn import_binding(&mut self, specifier: &str, name: &str) -> MewResult<Value> {
+    let exports = self.import_module(specifier)?;
+    exports
+      .get(name)
+      .cloned()
+      .ok_or_else(|| MewError::name(format!("Module '{}' does not export '{}'", specifier, name)))
+  }
+
+  // Record that the module being executed exports `name`
+  pub(crate) fn export(&mut self, name: &str) {
+    if let Some(frame) = self.module_stack.last_mut() {
+      frame.exports.push(name.to_string());
+    }
+  }
+
+  fn import_module(&mut self, specifier: &str) -> MewResult<Exports> {
+    let importer = self.module_stack.last().map(|frame| frame.path.clone());
+    let path = module::resolve_import(importer.as_deref(), specifier)?;
+
+    match self.modules.get(&path) {
+      Some(ModuleState::Loaded(exports)) => Ok(exports.clone()),
+      Some(ModuleState::Loading) => {
+        let start = self
+          .module_stack
+          .iter()
+          .position(|frame| frame.path == path)
+          .unwrap_or(0);
+        let mut chain: Vec<String> = self.module_stack[start..]
+          .iter()
+          .map(|frame| module::display_name(&frame.path))
+          .collect();
+        chain.push(module::display_name(&path));
+
+        Err(MewError::runtime(format!(
+          "Circular import detected: {}",
+          chain.join(" -> ")
+        )))
+      }
+      None => Ok(self.load_module(&path)?.1),
+    }
+  }
+
+  fn execute(&mut self, stmt: &Stmt) -> MewResult<ControlFlow> {
+    // Every loop iteration and function call runs at least one statement,
+    // so checking here is enough to stop any program
+    if let Err(error) = self.step() {
+      self.record_traceback(stmt.span.start);
+      return Err(error);
+    }
+
+    // Nesting is bounded by the parser, but a deep enough nest of blocks
+    // can still outgrow a small stack
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.execute_stmt(stmt))
+      .map_err(|error| self.locate(error, stmt.span))
+  }
+
+  fn execute_stmt(&mut self, stmt: &Stmt) -> MewResult<ControlFlow> {
+    match &stmt.kind {
+      StmtKind::Expression(expr) => Ok(ControlFlow::Normal(self.evaluate(expr)?)),
+      StmtKind::Print(expr) => {
         let value = self.evaluate(expr)?;
-        println!("{}", value);
-        Ok(Value::Undefined)
+        writeln!(self.stdout, "{}", value)?;
+        Ok(ControlFlow::Normal(Value::Undefined))
       }
-      Stmt::VarDeclaration(name, initializer, is_const) => {
+      StmtKind::VarDeclaration(name, initializer, kind) => {
         let value = if let Some(expr) = initializer {
           self.evaluate(expr)?
         } else {
           Value::Undefined
         };
 
-        self.environment.borrow_mut().define(name, value, *is_const);
-        Ok(Value::Undefined)
+        match kind {
+          // `catv` variables were hoisted, so the declaration only sets them
+          VarKind::Var => {
+            if initializer.is_some() {
+              self.environment.borrow_mut().assign(name, value)?;
+            }
+          }
+          VarKind::Let | VarKind::Const => {
+            self
+              .environment
+              .borrow_mut()
+              .define(name, value, *kind == VarKind::Const);
+          }
+        }
+        Ok(ControlFlow::Normal(Value::Undefined))
       }
-      Stmt::Block(statements) => self.execute_block(
+      StmtKind::Block(statements) => self.execute_block(
         statements,
         Environment::with_enclosing(self.environment.clone()),
       ),
-      Stmt::If(condition, then_branch, else_branch) => {
+      StmtKind::If(condition, then_branch, else_branch) => {
         if self.evaluate(condition)?.is_truthy() {
           self.execute(&then_branch.borrow())
         } else if let Some(else_stmt) = else_branch {
           self.execute(&else_stmt.borrow())
         } else {
-          Ok(Value::Undefined)
+          Ok(ControlFlow::Normal(Value::Undefined))
         }
       }
-      Stmt::While(condition, body) => {
-        let mut result = Value::Undefined;
+      StmtKind::While(..) | StmtKind::DoWhile(..) | StmtKind::For(..) | StmtKind::ForIn(..) => {
+        self.execute_loop(stmt, None)
+      }
+      StmtKind::Labeled(label, body) => {
+        let body = body.borrow();
+        let flow = match &body.kind {
+          StmtKind::While(..) | StmtKind::DoWhile(..) | StmtKind::For(..) | StmtKind::ForIn(..) => {
+            self.execute_loop(&body, Some(label))?
+          }
+          _ => self.execute(&body)?,
+        };
 
-        while self.evaluate(condition)?.is_truthy() {
-          match self.execute(&body.borrow()) {
-            Ok(value) => result = value,
-            Err(MewError::Runtime(msg, _)) if msg.contains("break") => break,
-            Err(MewError::Runtime(msg, _)) if msg.contains("continue") => continue,
-            Err(e) => return Err(e),
+        match flow {
+          ControlFlow::Break(Some(target)) if &target == label => {
+            Ok(ControlFlow::Normal(Value::Undefined))
           }
+          flow => Ok(flow),
         }
-
-        Ok(result)
       }
-      Stmt::Function(name, params, body) => {
+      StmtKind::Function(name, params, body, hoisted) => {
         let function = Rc::new(Function {
           name: Some(name.clone()),
           parameters: params.clone(),
           body: body.clone(),
+          hoisted: hoisted.clone(),
           closure: self.environment.clone(),
+          file: self.current_file(),
         });
 
         self
@@ -602,52 +1249,401 @@ impl Interpreter {
           .borrow_mut()
           .define(name, Value::Function(function), false);
 
-        Ok(Value::Undefined)
+        Ok(ControlFlow::Normal(Value::Undefined))
+      }
+      StmtKind::Class(name, parent, members) => {
+        let parent = match parent {
+          Some(expr) => Some(self.evaluate(expr)?),
+          None => None,
+        };
+
+        // Methods of either kind close over the scope the breed is declared
+        // in and get `self` and `super` when they are accessed
+        let mut methods = HashMap::new();
+        let mut statics = HashMap::new();
+        for member in members {
+          if let ClassMember::Method(method, params, body, is_static, hoisted) = member {
+            let function = Rc::new(Function {
+              name: Some(method.clone()),
+              parameters: params.clone(),
+              body: body.clone(),
+              hoisted: hoisted.clone(),
+              closure: self.environment.clone(),
+              file: self.current_file(),
+            });
+            let methods = if *is_static {
+              &mut statics
+            } else {
+              &mut methods
+            };
+            methods.insert(method.clone(), Value::Function(function));
+          }
+        }
+
+        let breed = Self::make_breed(name, parent, methods)?;
+        let Value::Object(obj) = &breed else {
+          unreachable!()
+        };
+        let parent = {
+          let mut obj = obj.borrow_mut();
+          let info = obj.breed_mut().expect("a breed");
+          info.statics = statics;
+          info.parent.clone()
+        };
+
+        // Static fields see the breed itself as `self`
+        let mut static_environment = Environment::with_enclosing(self.environment.clone());
+        static_environment.define("self", breed.clone(), true);
+        if let Some(parent) = parent {
+          static_environment.define("super", parent, true);
+        }
+        let static_environment = Rc::new(RefCell::new(static_environment));
+
+        for member in members {
+          if let ClassMember::StaticField(field, expr) = member {
+            let previous = std::mem::replace(&mut self.environment, static_environment.clone());
+            let value = self.evaluate(expr);
+            self.environment = previous;
+            obj.borrow_mut().insert(field.clone(), value?);
+          }
+        }
+
+        self.environment.borrow_mut().define(name, breed, false);
+
+        Ok(ControlFlow::Normal(Value::Undefined))
       }
-      Stmt::Return(value) => {
+      StmtKind::Return(value) => {
         let return_value = if let Some(expr) = value {
           self.evaluate(expr)?
         } else {
           Value::Undefined
         };
 
-        Err(MewError::runtime(format!("return:{}", return_value)))
+        Ok(ControlFlow::Return(return_value))
+      }
+      StmtKind::Break(label) => Ok(ControlFlow::Break(label.clone())),
+      StmtKind::Continue(label) => Ok(ControlFlow::Continue(label.clone())),
+      StmtKind::Import(names, specifier) => {
+        self.call_site = stmt.span.start;
+        for name in names {
+          let value = self.import_binding(specifier, name)?;
+          self.environment.borrow_mut().define(name, value, true);
+        }
+
+        Ok(ControlFlow::Normal(Value::Undefined))
+      }
+      StmtKind::Export(declaration) => {
+        let declaration = declaration.borrow();
+        let flow = self.execute(&declaration)?;
+
+        let name = match &declaration.kind {
+          StmtKind::VarDeclaration(name, _, _)
+          | StmtKind::Function(name, ..)
+          | StmtKind::Class(name, _, _) => name,
+          _ => unreachable!(),
+        };
+        self.export(name);
+
+        Ok(flow)
       }
-      Stmt::Break => Err(MewError::runtime("break")),
-      Stmt::Continue => Err(MewError::runtime("continue")),
-      Stmt::Switch(expr, cases) => {
+      StmtKind::Try(body, catch_clause, finally_block) => {
+        let mut outcome =
+          self.execute_block(body, Environment::with_enclosing(self.environment.clone()));
+
+        if let Some((binding, handler)) = catch_clause {
+          outcome = match outcome {
+            // The user or host asked to stop, so the program doesn't get a say
+            Err(error) if !error.is_catchable() => Err(error),
+            Err(error) => {
+              let mut environment = Environment::with_enclosing(self.environment.clone());
+              if let Some(name) = binding {
+                environment.define(name, Self::error_to_value(error), false);
+              }
+              self.execute_block(handler, environment)
+            }
+            outcome => outcome,
+          };
+        }
+
+        if let Some(finally_block) = finally_block {
+          // A jump out of the 'furever' block overrides the earlier outcome
+          match self.execute_block(
+            finally_block,
+            Environment::with_enclosing(self.environment.clone()),
+          )? {
+            ControlFlow::Normal(_) => {}
+            flow => return Ok(flow),
+          }
+        }
+
+        outcome
+      }
+      StmtKind::Throw(expr) => {
+        let value = self.evaluate(expr)?;
+        Err(MewError::Thrown(value, Span::unknown()))
+      }
+      StmtKind::Switch(expr, cases) => {
         let value = self.evaluate(expr)?;
         let mut default_case = None;
-        let mut _matched = false;
+        let mut selected = None;
 
         for (case_value, statements) in cases {
           if let Some(case_expr) = case_value {
             let case_result = self.evaluate(case_expr)?;
 
             if self.is_equal(&value, &case_result) {
-              _matched = true;
-              let result = self.execute_statements(statements)?;
-              return Ok(result);
+              selected = Some(statements);
+              break;
             }
           } else {
             default_case = Some(statements);
           }
         }
 
-        if !_matched && default_case.is_some() {
-          return self.execute_statements(default_case.unwrap());
+        match selected.or(default_case) {
+          // An unlabeled 'clawt' only leaves the switch itself
+          Some(statements) => match self.execute_block(
+            statements,
+            Environment::with_enclosing(self.environment.clone()),
+          )? {
+            ControlFlow::Break(None) => Ok(ControlFlow::Normal(Value::Undefined)),
+            flow => Ok(flow),
+          },
+          None => Ok(ControlFlow::Normal(Value::Undefined)),
+        }
+      }
+    }
+  }
+
+  fn execute_loop(&mut self, stmt: &Stmt, label: Option<&str>) -> MewResult<ControlFlow> {
+    match &stmt.kind {
+      StmtKind::While(condition, body) => {
+        let mut result = Value::Undefined;
+
+        while self.evaluate(condition)?.is_truthy() {
+          match Self::loop_signal(self.execute(&body.borrow())?, label) {
+            LoopSignal::Next(value) => result = value,
+            LoopSignal::Exit => break,
+            LoopSignal::Propagate(flow) => return Ok(flow),
+          }
         }
 
-        Ok(Value::Undefined)
+        Ok(ControlFlow::Normal(result))
       }
+      StmtKind::DoWhile(body, condition) => {
+        let mut result = Value::Undefined;
+
+        loop {
+          match Self::loop_signal(self.execute(&body.borrow())?, label) {
+            LoopSignal::Next(value) => result = value,
+            LoopSignal::Exit => break,
+            LoopSignal::Propagate(flow) => return Ok(flow),
+          }
+
+          if !self.evaluate(condition)?.is_truthy() {
+            break;
+          }
+        }
+
+        Ok(ControlFlow::Normal(result))
+      }
+      StmtKind::For(initializer, condition, increment, body) => {
+        let previous = self.environment.clone();
+        self.environment = Rc::new(RefCell::new(Environment::with_enclosing(previous.clone())));
+
+        let result = self.execute_for(initializer, condition, increment, body, label);
+
+        self.environment = previous;
+
+        result
+      }
+      StmtKind::ForIn(name, is_const, is_of, iterable, body) => {
+        let iterable = self.evaluate(iterable)?;
+        let items = if *is_of {
+          Self::native_object_values(self, vec![iterable])?
+        } else {
+          Self::native_object_keys(self, vec![iterable])?
+        };
+
+        let items = match &items {
+          Value::Array(items) => items.borrow().clone(),
+          _ => unreachable!(),
+        };
+
+        let mut result = Value::Undefined;
+
+        for item in items {
+          let mut environment = Environment::with_enclosing(self.environment.clone());
+          environment.define(name, item, *is_const);
+
+          let previous = self.environment.clone();
+          self.environment = Rc::new(RefCell::new(environment));
+          let flow = self.execute(&body.borrow());
+          self.environment = previous;
+
+          match Self::loop_signal(flow?, label) {
+            LoopSignal::Next(value) => result = value,
+            LoopSignal::Exit => break,
+            LoopSignal::Propagate(flow) => return Ok(flow),
+          }
+        }
+
+        Ok(ControlFlow::Normal(result))
+      }
+      _ => self.execute(stmt),
     }
   }
 
+  fn execute_for(
+    &mut self,
+    initializer: &Option<Rc<RefCell<Stmt>>>,
+    condition: &Expr,
+    increment: &Option<Expr>,
+    body: &Rc<RefCell<Stmt>>,
+    label: Option<&str>,
+  ) -> MewResult<ControlFlow> {
+    if let Some(init) = initializer {
+      if let flow @ (ControlFlow::Return(_) | ControlFlow::Break(_) | ControlFlow::Continue(_)) =
+        self.execute(&init.borrow())?
+      {
+        return Ok(flow);
+      }
+    }
+
+    let mut result = Value::Undefined;
+
+    while self.evaluate(condition)?.is_truthy() {
+      match Self::loop_signal(self.execute(&body.borrow())?, label) {
+        LoopSignal::Next(value) => result = value,
+        LoopSignal::Exit => break,
+        LoopSignal::Propagate(flow) => return Ok(flow),
+      }
+
+      if let Some(increment) = increment {
+        self.evaluate(increment)?;
+      }
+    }
+
+    Ok(ControlFlow::Normal(result))
+  }
+
+  // Decide what a loop should do with the outcome of one iteration of its body
+  fn loop_signal(flow: ControlFlow, label: Option<&str>) -> LoopSignal {
+    match flow {
+      ControlFlow::Normal(value) => LoopSignal::Next(value),
+      ControlFlow::Break(None) => LoopSignal::Exit,
+      ControlFlow::Break(Some(target)) if Some(target.as_str()) == label => LoopSignal::Exit,
+      ControlFlow::Continue(None) => LoopSignal::Next(Value::Undefined),
+      ControlFlow::Continue(Some(target)) if Some(target.as_str()) == label => {
+        LoopSignal::Next(Value::Undefined)
+      }
+      flow => LoopSignal::Propagate(flow),
+    }
+  }
+
+  // Convert an error into the value bound by a 'catch' clause. Thrown values
+  // are passed through untouched; internal errors become error objects.
+  pub(crate) fn error_to_value(error: MewError) -> Value {
+    if let MewError::Thrown(value, _) = error {
+      return value;
+    }
+
+    let location = error.location().unwrap_or_else(Location::unknown);
+    let mut object = HashMap::new();
+    object.insert(
+      "kind".to_string(),
+      Value::String(error.kind_name().to_string()),
+    );
+    object.insert("message".to_string(), Value::String(error.message()));
+    object.insert("line".to_string(), Value::Number(location.line as f64));
+    object.insert("column".to_string(), Value::Number(location.column as f64));
+
+    Value::object(object)
+  }
+
+  fn stray_control_flow(flow: ControlFlow) -> MewError {
+    match flow {
+      ControlFlow::Return(_) => MewError::runtime("'return' used outside of a function"),
+      ControlFlow::Break(None) => MewError::runtime("'clawt' used outside of a loop or catwalk"),
+      ControlFlow::Continue(None) => MewError::runtime("'meownext' used outside of a loop"),
+      ControlFlow::Break(Some(label)) | ControlFlow::Continue(Some(label)) => {
+        MewError::runtime(format!("Undefined label '{}'", label))
+      }
+      ControlFlow::Normal(_) => unreachable!(),
+    }
+  }
+
+  // Give an error the span of the node it was raised by, unless a node
+  // nested inside it already did, and remember the call stack at that point
+  fn locate(&mut self, error: MewError, span: Span) -> MewError {
+    if error.span() == Some(Span::unknown()) {
+      self.record_traceback(span.start);
+      error.at(span)
+    } else {
+      error
+    }
+  }
+
+  // Remember the call stack for an error that knew its location from the
+  // start, like a syntax error in an imported module
+  fn record(&mut self, error: MewError) -> MewError {
+    if let Some(location) = error.location() {
+      self.record_traceback(location);
+    }
+    error
+  }
+
+  pub(crate) fn record_traceback(&mut self, location: Location) {
+    let mut frames = Vec::new();
+
+    // Code run by `eval` has no module frame of its own
+    if !matches!(
+      self.calls.first(),
+      Some(CallFrame {
+        code: Code::Module(_),
+        ..
+      })
+    ) {
+      frames.push(StackFrame {
+        function: None,
+        file: None,
+        location,
+      });
+    }
+
+    for call in &self.calls {
+      if let Some(caller) = frames.last_mut() {
+        caller.location = call.call_site;
+      }
+      frames.push(call.stack_frame(location));
+    }
+
+    self.traceback = frames;
+  }
+
+  // The file of the code running now, which functions defined by it
+  // remember for tracebacks
+  pub(crate) fn current_file(&self) -> Option<Rc<Path>> {
+    self.calls.last().and_then(|call| call.file().cloned())
+  }
+
+  // `cat` declarations run before the rest of their block, so functions
+  // can be called above the place they are declared
+  fn hoist_functions(&mut self, statements: &[Rc<RefCell<Stmt>>]) -> MewResult<()> {
+    for statement in statements {
+      let statement = statement.borrow();
+      if statement.declares_function() {
+        self.execute(&statement)?;
+      }
+    }
+    Ok(())
+  }
+
   fn execute_block(
     &mut self,
     statements: &[Rc<RefCell<Stmt>>],
     environment: Environment,
-  ) -> MewResult<Value> {
+  ) -> MewResult<ControlFlow> {
     let previous = self.environment.clone();
     self.environment = Rc::new(RefCell::new(environment));
 
@@ -658,104 +1654,79 @@ impl Interpreter {
     result
   }
 
-  fn execute_statements(&mut self, statements: &[Rc<RefCell<Stmt>>]) -> MewResult<Value> {
+  fn execute_statements(&mut self, statements: &[Rc<RefCell<Stmt>>]) -> MewResult<ControlFlow> {
+    self.hoist_functions(statements)?;
+
     let mut result = Value::Undefined;
 
     for statement in statements {
-      match self.execute(&statement.borrow()) {
-        Ok(value) => result = value,
-        Err(MewError::Runtime(msg, _)) if msg.starts_with("return:") => {
-          let value_str = msg.trim_start_matches("return:");
-
-          if value_str == "undefined" {
-            return Ok(Value::Undefined);
-          } else if value_str == "null" {
-            return Ok(Value::Null);
-          } else if value_str == "true" {
-            return Ok(Value::Bool(true));
-          } else if value_str == "false" {
-            return Ok(Value::Bool(false));
-          } else if let Ok(num) = value_str.parse::<f64>() {
-            return Ok(Value::Number(num));
-          } else {
-            let s = value_str
-              .trim_start_matches('"')
-              .trim_end_matches('"')
-              .to_string();
-            return Ok(Value::String(s));
-          }
-        }
-        Err(e) => return Err(e),
+      let statement = statement.borrow();
+      if statement.declares_function() {
+        result = Value::Undefined;
+        continue;
+      }
+
+      match self.execute(&statement)? {
+        ControlFlow::Normal(value) => result = value,
+        flow => return Ok(flow),
       }
     }
 
-    Ok(result)
+    Ok(ControlFlow::Normal(result))
   }
 
   fn evaluate(&mut self, expr: &Expr) -> MewResult<Value> {
-    match expr {
-      Expr::Literal(value) => Ok(value.clone()),
-      Expr::Variable(name) => self.environment.borrow().get(name),
-      Expr::Assignment(name, value) => {
-        let value = self.evaluate(&*value)?;
-        self.environment.borrow_mut().assign(name, value.clone())?;
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.evaluate_expr(expr))
+      .map_err(|error| self.locate(error, expr.span))
+  }
+
+  fn evaluate_expr(&mut self, expr: &Expr) -> MewResult<Value> {
+    match &expr.kind {
+      ExprKind::Literal(value) => Ok(value.clone()),
+      ExprKind::Variable(name, slot) => self.look_up_variable(name, *slot),
+      ExprKind::Assignment(name, value, slot) => {
+        let value = self.evaluate(value)?;
+        self.assign_variable(name, *slot, value.clone())?;
         Ok(value)
       }
-      Expr::Binary(left, op, right) => {
-        let left = self.evaluate(&*left)?;
-        let right = self.evaluate(&*right)?;
-
-        match (op, &left, &right) {
-          (BinaryOp::Add, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
-          (BinaryOp::Sub, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
-          (BinaryOp::Mul, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a * b)),
-          (BinaryOp::Div, Value::Number(a), Value::Number(b)) => {
-            if *b == 0.0 {
-              Ok(Value::Number(f64::INFINITY))
-            } else {
-              Ok(Value::Number(a / b))
-            }
-          }
-          (BinaryOp::Mod, Value::Number(a), Value::Number(b)) => {
-            if *b == 0.0 {
-              Ok(Value::Number(f64::NAN))
-            } else {
-              Ok(Value::Number(a % b))
-            }
-          }
-
-          (BinaryOp::Add, Value::String(a), _) => Ok(Value::String(format!("{}{}", a, right))),
-          (BinaryOp::Add, _, Value::String(b)) => Ok(Value::String(format!("{}{}", left, b))),
-
-          (BinaryOp::Eq, _, _) => Ok(Value::Bool(self.is_equal(&left, &right))),
-          (BinaryOp::NotEq, _, _) => Ok(Value::Bool(!self.is_equal(&left, &right))),
-          (BinaryOp::Lt, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a < b)),
-          (BinaryOp::Lte, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a <= b)),
-          (BinaryOp::Gt, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a > b)),
-          (BinaryOp::Gte, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a >= b)),
+      ExprKind::CompoundAssignment(target, op, value) => {
+        let place = self.resolve_place(target)?;
+        let current = self.read_place(&place)?;
+        let value = self.evaluate(value)?;
+
+        let result = self.apply_binary(*op, &current, &value)?;
+        self.check_size(&result)?;
+        self.write_place(&place, result.clone())?;
+        Ok(result)
+      }
+      ExprKind::Binary(left, op, right) => {
+        let left = self.evaluate(left)?;
+
+        // Logical operators short-circuit and yield the deciding operand
+        match op {
+          BinaryOp::And if !left.is_truthy() => return Ok(left),
+          BinaryOp::Or if left.is_truthy() => return Ok(left),
+          BinaryOp::Nullish if !matches!(left, Value::Null | Value::Undefined) => return Ok(left),
+          BinaryOp::And | BinaryOp::Or | BinaryOp::Nullish => return self.evaluate(right),
+          _ => {}
+        }
 
-          (BinaryOp::And, _, _) => Ok(Value::Bool(left.is_truthy() && right.is_truthy())),
-          (BinaryOp::Or, _, _) => Ok(Value::Bool(left.is_truthy() || right.is_truthy())),
+        let right = self.evaluate(right)?;
 
-          // Type errors
-          _ => Err(MewError::type_error(format!(
-            "Cannot apply operator {:?} to {} and {}",
-            op,
-            left.type_name(),
-            right.type_name()
-          ))),
-        }
+        let result = self.apply_binary(*op, &left, &right)?;
+        self.check_size(&result)?;
+        Ok(result)
       }
-      Expr::Ternary(condition, then_expr, else_expr) => {
-        let condition_value = self.evaluate(&*condition)?;
+      ExprKind::Ternary(condition, then_expr, else_expr) => {
+        let condition_value = self.evaluate(condition)?;
         if condition_value.is_truthy() {
-          self.evaluate(&*then_expr)
+          self.evaluate(then_expr)
         } else {
-          self.evaluate(&*else_expr)
+          self.evaluate(else_expr)
         }
       }
-      Expr::Unary(op, expr) => {
-        let right = self.evaluate(&*expr)?;
+      ExprKind::Unary(op, expr) => {
+        let right = self.evaluate(expr)?;
 
         match (op, &right) {
           (UnaryOp::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
@@ -770,445 +1741,405 @@ impl Interpreter {
           ))),
         }
       }
-      Expr::Call(callee, arguments) => {
-        if let Expr::Get(object, _name) = &**callee {
-          let object_value = self.evaluate(object)?;
-          let method = self.evaluate(callee)?;
-
-          let mut args = Vec::new();
-
-          if let Value::NativeFunction(native) = &method {
-            if native.name == "toString" {
-              args.push(object_value.clone());
-            }
-          }
-
-          for arg in arguments {
-            args.push(self.evaluate(arg)?);
-          }
+      ExprKind::Call(..) | ExprKind::Get(..) | ExprKind::Index(..) | ExprKind::Optional(..) => {
+        Ok(self.evaluate_chain(expr)?.unwrap_or(Value::Undefined))
+      }
+      ExprKind::Grouping(inner) => self.evaluate(inner),
+      ExprKind::Set(object, name, value_expr) => {
+        let object_value = self.evaluate(object)?;
+        let value = self.evaluate(value_expr)?;
+        self.set_property(&object_value, name, value.clone())?;
+        Ok(value)
+      }
+      ExprKind::SetIndex(object, index, value_expr) => {
+        let object_value = self.evaluate(object)?;
+        let index_value = self.evaluate(index)?;
+        let value = self.evaluate(value_expr)?;
+        self.set_index(&object_value, &index_value, value.clone())?;
+        Ok(value)
+      }
+      ExprKind::ArrayLiteral(elements) => {
+        let mut array = Vec::new();
+
+        for element in elements {
+          array.push(self.evaluate(element)?);
+        }
+
+        self.check_collection_size(array.len())?;
+        Ok(Value::array(array))
+      }
+      ExprKind::ObjectLiteral(properties) => {
+        let mut object = HashMap::new();
+
+        for (key, expr) in properties {
+          object.insert(key.clone(), self.evaluate(expr)?);
+        }
+
+        self.check_collection_size(object.len())?;
+        Ok(Value::object(object))
+      }
+      ExprKind::Function(name, params, body, hoisted) => {
+        let function = Rc::new(Function {
+          name: name.clone(),
+          parameters: params.clone(),
+          body: body.clone(),
+          hoisted: hoisted.clone(),
+          closure: self.environment.clone(),
+          file: self.current_file(),
+        });
+
+        Ok(Value::Function(function))
+      }
+      ExprKind::Super(name) => {
+        let parent = self
+          .environment
+          .borrow()
+          .get("super")
+          .map_err(|_| MewError::runtime("'super' used outside of a breed with a parent"))?;
+        let receiver = self.environment.borrow().get("self")?;
+        self.get_super(&parent, receiver, name)
+      }
+      ExprKind::Increment(target, is_prefix) => self.step_number(target, 1.0, *is_prefix),
+      ExprKind::Decrement(target, is_prefix) => self.step_number(target, -1.0, *is_prefix),
+    }
+  }
+
+  // Evaluate a property, index or call chain. Returns `None` when a `?.`
+  // met null or undefined, which skips the rest of the chain.
+  fn evaluate_chain(&mut self, expr: &Expr) -> MewResult<Option<Value>> {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+      self.evaluate_chain_expr(expr)
+    })
+  }
+
+  fn evaluate_chain_expr(&mut self, expr: &Expr) -> MewResult<Option<Value>> {
+    match &expr.kind {
+      ExprKind::Optional(object) => match self.evaluate_chain(object)? {
+        None | Some(Value::Null | Value::Undefined) => Ok(None),
+        value => Ok(value),
+      },
+      ExprKind::Get(object, name) => match self.evaluate_chain(object)? {
+        Some(object_value) => self.get_property(&object_value, name).map(Some),
+        None => Ok(None),
+      },
+      ExprKind::Index(object, index) => match self.evaluate_chain(object)? {
+        Some(object_value) => {
+          let index_value = self.evaluate(index)?;
+          self.get_index(&object_value, &index_value).map(Some)
+        }
+        None => Ok(None),
+      },
+      ExprKind::Call(callee, arguments) => {
+        let Some(function) = self.evaluate_chain(callee)? else {
+          return Ok(None);
+        };
+
+        let mut args = Vec::new();
+        for arg in arguments {
+          args.push(self.evaluate(arg)?);
+        }
+
+        self.call_site = expr.span.start;
+        self.call_function(function, args).map(Some)
+      }
+      _ => self.evaluate(expr).map(Some),
+    }
+  }
+
+  pub(crate) fn apply_binary(&self, op: BinaryOp, left: &Value, right: &Value) -> MewResult<Value> {
+    match (op, left, right) {
+      (BinaryOp::Add, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+      (BinaryOp::Sub, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
+      (BinaryOp::Mul, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a * b)),
+      (BinaryOp::Div, Value::Number(a), Value::Number(b)) => {
+        if *b == 0.0 {
+          Ok(Value::Number(f64::INFINITY))
+        } else {
+          Ok(Value::Number(a / b))
+        }
+      }
+      (BinaryOp::Mod, Value::Number(a), Value::Number(b)) => {
+        if *b == 0.0 {
+          Ok(Value::Number(f64::NAN))
+        } else {
+          Ok(Value::Number(a % b))
+        }
+      }
+
+      (BinaryOp::Add, Value::String(_), _) | (BinaryOp::Add, _, Value::String(_)) => Ok(
+        Value::String(self.format_bounded(format_args!("{}{}", left, right))?),
+      ),
+
+      (BinaryOp::Eq, _, _) => Ok(Value::Bool(self.is_equal(left, right))),
+      (BinaryOp::NotEq, _, _) => Ok(Value::Bool(!self.is_equal(left, right))),
+      (BinaryOp::Lt, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a < b)),
+      (BinaryOp::Lte, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a <= b)),
+      (BinaryOp::Gt, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a > b)),
+      (BinaryOp::Gte, Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a >= b)),
+
+      (BinaryOp::InstanceOf, _, _) if Self::is_breed(right) => {
+        Ok(Value::Bool(Self::is_instance_of(left, right)))
+      }
+      (BinaryOp::InstanceOf, _, _) => Err(MewError::type_error(format!(
+        "Right-hand side of 'breedof' must be a breed, got {}",
+        right.type_name()
+      ))),
+
+      // Type errors
+      _ => Err(MewError::type_error(format!(
+        "Cannot apply operator {:?} to {} and {}",
+        op,
+        left.type_name(),
+        right.type_name()
+      ))),
+    }
+  }
+
+  pub(crate) fn get_property(&mut self, object_value: &Value, name: &str) -> MewResult<Value> {
+    if name == "toString" {
+      return Ok(Self::bind_native(
+        "toString",
+        Self::native_to_string,
+        object_value.clone(),
+      ));
+    }
+
+    match object_value {
+      Value::Object(obj) => {
+        let (own, breed) = {
+          let obj = obj.borrow();
+          (obj.get(name).cloned(), obj.instance_of().cloned())
+        };
+
+        if let Some(value) = own {
+          return Ok(value);
+        }
+
+        // Instances fall back to their breed's methods, breeds to their
+        // static methods and the static members of their parents
+        let value = match breed {
+          Some(breed) => Self::find_method(&breed, name)
+            .map(|(method, owner)| Self::bind_method(&method, object_value.clone(), &owner)),
+          None => Self::find_static(object_value, object_value, name),
+        };
+        Ok(value.unwrap_or(Value::Undefined))
+      }
+      Value::Array(arr) => {
+        let arr = arr.borrow();
+        if name == "length" {
+          Ok(Value::Number(arr.len() as f64))
+        } else if let Ok(index) = name.parse::<usize>() {
+          if index < arr.len() {
+            Ok(arr[index].clone())
+          } else {
+            Err(MewError::runtime(format!("Index out of bounds: {}", index)))
+          }
+        } else if let Some(method) = array_methods::lookup(name) {
+          Ok(Self::bind_native(name, method, object_value.clone()))
+        } else {
+          Err(MewError::type_error(format!(
+            "Cannot access property '{}' of array",
+            name
+          )))
+        }
+      }
+      Value::String(s) => {
+        if name == "length" {
+          Ok(Value::Number(string_methods::length(s) as f64))
+        } else if let Some(method) = string_methods::lookup(name) {
+          Ok(Self::bind_native(name, method, object_value.clone()))
+        } else {
+          Err(MewError::type_error(format!(
+            "Cannot access property '{}' of string",
+            name
+          )))
+        }
+      }
+      _ => Err(MewError::type_error(format!(
+        "Cannot access property '{}' of {}",
+        name,
+        object_value.type_name()
+      ))),
+    }
+  }
+
+  pub(crate) fn set_property(
+    &mut self,
+    object_value: &Value,
+    name: &str,
+    value: Value,
+  ) -> MewResult<()> {
+    match object_value {
+      Value::Object(obj) => {
+        obj.borrow_mut().insert(name.to_string(), value);
+        self.check_collection_size(obj.borrow().len())
+      }
+      Value::Array(_) => match name.parse::<usize>() {
+        Ok(index) => self.set_index(object_value, &Value::Number(index as f64), value),
+        Err(_) => Err(MewError::type_error(format!(
+          "Cannot set property '{}' of array",
+          name
+        ))),
+      },
+      _ => Err(MewError::type_error(format!(
+        "Cannot set property '{}' of {}",
+        name,
+        object_value.type_name()
+      ))),
+    }
+  }
+
+  pub(crate) fn get_index(
+    &mut self,
+    object_value: &Value,
+    index_value: &Value,
+  ) -> MewResult<Value> {
+    match (object_value, index_value) {
+      (Value::Array(arr), Value::Number(n)) => {
+        let index = Self::array_index(*n)?;
+        let arr = arr.borrow();
+        if index < arr.len() {
+          Ok(arr[index].clone())
+        } else {
+          Err(MewError::runtime(format!("Index out of bounds: {}", index)))
+        }
+      }
+      (Value::String(s), Value::Number(n)) => {
+        let index = Self::array_index(*n)?;
+        string_methods::char_at_index(s, index)
+          .ok_or_else(|| MewError::runtime(format!("Index out of bounds: {}", index)))
+      }
+      _ => {
+        let key = Self::property_key(index_value)?;
+        self.get_property(object_value, &key)
+      }
+    }
+  }
+
+  pub(crate) fn set_index(
+    &mut self,
+    object_value: &Value,
+    index_value: &Value,
+    value: Value,
+  ) -> MewResult<()> {
+    match (object_value, index_value) {
+      (Value::Array(arr), Value::Number(n)) => {
+        let index = Self::array_index(*n)?;
+        let mut arr = arr.borrow_mut();
+        if index < arr.len() {
+          arr[index] = value;
+          Ok(())
+        } else {
+          Err(MewError::runtime(format!("Index out of bounds: {}", index)))
+        }
+      }
+      _ => {
+        let key = Self::property_key(index_value)?;
+        self.set_property(object_value, &key, value)
+      }
+    }
+  }
 
-          self.call_function(method, args)
-        } else {
-          // Normal function call (not a method call)
-          let callee_value = self.evaluate(&*callee)?;
+  fn array_index(n: f64) -> MewResult<usize> {
+    if n >= 0.0 && n.fract() == 0.0 {
+      Ok(n as usize)
+    } else {
+      Err(MewError::runtime(format!(
+        "Invalid array index: {}",
+        Value::Number(n)
+      )))
+    }
+  }
 
-          let mut args = Vec::new();
-          for arg in arguments {
-            args.push(self.evaluate(arg)?);
-          }
+  // Convert a value used inside `[...]` into a property name
+  fn property_key(index_value: &Value) -> MewResult<String> {
+    match index_value {
+      Value::String(key) => Ok(key.clone()),
+      Value::Number(_) => Ok(index_value.to_string()),
+      _ => Err(MewError::type_error(format!(
+        "Property name must be a string or number, got: {}",
+        index_value.type_name()
+      ))),
+    }
+  }
 
-          self.call_function(callee_value, args)
-        }
-      }
-      Expr::Get(object, name) => {
-        let object_value = self.evaluate(&*object)?;
+  // Locals go straight to the slot the resolver found; globals, and names
+  // the resolver couldn't see, are looked up by name
+  fn look_up_variable(&self, name: &str, slot: Option<Slot>) -> MewResult<Value> {
+    match slot {
+      Some(slot) => self.environment.borrow().get_at(slot, name),
+      None => self.environment.borrow().get(name),
+    }
+  }
 
-        if name == "toString" {
-          return Ok(Value::NativeFunction(Rc::new(NativeFunction {
-            name: "toString".to_string(),
-            function: Self::native_to_string,
-          })));
-        }
+  fn assign_variable(&mut self, name: &str, slot: Option<Slot>, value: Value) -> MewResult<()> {
+    match slot {
+      Some(slot) => self.environment.borrow_mut().assign_at(slot, name, value),
+      None => self.environment.borrow_mut().assign(name, value),
+    }
+  }
 
-        match object_value {
-          Value::Object(obj) => {
-            if name.starts_with('[') && name.ends_with(']') {
-              let key_str = &name[1..name.len() - 1];
-
-              if let Ok(expr_value) = self.evaluate(&Expr::Variable(key_str.to_string())) {
-                if let Value::String(key) = expr_value {
-                  if let Some(value) = obj.get(&key) {
-                    Ok(value.clone())
-                  } else {
-                    Ok(Value::Undefined)
-                  }
-                } else if let Value::Number(num) = expr_value {
-                  let key = num.to_string();
-                  if let Some(value) = obj.get(&key) {
-                    Ok(value.clone())
-                  } else {
-                    Ok(Value::Undefined)
-                  }
-                } else {
-                  Err(MewError::type_error(format!(
-                    "Object property name must be a string or number, got: {}",
-                    expr_value.type_name()
-                  )))
-                }
-              } else {
-                // Try other ways to evaluate the index
-                let key = key_str.to_string();
-                if let Some(value) = obj.get(&key) {
-                  Ok(value.clone())
-                } else {
-                  Ok(Value::Undefined)
-                }
-              }
-            } else if let Some(value) = obj.get(name) {
-              Ok(value.clone())
-            } else {
-              Ok(Value::Undefined)
-            }
-          }
-          Value::Array(arr) => {
-            if name == "length" {
-              Ok(Value::Number(arr.len() as f64))
-            } else if let Ok(index) = name.parse::<usize>() {
-              if index < arr.len() {
-                Ok(arr[index].clone())
-              } else {
-                Err(MewError::runtime(format!("Index out of bounds: {}", index)))
-              }
-            } else {
-              Err(MewError::type_error(format!(
-                "Cannot access property '{}' of array",
-                name
-              )))
-            }
-          }
-          Value::String(s) => {
-            if name == "length" {
-              Ok(Value::Number(s.len() as f64))
-            } else {
-              Err(MewError::type_error(format!(
-                "Cannot access property '{}' of string",
-                name
-              )))
-            }
-          }
-          _ => Err(MewError::type_error(format!(
-            "Cannot access property '{}' of {}",
-            name,
-            object_value.type_name()
-          ))),
-        }
+  // Evaluate the container (and index) of an assignment target exactly once
+  fn resolve_place(&mut self, target: &Expr) -> MewResult<Place> {
+    match &target.kind {
+      ExprKind::Variable(name, slot) => Ok(Place::Variable(name.clone(), *slot)),
+      ExprKind::Get(object, name) => Ok(Place::Property(self.evaluate(object)?, name.clone())),
+      ExprKind::Index(object, index) => {
+        let object_value = self.evaluate(object)?;
+        Ok(Place::Index(object_value, self.evaluate(index)?))
       }
-      Expr::Set(object, name, value_expr) => {
-        let object_value = self.evaluate(&*object)?;
-        let value = self.evaluate(&*value_expr)?;
+      _ => Err(MewError::syntax("Invalid assignment target")),
+    }
+  }
 
-        match object_value {
-          Value::Object(mut obj) => {
-            obj.insert(name.clone(), value.clone());
-            Ok(value)
-          }
-          Value::Array(mut arr) => {
-            if let Ok(index) = name.parse::<usize>() {
-              if index < arr.len() {
-                arr[index] = value.clone();
-                Ok(value)
-              } else {
-                Err(MewError::runtime(format!("Index out of bounds: {}", index)))
-              }
-            } else {
-              Err(MewError::type_error(format!(
-                "Cannot set property '{}' of array",
-                name
-              )))
-            }
-          }
-          _ => Err(MewError::type_error(format!(
-            "Cannot set property '{}' of {}",
-            name,
-            object_value.type_name()
-          ))),
-        }
-      }
-      Expr::ArrayLiteral(elements) => {
-        let mut array = Vec::new();
+  fn read_place(&mut self, place: &Place) -> MewResult<Value> {
+    match place {
+      Place::Variable(name, slot) => self.look_up_variable(name, *slot),
+      Place::Property(object, name) => self.get_property(object, name),
+      Place::Index(object, index) => self.get_index(object, index),
+    }
+  }
 
-        for element in elements {
-          array.push(self.evaluate(element)?);
-        }
+  fn write_place(&mut self, place: &Place, value: Value) -> MewResult<()> {
+    match place {
+      Place::Variable(name, slot) => self.assign_variable(name, *slot, value),
+      Place::Property(object, name) => self.set_property(object, name, value),
+      Place::Index(object, index) => self.set_index(object, index, value),
+    }
+  }
 
-        Ok(Value::Array(array))
-      }
-      Expr::ObjectLiteral(properties) => {
-        let mut object = HashMap::new();
+  // Shared implementation of prefix/postfix `++` and `--`
+  fn step_number(&mut self, target: &Expr, delta: f64, is_prefix: bool) -> MewResult<Value> {
+    let verb = if delta > 0.0 {
+      "increment"
+    } else {
+      "decrement"
+    };
 
-        for (key, expr) in properties {
-          object.insert(key.clone(), self.evaluate(expr)?);
-        }
+    let place = self.resolve_place(target)?;
+    let current = self.read_place(&place)?;
 
-        Ok(Value::Object(object))
-      }
-      Expr::Function(name, params, body) => {
-        let function = Rc::new(Function {
-          name: name.clone(),
-          parameters: params.clone(),
-          body: body.clone(),
-          closure: self.environment.clone(),
-        });
+    if let Value::Number(n) = current {
+      self.write_place(&place, Value::Number(n + delta))?;
 
-        Ok(Value::Function(function))
-      }
-      Expr::Increment(target, is_prefix) => {
-        // Handle both prefix (++x) and postfix (x++) increment
-        match &**target {
-          Expr::Variable(name) => {
-            let current = self.environment.borrow().get(name)?;
-
-            if let Value::Number(n) = current {
-              let new_value = Value::Number(n + 1.0);
-              self
-                .environment
-                .borrow_mut()
-                .assign(name, new_value.clone())?;
-
-              if *is_prefix {
-                // For prefix (++x), return the new value
-                Ok(new_value)
-              } else {
-                // For postfix (x++), return the original value
-                Ok(Value::Number(n))
-              }
-            } else {
-              Err(MewError::type_error(format!(
-                "Cannot increment a non-number value: {}",
-                current.type_name()
-              )))
-            }
-          }
-          Expr::Get(object, property_name) => {
-            let object_value = self.evaluate(object)?;
-
-            match object_value {
-              Value::Object(mut obj) => {
-                if let Some(value) = obj.get(property_name) {
-                  if let Value::Number(n) = value {
-                    let new_value = Value::Number(n + 1.0);
-                    let old_value = Value::Number(*n);
-                    obj.insert(property_name.clone(), new_value.clone());
-
-                    if *is_prefix {
-                      // For prefix (++obj.prop), return the new value
-                      Ok(new_value)
-                    } else {
-                      // For postfix (obj.prop++), return the original value
-                      Ok(old_value)
-                    }
-                  } else {
-                    Err(MewError::type_error(format!(
-                      "Cannot increment a non-number property: {}",
-                      property_name
-                    )))
-                  }
-                } else {
-                  Err(MewError::name(format!(
-                    "Property not found: {}",
-                    property_name
-                  )))
-                }
-              }
-              Value::Array(mut arr) => {
-                if property_name == "length" {
-                  return Err(MewError::type_error("Cannot increment array length"));
-                }
-
-                if let Ok(index) = property_name.parse::<usize>() {
-                  if index < arr.len() {
-                    if let Value::Number(n) = arr[index] {
-                      let new_value = Value::Number(n + 1.0);
-                      arr[index] = new_value.clone();
-
-                      if *is_prefix {
-                        Ok(new_value)
-                      } else {
-                        Ok(Value::Number(n))
-                      }
-                    } else {
-                      Err(MewError::type_error(format!(
-                        "Cannot increment a non-number array element"
-                      )))
-                    }
-                  } else {
-                    Err(MewError::runtime(format!("Index out of bounds: {}", index)))
-                  }
-                } else if property_name.starts_with('[') && property_name.ends_with(']') {
-                  let index_str = &property_name[1..property_name.len() - 1];
-                  if let Ok(expr_value) = self.evaluate(&Expr::Variable(index_str.to_string())) {
-                    if let Value::Number(n) = expr_value {
-                      let index = n as usize;
-                      if index < arr.len() {
-                        if let Value::Number(element_val) = arr[index] {
-                          let new_value = Value::Number(element_val + 1.0);
-                          arr[index] = new_value.clone();
-
-                          if *is_prefix {
-                            Ok(new_value)
-                          } else {
-                            Ok(Value::Number(element_val))
-                          }
-                        } else {
-                          Err(MewError::type_error(
-                            "Cannot increment a non-number array element",
-                          ))
-                        }
-                      } else {
-                        Err(MewError::runtime(format!("Index out of bounds: {}", index)))
-                      }
-                    } else {
-                      Err(MewError::type_error(format!(
-                        "Array index must be a number, got: {}",
-                        expr_value.type_name()
-                      )))
-                    }
-                  } else {
-                    Err(MewError::type_error(format!(
-                      "Invalid array index: {}",
-                      index_str
-                    )))
-                  }
-                } else {
-                  Err(MewError::type_error(format!(
-                    "Cannot access property '{}' of array",
-                    property_name
-                  )))
-                }
-              }
-              _ => Err(MewError::type_error(format!(
-                "Cannot access property of {}",
-                object_value.type_name()
-              ))),
-            }
-          }
-          _ => Err(MewError::syntax("Invalid increment target")),
-        }
-      }
-      Expr::Decrement(target, is_prefix) => {
-        match &**target {
-          Expr::Variable(name) => {
-            let current = self.environment.borrow().get(name)?;
-
-            if let Value::Number(n) = current {
-              let new_value = Value::Number(n - 1.0);
-              self
-                .environment
-                .borrow_mut()
-                .assign(name, new_value.clone())?;
-
-              if *is_prefix {
-                Ok(new_value)
-              } else {
-                Ok(Value::Number(n))
-              }
-            } else {
-              Err(MewError::type_error(format!(
-                "Cannot decrement a non-number value: {}",
-                current.type_name()
-              )))
-            }
-          }
-          Expr::Get(object, property_name) => {
-            let object_value = self.evaluate(object)?;
-
-            match object_value {
-              Value::Object(mut obj) => {
-                if let Some(value) = obj.get(property_name) {
-                  if let Value::Number(n) = value {
-                    let new_value = Value::Number(n - 1.0);
-                    let old_value = Value::Number(*n);
-                    obj.insert(property_name.clone(), new_value.clone());
-
-                    if *is_prefix {
-                      Ok(new_value)
-                    } else {
-                      Ok(old_value)
-                    }
-                  } else {
-                    Err(MewError::type_error(format!(
-                      "Cannot decrement a non-number property: {}",
-                      property_name
-                    )))
-                  }
-                } else {
-                  Err(MewError::name(format!(
-                    "Property not found: {}",
-                    property_name
-                  )))
-                }
-              }
-              Value::Array(mut arr) => {
-                if property_name == "length" {
-                  return Err(MewError::type_error("Cannot decrement array length"));
-                }
-
-                if let Ok(index) = property_name.parse::<usize>() {
-                  if index < arr.len() {
-                    if let Value::Number(n) = arr[index] {
-                      let new_value = Value::Number(n - 1.0);
-                      arr[index] = new_value.clone();
-
-                      if *is_prefix {
-                        Ok(new_value)
-                      } else {
-                        Ok(Value::Number(n))
-                      }
-                    } else {
-                      Err(MewError::type_error(format!(
-                        "Cannot decrement a non-number array element"
-                      )))
-                    }
-                  } else {
-                    Err(MewError::runtime(format!("Index out of bounds: {}", index)))
-                  }
-                } else if property_name.starts_with('[') && property_name.ends_with(']') {
-                  // Handle the dynamic array indexing case (similar to Get handling)
-                  let index_str = &property_name[1..property_name.len() - 1];
-                  if let Ok(expr_value) = self.evaluate(&Expr::Variable(index_str.to_string())) {
-                    if let Value::Number(n) = expr_value {
-                      let index = n as usize;
-                      if index < arr.len() {
-                        if let Value::Number(element_val) = arr[index] {
-                          let new_value = Value::Number(element_val - 1.0);
-                          arr[index] = new_value.clone();
-
-                          if *is_prefix {
-                            Ok(new_value)
-                          } else {
-                            Ok(Value::Number(element_val))
-                          }
-                        } else {
-                          Err(MewError::type_error(
-                            "Cannot decrement a non-number array element",
-                          ))
-                        }
-                      } else {
-                        Err(MewError::runtime(format!("Index out of bounds: {}", index)))
-                      }
-                    } else {
-                      Err(MewError::type_error(format!(
-                        "Array index must be a number, got: {}",
-                        expr_value.type_name()
-                      )))
-                    }
-                  } else {
-                    Err(MewError::type_error(format!(
-                      "Invalid array index: {}",
-                      index_str
-                    )))
-                  }
-                } else {
-                  Err(MewError::type_error(format!(
-                    "Cannot access property '{}' of array",
-                    property_name
-                  )))
-                }
-              }
-              _ => Err(MewError::type_error(format!(
-                "Cannot access property of {}",
-                object_value.type_name()
-              ))),
-            }
-          }
-          _ => Err(MewError::syntax("Invalid decrement target")),
-        }
+      if is_prefix {
+        // For prefix (++x), return the new value
+        Ok(Value::Number(n + delta))
+      } else {
+        // For postfix (x++), return the original value
+        Ok(Value::Number(n))
       }
+    } else {
+      Err(MewError::type_error(format!(
+        "Cannot {} a non-number value: {}",
+        verb,
+        current.type_name()
+      )))
     }
   }
 
-  fn call_function(&mut self, callee: Value, arguments: Vec<Value>) -> MewResult<Value> {
-    match callee {
+  pub(crate) fn call_function(&mut self, callee: Value, arguments: Vec<Value>) -> MewResult<Value> {
+    match &callee {
       Value::Function(function) => {
         if arguments.len() != function.parameters.len() {
           return Err(MewError::runtime(format!(
@@ -1223,34 +2154,37 @@ impl Interpreter {
         for (i, param) in function.parameters.iter().enumerate() {
           environment.define(param, arguments[i].clone(), false);
         }
+        for name in &function.hoisted {
+          environment.hoist(name);
+        }
 
-        match self.execute_block(&function.body, environment) {
-          Ok(value) => Ok(value),
-          Err(MewError::Runtime(msg, _)) if msg.starts_with("return:") => {
-            let value_str = msg.trim_start_matches("return:");
-
-            if value_str == "undefined" {
-              Ok(Value::Undefined)
-            } else if value_str == "null" {
-              Ok(Value::Null)
-            } else if value_str == "true" {
-              Ok(Value::Bool(true))
-            } else if value_str == "false" {
-              Ok(Value::Bool(false))
-            } else if let Ok(num) = value_str.parse::<f64>() {
-              Ok(Value::Number(num))
-            } else {
-              let s = value_str
-                .trim_start_matches('"')
-                .trim_end_matches('"')
-                .to_string();
-              Ok(Value::String(s))
-            }
+        if let Some(max) = self.limits.max_call_depth {
+          if self.call_depth >= max {
+            self.record_traceback(self.call_site);
+            return Err(MewError::LimitExceeded(Limit::CallDepth(max)));
           }
-          Err(e) => Err(e),
+        }
+
+        self.call_depth += 1;
+        self.calls.push(CallFrame::new(
+          Code::Function(function.clone()),
+          self.call_site,
+        ));
+        let outcome = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+          self.execute_block(&function.body, environment)
+        });
+        self.calls.pop();
+        self.call_depth -= 1;
+
+        match outcome? {
+          ControlFlow::Normal(_) => Ok(Value::Undefined),
+          ControlFlow::Return(value) => Ok(value),
+          flow => Err(Self::stray_control_flow(flow)),
         }
       }
-      Value::NativeFunction(native) => (native.function)(arguments),
+      Value::NativeFunction(native) => (native.function)(self, arguments),
+      Value::Closure(closure) => self.call_closure(closure.clone(), arguments),
+      _ if Self::is_breed(&callee) => self.construct(callee, arguments),
       _ => Err(MewError::type_error(format!(
         "Can only call functions and classes, got {}",
         callee.type_name()
@@ -1258,7 +2192,210 @@ impl Interpreter {
     }
   }
 
-  fn is_equal(&self, a: &Value, b: &Value) -> bool {
+  // Bind a built-in method to the value it was accessed on. The method
+  // receives that value as its first argument.
+  fn bind_native(name: &str, method: NativeFunctionType, receiver: Value) -> Value {
+    Value::native_function(name, move |interpreter, args| {
+      let mut full_args = Vec::with_capacity(args.len() + 1);
+      full_args.push(receiver.clone());
+      full_args.extend(args);
+      let result = method(interpreter, full_args)?;
+
+      // Methods like `push` grow their receiver in place
+      interpreter.check_size(&receiver)?;
+      interpreter.check_size(&result)?;
+      Ok(result)
+    })
+  }
+
+  // Create an instance of a breed and run its (possibly inherited) init
+  fn construct(&mut self, breed: Value, arguments: Vec<Value>) -> MewResult<Value> {
+    let instance = Value::Object(Rc::new(RefCell::new(Object::new(
+      HashMap::new(),
+      ObjectKind::Instance(breed.clone()),
+    ))));
+
+    match Self::find_method(&breed, "init") {
+      Some((init, owner)) => {
+        let init = Self::bind_method(&init, instance.clone(), &owner);
+        self.call_function(init, arguments)?;
+      }
+      None if !arguments.is_empty() => {
+        return Err(MewError::runtime(format!(
+          "Expected 0 arguments but got {}",
+          arguments.len()
+        )))
+      }
+      None => {}
+    }
+
+    Ok(instance)
+  }
+
+  // Build a breed object from its instance methods
+  pub(crate) fn make_breed(
+    name: &str,
+    parent: Option<Value>,
+    methods: HashMap<String, Value>,
+  ) -> MewResult<Value> {
+    if let Some(parent) = &parent {
+      if !Self::is_breed(parent) {
+        return Err(MewError::type_error(format!(
+          "Breed '{}' can only inherit from a breed, got {}",
+          name,
+          parent.type_name()
+        )));
+      }
+    }
+
+    let breed = Breed {
+      name: name.to_string(),
+      methods,
+      statics: HashMap::new(),
+      parent,
+    };
+    Ok(Value::Object(Rc::new(RefCell::new(Object::new(
+      HashMap::new(),
+      ObjectKind::Breed(breed),
+    )))))
+  }
+
+  // Look up `name` on the parent breed for `super.name` inside a method
+  pub(crate) fn get_super(
+    &mut self,
+    parent: &Value,
+    receiver: Value,
+    name: &str,
+  ) -> MewResult<Value> {
+    // Static methods reach the parent's static members, and instance
+    // methods its instance methods
+    let found = if Self::is_breed(&receiver) {
+      Self::find_static(parent, &receiver, name)
+    } else {
+      Self::find_method(parent, name)
+        .map(|(method, owner)| Self::bind_method(&method, receiver, &owner))
+    };
+    if let Some(value) = found {
+      return Ok(value);
+    }
+
+    match self.get_property(parent, name)? {
+      Value::Undefined => Err(MewError::runtime(format!(
+        "Parent breed has no method '{}'",
+        name
+      ))),
+      value => Ok(value),
+    }
+  }
+
+  fn is_breed(value: &Value) -> bool {
+    match value {
+      Value::Object(obj) => obj.borrow().breed().is_some(),
+      _ => false,
+    }
+  }
+
+  fn is_instance_of(value: &Value, breed: &Value) -> bool {
+    let Value::Object(instance) = value else {
+      return false;
+    };
+
+    let mut current = instance.borrow().instance_of().cloned();
+    while let Some(Value::Object(candidate)) = &current {
+      if let Value::Object(target) = breed {
+        if Rc::ptr_eq(candidate, target) {
+          return true;
+        }
+      }
+      let parent = candidate.borrow().breed().and_then(|b| b.parent.clone());
+      current = parent;
+    }
+
+    false
+  }
+
+  // Look up an instance method on a breed or its ancestors. Also returns the
+  // breed that defines it, which determines what `super` means inside it.
+  fn find_method(breed: &Value, name: &str) -> Option<(Value, Value)> {
+    let mut current = breed.clone();
+
+    loop {
+      let parent = match &current {
+        Value::Object(obj) => {
+          let obj = obj.borrow();
+          let breed = obj.breed()?;
+          if let Some(method) = breed.methods.get(name) {
+            return Some((method.clone(), current.clone()));
+          }
+          breed.parent.clone()
+        }
+        _ => None,
+      };
+
+      current = parent?;
+    }
+  }
+
+  // Look up a static member on a breed or its ancestors. Static methods are
+  // bound to `receiver`, so an inherited one sees the breed it was called on.
+  fn find_static(breed: &Value, receiver: &Value, name: &str) -> Option<Value> {
+    let mut current = breed.clone();
+
+    loop {
+      let parent = match &current {
+        Value::Object(obj) => {
+          let obj = obj.borrow();
+          if let Some(value) = obj.get(name) {
+            return Some(value.clone());
+          }
+          let breed = obj.breed()?;
+          if let Some(method) = breed.statics.get(name) {
+            return Some(Self::bind_method(method, receiver.clone(), &current));
+          }
+          breed.parent.clone()
+        }
+        _ => None,
+      };
+
+      current = parent?;
+    }
+  }
+
+  // Wrap a method in a closure where `self` is the receiver and `super` is
+  // the parent of the breed that defines the method
+  fn bind_method(method: &Value, receiver: Value, owner: &Value) -> Value {
+    let parent = match owner {
+      Value::Object(owner) => owner.borrow().breed().and_then(|b| b.parent.clone()),
+      _ => None,
+    };
+
+    let method = match method {
+      Value::Function(method) => method,
+      // Compiled methods take `self` and `super` in their first slots
+      Value::Closure(method) => {
+        let parent = parent.unwrap_or(Value::Undefined);
+        return Value::Closure(Rc::new(method.bind(receiver, parent)));
+      }
+      _ => unreachable!(),
+    };
+
+    let mut environment = Environment::with_enclosing(method.closure.clone());
+    environment.define("self", receiver, true);
+    if let Some(parent) = parent {
+      environment.define("super", parent, true);
+    }
+
+    Value::Function(Rc::new(Function {
+      name: method.name.clone(),
+      parameters: method.parameters.clone(),
+      body: method.body.clone(),
+      hoisted: method.hoisted.clone(),
+      closure: Rc::new(RefCell::new(environment)),
+      file: method.file.clone(),
+    }))
+  }
+
+  pub(crate) fn is_equal(&self, a: &Value, b: &Value) -> bool {
     match (a, b) {
       (Value::Null, Value::Null) => true,
       (Value::Undefined, Value::Undefined) => true,
@@ -1271,11 +2408,17 @@ impl Interpreter {
       }
       (Value::Bool(a), Value::Bool(b)) => a == b,
       (Value::String(a), Value::String(b)) => a == b,
-      _ => std::ptr::eq(a, b),
+      // Arrays, objects and functions compare by identity
+      (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
+      (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+      (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+      (Value::NativeFunction(a), Value::NativeFunction(b)) => Rc::ptr_eq(a, b),
+      (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+      _ => false,
     }
   }
 
-  fn native_mewth_pounce(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_pounce(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Mewth.pounce requires exactly one number argument",
@@ -1291,7 +2434,7 @@ impl Interpreter {
     }
   }
 
-  fn native_mewth_leap(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_leap(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Mewth.leap requires exactly one number argument",
@@ -1307,7 +2450,7 @@ impl Interpreter {
     }
   }
 
-  fn native_mewth_curl(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_curl(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Mewth.curl requires exactly one number argument",
@@ -1323,7 +2466,7 @@ impl Interpreter {
     }
   }
 
-  fn native_mewth_lick(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_lick(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Mewth.lick requires exactly one number argument",
@@ -1339,7 +2482,7 @@ impl Interpreter {
     }
   }
 
-  fn native_mewth_alpha(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_alpha(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.is_empty() {
       return Err(MewError::runtime(
         "Mewth.alpha requires at least one number argument",
@@ -1367,7 +2510,7 @@ impl Interpreter {
     Ok(Value::Number(max))
   }
 
-  fn native_mewth_kitten(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_kitten(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.is_empty() {
       return Err(MewError::runtime(
         "Mewth.kitten requires at least one number argument",
@@ -1395,7 +2538,7 @@ impl Interpreter {
     Ok(Value::Number(min))
   }
 
-  fn native_mewth_chase(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_chase(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if !args.is_empty() {
       return Err(MewError::runtime("Mewth.chase doesn't take any arguments"));
     }
@@ -1405,7 +2548,7 @@ impl Interpreter {
     Ok(Value::Number(rng.random::<f64>()))
   }
 
-  fn native_mewth_dig(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_dig(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Mewth.dig requires exactly one number argument",
@@ -1428,7 +2571,7 @@ impl Interpreter {
     }
   }
 
-  fn native_mewth_scratch(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_scratch(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 2 {
       return Err(MewError::runtime(
         "Mewth.scratch requires exactly two number arguments",
@@ -1445,7 +2588,10 @@ impl Interpreter {
     }
   }
 
-  fn native_mewth_tail_direction(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewth_tail_direction(
+    _interpreter: &mut Interpreter,
+    args: Vec<Value>,
+  ) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "Mewth.tailDirection requires exactly one number argument",
@@ -1471,7 +2617,7 @@ impl Interpreter {
   }
 
   // CatTime native functions
-  fn native_cat_time_now(_args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_now(_interpreter: &mut Interpreter, _args: Vec<Value>) -> MewResult<Value> {
     let now = std::time::SystemTime::now()
       .duration_since(std::time::UNIX_EPOCH)
       .unwrap()
@@ -1479,7 +2625,10 @@ impl Interpreter {
     Ok(Value::Number(now as f64))
   }
 
-  fn native_cat_time_wake_up(_args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_wake_up(
+    _interpreter: &mut Interpreter,
+    _args: Vec<Value>,
+  ) -> MewResult<Value> {
     // Create a date object with current timestamp
     let now = std::time::SystemTime::now()
       .duration_since(std::time::UNIX_EPOCH)
@@ -1490,10 +2639,13 @@ impl Interpreter {
     let mut date_obj = HashMap::new();
     date_obj.insert("_timestamp".to_string(), Value::Number(now as f64));
 
-    Ok(Value::Object(date_obj))
+    Ok(Value::object(date_obj))
   }
 
-  fn native_cat_time_full_year(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_full_year(
+    _interpreter: &mut Interpreter,
+    args: Vec<Value>,
+  ) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.fullYear requires exactly one argument (date object)",
@@ -1502,7 +2654,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1524,7 +2676,7 @@ impl Interpreter {
     Ok(Value::Number(datetime.year() as f64))
   }
 
-  fn native_cat_time_month(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_month(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.month requires exactly one argument (date object)",
@@ -1533,7 +2685,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1556,7 +2708,7 @@ impl Interpreter {
     Ok(Value::Number((datetime.month() - 1) as f64))
   }
 
-  fn native_cat_time_day(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_day(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.day requires exactly one argument (date object)",
@@ -1565,7 +2717,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1587,7 +2739,7 @@ impl Interpreter {
     Ok(Value::Number(datetime.day() as f64))
   }
 
-  fn native_cat_time_weekday(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_weekday(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.weekday requires exactly one argument (date object)",
@@ -1596,7 +2748,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1621,7 +2773,7 @@ impl Interpreter {
     Ok(Value::Number(weekday as f64))
   }
 
-  fn native_cat_time_hours(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_hours(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.hours requires exactly one argument (date object)",
@@ -1630,7 +2782,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1652,7 +2804,7 @@ impl Interpreter {
     Ok(Value::Number(datetime.hour() as f64))
   }
 
-  fn native_cat_time_minutes(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_minutes(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.minutes requires exactly one argument (date object)",
@@ -1661,7 +2813,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1683,7 +2835,7 @@ impl Interpreter {
     Ok(Value::Number(datetime.minute() as f64))
   }
 
-  fn native_cat_time_seconds(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_seconds(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.seconds requires exactly one argument (date object)",
@@ -1692,7 +2844,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1714,7 +2866,10 @@ impl Interpreter {
     Ok(Value::Number(datetime.second() as f64))
   }
 
-  fn native_cat_time_milliseconds(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_milliseconds(
+    _interpreter: &mut Interpreter,
+    args: Vec<Value>,
+  ) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.milliseconds requires exactly one argument (date object)",
@@ -1723,7 +2878,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1744,7 +2899,7 @@ impl Interpreter {
     Ok(Value::Number(millis as f64))
   }
 
-  fn native_cat_time_to_meow(args: Vec<Value>) -> MewResult<Value> {
+  fn native_cat_time_to_meow(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "CatTime.toMeow requires exactly one argument (date object)",
@@ -1753,7 +2908,7 @@ impl Interpreter {
 
     // Extract timestamp from date object
     let timestamp = match &args[0] {
-      Value::Object(obj) => match obj.get("_timestamp") {
+      Value::Object(obj) => match obj.borrow().get("_timestamp") {
         Some(Value::Number(ts)) => *ts,
         _ => {
           return Err(MewError::runtime(
@@ -1779,7 +2934,7 @@ impl Interpreter {
   }
 
   // MewJ native functions
-  fn native_mewj_sniff(args: Vec<Value>) -> MewResult<Value> {
+  fn native_mewj_sniff(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.len() != 1 {
       return Err(MewError::runtime(
         "MewJ.sniff requires exactly one argument",
@@ -1787,10 +2942,12 @@ impl Interpreter {
     }
 
     match &args[0] {
-      Value::String(json_str) => match serde_json::from_str::<serde_json::Value>(json_str) {
-        Ok(json_value) => Ok(Interpreter::json_to_mew_value(json_value)),
-        Err(e) => Err(MewError::runtime(format!("Invalid JSON syntax: {}", e))),
-      },
+      Value::String(json_str) => {
+        let value = convert::parse_json(json_str)
+          .map_err(|e| MewError::runtime(format!("Invalid JSON syntax: {}", e)))?;
+        interpreter.check_nested_sizes(&value)?;
+        Ok(value)
+      }
       _ => Err(MewError::type_error(format!(
         "MewJ.sniff requires a string argument, got {}",
         args[0].type_name()
@@ -1798,201 +2955,37 @@ impl Interpreter {
     }
   }
 
-  fn native_mewj_mewify(args: Vec<Value>) -> MewResult<Value> {
-    if args.len() < 1 || args.len() > 2 {
+  fn native_mewj_mewify(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+    if args.is_empty() || args.len() > 2 {
       return Err(MewError::runtime(
         "MewJ.mewify requires one or two arguments",
       ));
     }
 
     let value = &args[0];
-    let indent = if args.len() == 2 {
-      match &args[1] {
-        Value::Number(n) => {
-          if *n >= 0.0 && *n <= 10.0 {
-            Some(*n as usize)
-          } else {
-            None
-          }
-        }
-        _ => None,
-      }
-    } else {
-      None
+    let indent = match args.get(1) {
+      Some(Value::Number(n)) if *n >= 1.0 && *n <= 10.0 => Some(" ".repeat(*n as usize)),
+      _ => None,
     };
 
-    match Interpreter::mew_value_to_json(value) {
-      Ok(json_value) => {
-        let result = if let Some(_spaces) = indent {
-          match serde_json::to_string_pretty(&json_value) {
-            Ok(s) => s,
-            Err(e) => return Err(MewError::runtime(format!("Serialization error: {}", e))),
-          }
-        } else {
-          match serde_json::to_string(&json_value) {
-            Ok(s) => s,
-            Err(e) => return Err(MewError::runtime(format!("Serialization error: {}", e))),
-          }
-        };
-
-        Ok(Value::String(result))
-      }
-      Err(e) => Err(e),
-    }
-  }
-
-  // Helper function to convert serde_json::Value to Mew Value
-  fn json_to_mew_value(json_value: serde_json::Value) -> Value {
-    match json_value {
-      serde_json::Value::Null => Value::Null,
-      serde_json::Value::Bool(b) => Value::Bool(b),
-      serde_json::Value::Number(n) => {
-        if let Some(f) = n.as_f64() {
-          Value::Number(f)
-        } else {
-          // Handle integers that don't fit in f64
-          Value::Number(n.as_i64().unwrap_or(0) as f64)
-        }
-      }
-      serde_json::Value::String(s) => Value::String(s),
-      serde_json::Value::Array(arr) => {
-        let mew_arr = arr.into_iter().map(Self::json_to_mew_value).collect();
-        Value::Array(mew_arr)
-      }
-      serde_json::Value::Object(obj) => {
-        let mut mew_obj = HashMap::new();
-        for (k, v) in obj {
-          mew_obj.insert(k, Self::json_to_mew_value(v));
-        }
-        Value::Object(mew_obj)
-      }
-    }
-  }
-
-  // Helper function to convert Mew Value to serde_json::Value
-  fn mew_value_to_json(value: &Value) -> MewResult<serde_json::Value> {
-    match value {
-      Value::Null => Ok(serde_json::Value::Null),
-      Value::Bool(b) => Ok(serde_json::Value::Bool(*b)),
-      Value::Number(n) => {
-        if n.is_finite() {
-          Ok(serde_json::Value::Number(
-            serde_json::Number::from_f64(*n).unwrap(),
-          ))
-        } else if n.is_nan() {
-          Err(MewError::runtime("Cannot convert NaN to JSON"))
-        } else {
-          Err(MewError::runtime("Cannot convert Infinity to JSON"))
-        }
-      }
-      Value::String(s) => Ok(serde_json::Value::String(s.clone())),
-      Value::Array(arr) => {
-        let mut json_arr = Vec::new();
-        for item in arr {
-          json_arr.push(Self::mew_value_to_json(item)?);
-        }
-        Ok(serde_json::Value::Array(json_arr))
-      }
-      Value::Object(obj) => {
-        let mut json_obj = serde_json::Map::new();
-        for (k, v) in obj {
-          json_obj.insert(k.clone(), Self::mew_value_to_json(v)?);
-        }
-        Ok(serde_json::Value::Object(json_obj))
-      }
-      Value::Undefined => Ok(serde_json::Value::Null), // Convert undefined to null in JSON
-      Value::Function(_) | Value::NativeFunction(_) => {
-        Err(MewError::runtime("Functions cannot be converted to JSON"))
-      }
-    }
-  }
-
-  // Helper method to evaluate simple expressions that might be array indices
-  #[allow(dead_code)]
-  fn try_evaluate_as_expression(&mut self, expr_str: &str) -> Option<usize> {
-    // Try to handle simple expressions like i+1, i-1, etc.
-
-    // First, check for binary operations
-    if let Some(plus_pos) = expr_str.find('+') {
-      let left = &expr_str[0..plus_pos].trim();
-      let right = &expr_str[plus_pos + 1..].trim();
-
-      // Evaluate the left-hand side
-      let left_val = if let Ok(val) = self.evaluate(&Expr::Variable(left.to_string())) {
-        if let Value::Number(num) = val {
-          Some(num)
-        } else {
-          None
-        }
-      } else {
-        // Try parsing it as a number
-        left.parse::<f64>().ok()
-      };
-
-      // Evaluate the right-hand side
-      let right_val = if let Ok(val) = self.evaluate(&Expr::Variable(right.to_string())) {
-        if let Value::Number(num) = val {
-          Some(num)
-        } else {
-          None
-        }
-      } else {
-        // Try parsing it as a number
-        right.parse::<f64>().ok()
-      };
-
-      // If both sides are valid numbers, return the result
-      if let (Some(left_num), Some(right_num)) = (left_val, right_val) {
-        return Some((left_num + right_num) as usize);
-      }
-    } else if let Some(minus_pos) = expr_str.find('-') {
-      let left = &expr_str[0..minus_pos].trim();
-      let right = &expr_str[minus_pos + 1..].trim();
-
-      // Evaluate the left-hand side
-      let left_val = if let Ok(val) = self.evaluate(&Expr::Variable(left.to_string())) {
-        if let Value::Number(num) = val {
-          Some(num)
-        } else {
-          None
-        }
-      } else {
-        // Try parsing it as a number
-        left.parse::<f64>().ok()
-      };
-
-      // Evaluate the right-hand side
-      let right_val = if let Ok(val) = self.evaluate(&Expr::Variable(right.to_string())) {
-        if let Value::Number(num) = val {
-          Some(num)
-        } else {
-          None
-        }
-      } else {
-        // Try parsing it as a number
-        right.parse::<f64>().ok()
-      };
-
-      // If both sides are valid numbers and left >= right, return the result
-      if let (Some(left_num), Some(right_num)) = (left_val, right_val) {
-        if left_num >= right_num {
-          return Some((left_num - right_num) as usize);
-        }
-      }
-    }
-
-    // If we can't evaluate it as an expression, try to evaluate it as a variable
-    if let Ok(val) = self.evaluate(&Expr::Variable(expr_str.to_string())) {
-      if let Value::Number(num) = val {
-        return Some(num as usize);
+    let mut json = BoundedOutput::new(interpreter.limits.max_string_length);
+    let result = match indent {
+      Some(indent) => {
+        let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+        value.serialize(&mut serde_json::Serializer::with_formatter(
+          &mut json, formatter,
+        ))
       }
-    }
+      None => value.serialize(&mut serde_json::Serializer::new(&mut json)),
+    };
 
-    None
+    json
+      .finish(result, |e| MewError::type_error(e.to_string()))
+      .map(Value::String)
   }
 
   // Native toString implementation for all value types
-  fn native_to_string(args: Vec<Value>) -> MewResult<Value> {
+  fn native_to_string(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
     if args.is_empty() {
       return Err(MewError::runtime("toString requires at least one argument"));
     }
@@ -2001,26 +2994,13 @@ impl Interpreter {
     let string_repr = match arg {
       Value::Null => "null".to_string(),
       Value::Undefined => "undefined".to_string(),
-      Value::Number(n) => {
-        if n.is_nan() {
-          "NaN".to_string()
-        } else if n.is_infinite() {
-          if n.is_sign_positive() {
-            "Infinity".to_string()
-          } else {
-            "-Infinity".to_string()
-          }
-        } else if n.fract() == 0.0 {
-          format!("{}", *n as i64)
-        } else {
-          n.to_string()
-        }
-      }
+      Value::Number(_) => arg.to_string(),
       Value::Bool(b) => b.to_string(),
       Value::String(s) => s.clone(),
       Value::Array(arr) => {
-        let elements: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
-        format!("[{}]", elements.join(","))
+        let mut output = BoundedOutput::new(interpreter.limits.max_string_length);
+        let result = write_array(&mut output, &arr.borrow());
+        output.finish(result, |_| MewError::runtime("Could not format the array"))?
       }
       Value::Object(_) => "[object Object]".to_string(),
       Value::Function(func) => {
@@ -2033,6 +3013,7 @@ impl Interpreter {
       Value::NativeFunction(func) => {
         format!("function {}(...) [native]", func.name)
       }
+      Value::Closure(_) => arg.to_string(),
     };
 
     Ok(Value::String(string_repr))
@@ -2040,20 +3021,11 @@ impl Interpreter {
 }
 
 pub fn interpret(source: &str) -> MewResult<Value> {
-  use crate::lexer::MewLexer;
-  use crate::parser::Parser;
-
   let mut lexer = MewLexer::new(source);
-  let tokens = match lexer.scan_tokens() {
-    Ok(t) => t,
-    Err(e) => return Err(e),
-  };
+  let tokens = lexer.scan_tokens()?;
 
   let mut parser = Parser::new(tokens);
-  let statements = match parser.parse() {
-    Ok(s) => s,
-    Err(e) => return Err(e),
-  };
+  let statements = parser.parse()?;
 
   let mut interpreter = Interpreter::new();
   interpreter.interpret(&statements)
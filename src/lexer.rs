@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::{Location, MewError, MewResult};
+use crate::error::{Diagnostic, Location, MewError, MewResult, Span};
 use std::fmt;
 use std::str::FromStr;
 
@@ -43,6 +43,15 @@ pub enum TokenKind {
   Public,
   Import,
   From,
+  Try,
+  Catch,
+  Finally,
+  Throw,
+  Class,
+  Static,
+  SelfKw,
+  Super,
+  InstanceOf,
 
   // Literals
   Number(f64),
@@ -92,6 +101,8 @@ pub enum TokenKind {
   Semicolon,
   Colon,
   Question,
+  QuestionQuestion,
+  QuestionDot,
   Arrow,
 
   // End of file
@@ -114,6 +125,24 @@ impl Token {
       location: Location::new(line, column),
     }
   }
+
+  /// Location just past the last character of the token
+  pub fn end(&self) -> Location {
+    let mut end = self.location;
+    for c in self.lexeme.chars() {
+      if c == '\n' {
+        end.line += 1;
+        end.column = 1;
+      } else {
+        end.column += 1;
+      }
+    }
+    end
+  }
+
+  pub fn span(&self) -> Span {
+    Span::new(self.location, self.end())
+  }
 }
 
 impl fmt::Display for Token {
@@ -124,7 +153,8 @@ impl fmt::Display for Token {
 
 /// MewLexer processes source code into tokens
 pub struct MewLexer {
-  source: String,
+  // Indexed by character so that non-ASCII source is sliced correctly
+  source: Vec<char>,
   tokens: Vec<Token>,
   start: usize,
   current: usize,
@@ -135,7 +165,7 @@ pub struct MewLexer {
 impl MewLexer {
   pub fn new(source: &str) -> Self {
     Self {
-      source: source.to_string(),
+      source: source.chars().collect(),
       tokens: Vec::new(),
       start: 0,
       current: 0,
@@ -176,7 +206,15 @@ impl MewLexer {
       '.' => self.add_token(TokenKind::Dot),
       ';' => self.add_token(TokenKind::Semicolon),
       ':' => self.add_token(TokenKind::Colon),
-      '?' => self.add_token(TokenKind::Question),
+      '?' => {
+        if self.match_char('?') {
+          self.add_token(TokenKind::QuestionQuestion)
+        } else if self.match_char('.') {
+          self.add_token(TokenKind::QuestionDot)
+        } else {
+          self.add_token(TokenKind::Question)
+        }
+      }
 
       // Operators that might be two characters
       '+' => {
@@ -210,9 +248,10 @@ impl MewLexer {
             self.advance();
           }
         } else if self.match_char('*') {
+          let start = Location::new(self.line, self.column - 2);
           loop {
             if self.is_at_end() {
-              return Err(MewError::syntax("Unterminated multi-line comment"));
+              return Err(unterminated("Unterminated multi-line comment", start));
             } else if self.peek() == '*' && self.peek_next() == '/' {
               self.advance();
               self.advance();
@@ -271,7 +310,7 @@ impl MewLexer {
           self.add_token(TokenKind::And)
         } else {
           return Err(MewError::syntax_at(
-            format!("Unexpected character '&'"),
+            "Unexpected character '&'",
             Location::new(self.line, self.column - 1),
           ));
         }
@@ -281,7 +320,7 @@ impl MewLexer {
           self.add_token(TokenKind::Or)
         } else {
           return Err(MewError::syntax_at(
-            format!("Unexpected character '|'"),
+            "Unexpected character '|'",
             Location::new(self.line, self.column - 1),
           ));
         }
@@ -315,6 +354,9 @@ impl MewLexer {
   }
 
   fn string(&mut self, quote: char) -> MewResult<()> {
+    // Strings may span lines, so remember where the opening quote was
+    let line = self.line;
+    let column = self.column - 1;
     let mut value = String::new();
 
     while self.peek() != quote && !self.is_at_end() {
@@ -330,9 +372,8 @@ impl MewLexer {
           '\'' => value.push('\''),
           '"' => value.push('"'),
           _ => {
-            let msg = format!("Invalid escape sequence");
             return Err(MewError::syntax_at(
-              msg,
+              "Invalid escape sequence",
               Location::new(self.line, self.column - 1),
             ));
           }
@@ -347,21 +388,18 @@ impl MewLexer {
     }
 
     if self.is_at_end() {
-      return Err(MewError::syntax_at(
-        format!("Unterminated string"),
-        Location::new(self.line, self.column),
+      return Err(unterminated(
+        "Unterminated string",
+        Location::new(line, column),
       ));
     }
 
     self.advance();
 
-    let lexeme = self.source[self.start..self.current].to_string();
-    self.tokens.push(Token::new(
-      TokenKind::String(value),
-      lexeme,
-      self.line,
-      self.column - (self.current - self.start),
-    ));
+    let lexeme = self.lexeme();
+    self
+      .tokens
+      .push(Token::new(TokenKind::String(value), lexeme, line, column));
 
     Ok(())
   }
@@ -379,7 +417,7 @@ impl MewLexer {
       }
     }
 
-    let lexeme = self.source[self.start..self.current].to_string();
+    let lexeme = self.lexeme();
     let value = match f64::from_str(&lexeme) {
       Ok(v) => v,
       Err(_) => {
@@ -401,11 +439,17 @@ impl MewLexer {
   }
 
   fn identifier(&mut self) -> MewResult<()> {
-    while self.peek().is_ascii_alphanumeric() || self.peek() == '_' || self.peek() == '?' {
+    while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
       self.advance();
     }
 
-    let lexeme = self.source[self.start..self.current].to_string();
+    // `?` only belongs to the `meow?` and `meowse?` keywords, so that
+    // `a?.b` and `a??b` lex as operators
+    if self.peek() == '?' && matches!(self.lexeme().as_str(), "meow" | "meowse") {
+      self.advance();
+    }
+
+    let lexeme = self.lexeme();
     let kind = match lexeme.as_str() {
       // Keywords
       "catst" => TokenKind::Const,
@@ -430,6 +474,15 @@ impl MewLexer {
       "pub" => TokenKind::Public,
       "import" => TokenKind::Import,
       "from" => TokenKind::From,
+      "purrhaps" => TokenKind::Try,
+      "catch" => TokenKind::Catch,
+      "furever" => TokenKind::Finally,
+      "hairball" => TokenKind::Throw,
+      "breed" => TokenKind::Class,
+      "static" => TokenKind::Static,
+      "self" => TokenKind::SelfKw,
+      "super" => TokenKind::Super,
+      "breedof" => TokenKind::InstanceOf,
 
       // Special literals
       "null" => TokenKind::Null,
@@ -458,7 +511,7 @@ impl MewLexer {
   }
 
   fn advance(&mut self) -> char {
-    let c = self.source.chars().nth(self.current).unwrap_or('\0');
+    let c = self.source.get(self.current).copied().unwrap_or('\0');
     self.current += 1;
     self.column += 1;
     c
@@ -469,7 +522,7 @@ impl MewLexer {
       return false;
     }
 
-    if self.source.chars().nth(self.current).unwrap_or('\0') != expected {
+    if self.source.get(self.current).copied().unwrap_or('\0') != expected {
       return false;
     }
 
@@ -482,18 +535,22 @@ impl MewLexer {
     if self.is_at_end() {
       return '\0';
     }
-    self.source.chars().nth(self.current).unwrap_or('\0')
+    self.source.get(self.current).copied().unwrap_or('\0')
   }
 
   fn peek_next(&self) -> char {
     if self.current + 1 >= self.source.len() {
       return '\0';
     }
-    self.source.chars().nth(self.current + 1).unwrap_or('\0')
+    self.source.get(self.current + 1).copied().unwrap_or('\0')
+  }
+
+  fn lexeme(&self) -> String {
+    self.source[self.start..self.current].iter().collect()
   }
 
   fn add_token(&mut self, kind: TokenKind) {
-    let lexeme = self.source[self.start..self.current].to_string();
+    let lexeme = self.lexeme();
     self.tokens.push(Token::new(
       kind,
       lexeme,
@@ -502,3 +559,11 @@ impl MewLexer {
     ));
   }
 }
+
+// A string or comment still open at the end of the input, which more input
+// could close
+fn unterminated(message: &str, start: Location) -> MewError {
+  MewError::Parse(vec![
+    Diagnostic::new(message, Span::at(start)).at_end_of_input()
+  ])
+}
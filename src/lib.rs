@@ -1,13 +1,22 @@
+pub mod array_methods;
+pub mod convert;
 pub mod error;
+pub mod ffi;
 pub mod interpreter;
 pub mod lexer;
+pub mod module;
 pub mod parser;
+pub mod resolver;
+pub mod string_methods;
 pub mod value;
+pub mod vm;
 
-pub use error::{Location, MewError, MewResult};
-pub use interpreter::Interpreter;
+pub use convert::{from_value, to_value};
+pub use error::{Diagnostic, Label, Limit, Location, MewError, MewResult, Span, StackFrame};
+pub use interpreter::{Engine, Interpreter, InterruptHandle, Limits};
 pub use lexer::{MewLexer, Token, TokenKind};
 pub use parser::Parser;
-pub use value::Value;
+pub use resolver::Resolver;
+pub use value::{NativeFunction, Value};
 
 pub use interpreter::interpret;
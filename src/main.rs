@@ -14,11 +14,6 @@
 // along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
 
 mod cli;
-mod error;
-mod interpreter;
-mod lexer;
-mod parser;
-mod value;
 
 use clap::Parser;
 use cli::{Cli, Commands};
@@ -26,9 +21,10 @@ use std::process;
 
 fn main() {
   let cli = Cli::parse();
+  let engine = cli.engine.into();
 
   if let Some(file_path) = cli.file_path {
-    if let Err(e) = cli::run_file(&file_path) {
+    if let Err(e) = cli::run_file(&file_path, engine) {
       eprintln!("hiss! Error: {}", e);
       process::exit(1);
     }
@@ -49,13 +45,13 @@ fn main() {
       }
     }
     Some(Commands::Start) => {
-      if let Err(e) = cli::handle_start() {
+      if let Err(e) = cli::handle_start(engine) {
         eprintln!("hiss! Error: {}", e);
         process::exit(1);
       }
     }
     None => {
-      if let Err(e) = cli::run_repl() {
+      if let Err(e) = cli::run_repl(engine) {
         eprintln!("hiss! Error: {}", e);
         process::exit(1);
       }
@@ -0,0 +1,76 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::{MewError, MewResult};
+use crate::value::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Values exported with `pub` from a module, keyed by name
+pub type Exports = Rc<HashMap<String, Value>>;
+
+/// Load state of a module, keyed by its canonical path in the interpreter
+#[derive(Debug, Clone)]
+pub enum ModuleState {
+  /// The module is currently executing; importing it again is a cycle
+  Loading,
+  Loaded(Exports),
+}
+
+/// Resolve an import specifier to the canonical path of a `.mew` file.
+///
+/// Specifiers starting with `./` or `../` are relative to the importing
+/// file. Any other relative specifier is resolved from the project root, the
+/// nearest directory containing `mew.toml`.
+pub fn resolve_import(importer: Option<&Path>, specifier: &str) -> MewResult<PathBuf> {
+  let base_dir = match importer.and_then(Path::parent) {
+    Some(dir) => dir.to_path_buf(),
+    None => std::env::current_dir()?,
+  };
+
+  let mut path = PathBuf::from(specifier);
+  if path.extension().is_none() {
+    path.set_extension("mew");
+  }
+
+  let candidate = if path.is_absolute() {
+    path
+  } else if specifier.starts_with("./") || specifier.starts_with("../") {
+    base_dir.join(path)
+  } else {
+    find_project_root(&base_dir).unwrap_or(base_dir).join(path)
+  };
+
+  candidate
+    .canonicalize()
+    .map_err(|_| MewError::runtime(format!("Cannot find module '{}'", specifier)))
+}
+
+/// Walk up from `start` looking for the directory that holds `mew.toml`
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+  start
+    .ancestors()
+    .find(|dir| dir.join("mew.toml").is_file())
+    .map(Path::to_path_buf)
+}
+
+/// Human-readable name for a module path in error messages
+pub fn display_name(path: &Path) -> String {
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+    .unwrap_or_else(|| path.display().to_string())
+}
@@ -13,9 +13,12 @@
 // You should have received a copy of the GNU General Public License
 // along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::{MewError, MewResult};
+use crate::error::{Diagnostic, Location, MewError, MewResult, Span};
+use crate::interpreter::{STACK_RED_ZONE, STACK_SEGMENT};
 use crate::lexer::{Token, TokenKind};
-use crate::value::{BinaryOp, Expr, Stmt, UnaryOp, Value};
+use crate::value::{
+  BinaryOp, ClassMember, Expr, ExprKind, Stmt, StmtKind, UnaryOp, Value, VarKind,
+};
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
@@ -23,70 +26,174 @@ use std::rc::Rc;
 // Add a Display implementation for Expr
 impl fmt::Display for Expr {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    match self {
-      Expr::Literal(val) => write!(f, "{}", val),
-      Expr::Variable(name) => write!(f, "{}", name),
-      Expr::Assignment(name, _) => write!(f, "{} = ...", name),
-      Expr::Binary(_, _, _) => write!(f, "<binary-expr>"),
-      Expr::Unary(_, _) => write!(f, "<unary-expr>"),
-      Expr::Call(_, _) => write!(f, "<call-expr>"),
-      Expr::Get(_, name) => write!(f, "<get-expr>.{}", name),
-      Expr::Set(_, name, _) => write!(f, "<set-expr>.{} = ...", name),
-      Expr::ArrayLiteral(_) => write!(f, "[...]"),
-      Expr::ObjectLiteral(_) => write!(f, "{{...}}"),
-      Expr::Function(name, _, _) => {
+    match &self.kind {
+      ExprKind::Literal(val) => write!(f, "{}", val),
+      ExprKind::Variable(name, _) => write!(f, "{}", name),
+      ExprKind::Assignment(name, _, _) => write!(f, "{} = ...", name),
+      ExprKind::CompoundAssignment(target, op, _) => write!(f, "{} {:?}= ...", target, op),
+      ExprKind::Binary(_, _, _) => write!(f, "<binary-expr>"),
+      ExprKind::Unary(_, _) => write!(f, "<unary-expr>"),
+      ExprKind::Call(_, _) => write!(f, "<call-expr>"),
+      ExprKind::Get(_, name) => write!(f, "<get-expr>.{}", name),
+      ExprKind::Set(_, name, _) => write!(f, "<set-expr>.{} = ...", name),
+      ExprKind::Index(object, index) => write!(f, "{}[{}]", object, index),
+      ExprKind::SetIndex(object, index, _) => write!(f, "{}[{}] = ...", object, index),
+      ExprKind::ArrayLiteral(_) => write!(f, "[...]"),
+      ExprKind::ObjectLiteral(_) => write!(f, "{{...}}"),
+      ExprKind::Function(name, ..) => {
         if let Some(n) = name {
           write!(f, "function {}(...)", n)
         } else {
           write!(f, "function(...)")
         }
       }
-      Expr::Increment(expr, is_prefix) => {
+      ExprKind::Increment(expr, is_prefix) => {
         if *is_prefix {
           write!(f, "++{}", expr)
         } else {
           write!(f, "{}++", expr)
         }
       }
-      Expr::Decrement(expr, is_prefix) => {
+      ExprKind::Decrement(expr, is_prefix) => {
         if *is_prefix {
           write!(f, "--{}", expr)
         } else {
           write!(f, "{}--", expr)
         }
       }
-      Expr::Ternary(_, _, _) => write!(f, "<ternary-expr>"),
+      ExprKind::Ternary(_, _, _) => write!(f, "<ternary-expr>"),
+      ExprKind::Super(name) => write!(f, "super.{}", name),
+      ExprKind::Optional(object) => write!(f, "{}?", object),
+      ExprKind::Grouping(expr) => write!(f, "({})", expr),
     }
   }
 }
 
+type ParseResult<T> = Result<T, Diagnostic>;
+
+// How deeply statements and expressions may nest. A parenthesized expression
+// takes two levels.
+const MAX_NESTING: usize = 512;
+
 pub struct Parser {
   tokens: Vec<Token>,
   current: usize,
+  errors: Vec<Diagnostic>,
+  depth: usize,
 }
 
 impl Parser {
   pub fn new(tokens: Vec<Token>) -> Self {
-    Self { tokens, current: 0 }
+    Self {
+      tokens,
+      current: 0,
+      errors: Vec::new(),
+      depth: 0,
+    }
   }
 
+  /// Parse the whole input. After a syntax error the parser skips to the next
+  /// statement and carries on, so every error in the file is reported at once.
   pub fn parse(&mut self) -> MewResult<Vec<Rc<RefCell<Stmt>>>> {
     let mut statements = Vec::new();
 
     while !self.is_at_end() {
-      match self.declaration() {
+      let start = self.current;
+      match self.top_level_declaration() {
         Ok(stmt) => statements.push(Rc::new(RefCell::new(stmt))),
-        Err(e) => {
-          self.synchronize();
-          return Err(e);
-        }
+        Err(error) => self.recover(error, start),
       }
     }
 
+    if !self.errors.is_empty() {
+      return Err(MewError::Parse(std::mem::take(&mut self.errors)));
+    }
+
     Ok(statements)
   }
 
-  fn declaration(&mut self) -> MewResult<Stmt> {
+  // Imports and exports may only appear at the top level of a module
+  fn top_level_declaration(&mut self) -> ParseResult<Stmt> {
+    if self.match_tokens(&[TokenKind::Import]) {
+      return self.import_declaration();
+    }
+
+    if self.match_tokens(&[TokenKind::Public]) {
+      return self.export_declaration();
+    }
+
+    self.declaration()
+  }
+
+  fn import_declaration(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
+    self.consume(TokenKind::LeftBrace, "Expected '{' after 'import'.")?;
+
+    let mut names = Vec::new();
+    if !self.check(TokenKind::RightBrace) {
+      loop {
+        names.push(self.consume_identifier("Expected name to import.")?);
+
+        if !self.match_tokens(&[TokenKind::Comma]) || self.check(TokenKind::RightBrace) {
+          break;
+        }
+      }
+    }
+
+    self.consume(TokenKind::RightBrace, "Expected '}' after imported names.")?;
+    self.consume(TokenKind::From, "Expected 'from' after imported names.")?;
+
+    let path = match &self.peek().kind {
+      TokenKind::String(path) => path.clone(),
+      _ => {
+        return Err(Diagnostic::new(
+          "Expected module path string after 'from'.",
+          self.error_span(),
+        ))
+      }
+    };
+    self.advance();
+
+    self.consume_semicolon("Expected ';' after import.")?;
+    Ok(self.stmt(StmtKind::Import(names, path), start))
+  }
+
+  fn export_declaration(&mut self) -> ParseResult<Stmt> {
+    let keyword = self.previous();
+
+    let declaration = if self.match_tokens(&[TokenKind::Var, TokenKind::Let, TokenKind::Const]) {
+      self.var_declaration()?
+    } else if self.match_tokens(&[TokenKind::Function]) {
+      self.function_declaration("function")?
+    } else if self.match_tokens(&[TokenKind::Class]) {
+      self.class_declaration()?
+    } else {
+      return Err(Diagnostic::new(
+        "Expected a variable, function or breed declaration after 'pub'.",
+        keyword.span(),
+      ));
+    };
+
+    Ok(self.stmt(
+      StmtKind::Export(Rc::new(RefCell::new(declaration))),
+      keyword.location,
+    ))
+  }
+
+  fn declaration(&mut self) -> ParseResult<Stmt> {
+    if self.check(TokenKind::Import) || self.check(TokenKind::Public) {
+      return Err(
+        Diagnostic::new(
+          format!(
+            "'{}' is only allowed at the top level of a module.",
+            self.peek().lexeme
+          ),
+          self.error_span(),
+        )
+        .with_help("move it out of the block, to the top of the file"),
+      );
+    }
+
     if self.match_tokens(&[TokenKind::Var, TokenKind::Let, TokenKind::Const]) {
       return self.var_declaration();
     }
@@ -95,15 +202,16 @@ impl Parser {
       return self.function_declaration("function");
     }
 
+    if self.match_tokens(&[TokenKind::Class]) {
+      return self.class_declaration();
+    }
+
     self.statement()
   }
 
-  fn var_declaration(&mut self) -> MewResult<Stmt> {
+  fn var_declaration(&mut self) -> ParseResult<Stmt> {
     let token = self.previous();
-    let is_const = match token.kind {
-      TokenKind::Const => true,
-      _ => false,
-    };
+    let kind = var_kind(&token.kind);
 
     let name = self.consume_identifier("Expected variable name.")?;
 
@@ -113,16 +221,17 @@ impl Parser {
       None
     };
 
-    self.consume(
-      TokenKind::Semicolon,
-      "Expected ';' after variable declaration.",
-    )?;
-    Ok(Stmt::VarDeclaration(name, initializer, is_const))
+    self.consume_semicolon("Expected ';' after variable declaration.")?;
+    Ok(self.stmt(
+      StmtKind::VarDeclaration(name, initializer, kind),
+      token.location,
+    ))
   }
 
-  fn function_declaration(&mut self, kind: &str) -> MewResult<Stmt> {
+  fn function_declaration(&mut self, kind: &str) -> ParseResult<Stmt> {
+    let start = self.previous().location;
     let name = self.consume_identifier(&format!("Expected {} name.", kind))?;
-    self.consume(
+    let open = self.consume(
       TokenKind::LeftParen,
       &format!("Expected '(' after {} name.", kind),
     )?;
@@ -131,9 +240,9 @@ impl Parser {
     if !self.check(TokenKind::RightParen) {
       loop {
         if parameters.len() >= 255 {
-          return Err(MewError::syntax_at(
+          return Err(Diagnostic::new(
             "Cannot have more than 255 parameters.",
-            self.peek().location,
+            self.error_span(),
           ));
         }
 
@@ -145,7 +254,12 @@ impl Parser {
       }
     }
 
-    self.consume(TokenKind::RightParen, "Expected ')' after parameters.")?;
+    self.consume_closing(
+      TokenKind::RightParen,
+      "Expected ')' after parameters.",
+      &open,
+      "parameters start here",
+    )?;
 
     self.consume(
       TokenKind::LeftBrace,
@@ -153,17 +267,69 @@ impl Parser {
     )?;
     let body = self.block()?;
 
-    Ok(Stmt::Function(name, parameters, body))
+    Ok(self.stmt(
+      StmtKind::Function(name, parameters, body, Vec::new()),
+      start,
+    ))
   }
 
-  fn statement(&mut self) -> MewResult<Stmt> {
+  fn class_declaration(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
+    let name = self.consume_identifier("Expected breed name.")?;
+
+    let parent = if self.match_tokens(&[TokenKind::From]) {
+      let parent_start = self.peek().location;
+      let parent = self.consume_identifier("Expected parent breed name after 'from'.")?;
+      Some(self.expr(ExprKind::Variable(parent, None), parent_start))
+    } else {
+      None
+    };
+
+    let open = self.consume(TokenKind::LeftBrace, "Expected '{' before breed body.")?;
+
+    let mut members = Vec::new();
+    while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+      let is_static = self.match_tokens(&[TokenKind::Static]);
+
+      if is_static && self.check_next(TokenKind::Equal) {
+        let field = self.consume_identifier("Expected static field name.")?;
+        self.consume(TokenKind::Equal, "Expected '=' after static field name.")?;
+        let value = self.expression()?;
+        self.consume(TokenKind::Semicolon, "Expected ';' after static field.")?;
+        members.push(ClassMember::StaticField(field, value));
+        continue;
+      }
+
+      match self.function_declaration("method")?.kind {
+        StmtKind::Function(method, parameters, body, hoisted) => members.push(ClassMember::Method(
+          method, parameters, body, is_static, hoisted,
+        )),
+        _ => unreachable!(),
+      }
+    }
+
+    self.consume_closing(
+      TokenKind::RightBrace,
+      "Expected '}' after breed body.",
+      &open,
+      "breed body opened here",
+    )?;
+    Ok(self.stmt(StmtKind::Class(name, parent, members), start))
+  }
+
+  fn statement(&mut self) -> ParseResult<Stmt> {
+    self.nested(Self::parse_statement)
+  }
+
+  fn parse_statement(&mut self) -> ParseResult<Stmt> {
     if self.match_tokens(&[TokenKind::Print]) {
       return self.print_statement();
     }
 
     if self.match_tokens(&[TokenKind::LeftBrace]) {
+      let start = self.previous().location;
       let statements = self.block()?;
-      return Ok(Stmt::Block(statements));
+      return Ok(self.stmt(StmtKind::Block(statements), start));
     }
 
     if self.match_tokens(&[TokenKind::If]) {
@@ -198,32 +364,55 @@ impl Parser {
       return self.switch_statement();
     }
 
+    if self.match_tokens(&[TokenKind::Try]) {
+      return self.try_statement();
+    }
+
+    if self.match_tokens(&[TokenKind::Throw]) {
+      return self.throw_statement();
+    }
+
+    if self.check_type_variant::<String>(&TokenKind::Identifier(String::new()))
+      && self.check_next(TokenKind::Colon)
+    {
+      return self.labeled_statement();
+    }
+
     self.expression_statement()
   }
 
-  fn print_statement(&mut self) -> MewResult<Stmt> {
+  fn print_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
     self.consume(TokenKind::LeftParen, "Expected '(' after 'purr'.")?;
     let value = self.expression()?;
     self.consume(TokenKind::RightParen, "Expected ')' after expression.")?;
-    self.consume(TokenKind::Semicolon, "Expected ';' after value.")?;
-    Ok(Stmt::Print(value))
+    self.consume_semicolon("Expected ';' after value.")?;
+    Ok(self.stmt(StmtKind::Print(value), start))
   }
 
-  fn block(&mut self) -> MewResult<Vec<Rc<RefCell<Stmt>>>> {
+  fn block(&mut self) -> ParseResult<Vec<Rc<RefCell<Stmt>>>> {
+    let open = self.previous();
     let mut statements = Vec::new();
 
     while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+      let start = self.current;
       match self.declaration() {
         Ok(stmt) => statements.push(Rc::new(RefCell::new(stmt))),
-        Err(e) => return Err(e),
+        Err(error) => self.recover(error, start),
       }
     }
 
-    self.consume(TokenKind::RightBrace, "Expected '}' after block.")?;
+    self.consume_closing(
+      TokenKind::RightBrace,
+      "Expected '}' after block.",
+      &open,
+      "block opened here",
+    )?;
     Ok(statements)
   }
 
-  fn if_statement(&mut self) -> MewResult<Stmt> {
+  fn if_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
     self.consume(TokenKind::LeftParen, "Expected '(' after 'meow?'.")?;
     let condition = self.expression()?;
     self.consume(TokenKind::RightParen, "Expected ')' after condition.")?;
@@ -237,20 +426,22 @@ impl Parser {
       else_branch = Some(Rc::new(RefCell::new(self.statement()?)));
     }
 
-    Ok(Stmt::If(condition, then_branch, else_branch))
+    Ok(self.stmt(StmtKind::If(condition, then_branch, else_branch), start))
   }
 
-  fn while_statement(&mut self) -> MewResult<Stmt> {
+  fn while_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
     self.consume(TokenKind::LeftParen, "Expected '(' after 'mewhile'.")?;
     let condition = self.expression()?;
     self.consume(TokenKind::RightParen, "Expected ')' after condition.")?;
 
     let body = Rc::new(RefCell::new(self.statement()?));
 
-    Ok(Stmt::While(condition, body))
+    Ok(self.stmt(StmtKind::While(condition, body), start))
   }
 
-  fn do_while_statement(&mut self) -> MewResult<Stmt> {
+  fn do_while_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
     let body = Rc::new(RefCell::new(self.statement()?));
 
     self.consume(
@@ -260,18 +451,13 @@ impl Parser {
     self.consume(TokenKind::LeftParen, "Expected '(' after 'mewhile'.")?;
     let condition = self.expression()?;
     self.consume(TokenKind::RightParen, "Expected ')' after condition.")?;
-    self.consume(
-      TokenKind::Semicolon,
-      "Expected ';' after do-while statement.",
-    )?;
+    self.consume_semicolon("Expected ';' after do-while statement.")?;
 
-    Ok(Stmt::Block(vec![
-      body.clone(),
-      Rc::new(RefCell::new(Stmt::While(condition, body))),
-    ]))
+    Ok(self.stmt(StmtKind::DoWhile(body, condition), start))
   }
 
-  fn for_statement(&mut self) -> MewResult<Stmt> {
+  fn for_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
     self.consume(TokenKind::LeftParen, "Expected '(' after 'fur'.")?;
 
     let initializer;
@@ -287,12 +473,9 @@ impl Parser {
         None
       };
 
-      if self.match_tokens(&[TokenKind::In]) || self.match_tokens(&[TokenKind::Of]) {
+      if self.match_tokens(&[TokenKind::In, TokenKind::Of]) {
         let is_of = self.previous().kind == TokenKind::Of;
-        return self.for_in_of_statement(
-          Stmt::VarDeclaration(var_name, var_initializer, is_const),
-          is_of,
-        );
+        return self.for_in_of_statement(start, var_name, is_const, is_of);
       }
 
       self.consume(
@@ -300,7 +483,10 @@ impl Parser {
         "Expected ';' after variable declaration.",
       )?;
 
-      initializer = Some(Stmt::VarDeclaration(var_name, var_initializer, is_const));
+      initializer = Some(self.stmt(
+        StmtKind::VarDeclaration(var_name, var_initializer, var_kind(&token.kind)),
+        token.location,
+      ));
     } else if self.match_tokens(&[TokenKind::Semicolon]) {
       initializer = None;
     } else {
@@ -310,7 +496,7 @@ impl Parser {
     let condition = if !self.check(TokenKind::Semicolon) {
       self.expression()?
     } else {
-      Expr::Literal(Value::Bool(true))
+      self.expr(ExprKind::Literal(Value::Bool(true)), self.peek().location)
     };
     self.consume(TokenKind::Semicolon, "Expected ';' after loop condition.")?;
 
@@ -322,29 +508,27 @@ impl Parser {
 
     self.consume(TokenKind::RightParen, "Expected ')' after for clauses.")?;
 
-    let mut body = self.statement()?;
-
-    if let Some(inc) = increment {
-      body = Stmt::Block(vec![
-        Rc::new(RefCell::new(body)),
-        Rc::new(RefCell::new(Stmt::Expression(inc))),
-      ]);
-    }
-
-    body = Stmt::While(condition, Rc::new(RefCell::new(body)));
+    let body = self.statement()?;
 
-    if let Some(init) = initializer {
-      body = Stmt::Block(vec![
-        Rc::new(RefCell::new(init)),
+    Ok(self.stmt(
+      StmtKind::For(
+        initializer.map(|init| Rc::new(RefCell::new(init))),
+        condition,
+        increment,
         Rc::new(RefCell::new(body)),
-      ]);
-    }
-
-    Ok(body)
+      ),
+      start,
+    ))
   }
 
-  fn for_in_of_statement(&mut self, initializer: Stmt, is_of: bool) -> MewResult<Stmt> {
-    let iterator = self.expression()?;
+  fn for_in_of_statement(
+    &mut self,
+    start: Location,
+    name: String,
+    is_const: bool,
+    is_of: bool,
+  ) -> ParseResult<Stmt> {
+    let iterable = self.expression()?;
     self.consume(
       TokenKind::RightParen,
       "Expected ')' after for-in/of clauses.",
@@ -352,144 +536,65 @@ impl Parser {
 
     let body = self.statement()?;
 
-    let (var_name, is_const) = match &initializer {
-      Stmt::VarDeclaration(name, _, const_val) => (name.clone(), *const_val),
-      _ => return Err(MewError::syntax("Invalid for-in/of loop initializer.")),
-    };
-
-    let iterator_var = format!("__iterator_{}", var_name);
-    let index_var = format!("__index_{}", var_name);
-
-    let iterator_decl = Stmt::VarDeclaration(iterator_var.clone(), Some(iterator.clone()), false);
-
-    let index_decl = Stmt::VarDeclaration(
-      index_var.clone(),
-      Some(Expr::Literal(Value::Number(0.0))),
-      false,
-    );
-
-    let keys_or_values = if is_of {
-      Expr::Call(
-        Box::new(Expr::Get(
-          Box::new(Expr::Variable(String::from("Object"))),
-          String::from("values"),
-        )),
-        vec![Expr::Variable(iterator_var.clone())],
-      )
-    } else {
-      Expr::Call(
-        Box::new(Expr::Get(
-          Box::new(Expr::Variable(String::from("Object"))),
-          String::from("keys"),
-        )),
-        vec![Expr::Variable(iterator_var.clone())],
-      )
-    };
-
-    let collection_var = if is_of {
-      format!("__values_{}", var_name)
-    } else {
-      format!("__keys_{}", var_name)
-    };
-    let collection_decl = Stmt::VarDeclaration(collection_var.clone(), Some(keys_or_values), false);
-
-    let condition = Expr::Binary(
-      Box::new(Expr::Variable(index_var.clone())),
-      BinaryOp::Lt,
-      Box::new(Expr::Get(
-        Box::new(Expr::Variable(collection_var.clone())),
-        String::from("length"),
-      )),
-    );
-
-    let loop_body = if is_const {
-      let const_decl = Stmt::VarDeclaration(
-        var_name.clone(),
-        Some(Expr::Get(
-          Box::new(Expr::Variable(collection_var.clone())),
-          String::from("[") + &index_var + "]",
-        )),
-        true,
-      );
-
-      Stmt::Block(vec![
-        Rc::new(RefCell::new(const_decl)),
-        Rc::new(RefCell::new(body)),
-        Rc::new(RefCell::new(Stmt::Expression(Expr::Increment(
-          Box::new(Expr::Variable(index_var.clone())),
-          false,
-        )))),
-      ])
-    } else {
-      let var_assignment = Stmt::Expression(Expr::Assignment(
-        var_name.clone(),
-        Box::new(Expr::Get(
-          Box::new(Expr::Variable(collection_var.clone())),
-          String::from("[") + &index_var + "]",
-        )),
-      ));
-
-      Stmt::Block(vec![
-        Rc::new(RefCell::new(var_assignment)),
-        Rc::new(RefCell::new(body)),
-        Rc::new(RefCell::new(Stmt::Expression(Expr::Increment(
-          Box::new(Expr::Variable(index_var.clone())),
-          false,
-        )))),
-      ])
-    };
-
-    let while_loop = Stmt::While(condition, Rc::new(RefCell::new(loop_body)));
-
-    let mut statements = vec![
-      Rc::new(RefCell::new(iterator_decl)),
-      Rc::new(RefCell::new(index_decl)),
-      Rc::new(RefCell::new(collection_decl)),
-    ];
-
-    if !is_const {
-      statements.push(Rc::new(RefCell::new(initializer)));
-    }
+    Ok(self.stmt(
+      StmtKind::ForIn(name, is_const, is_of, iterable, Rc::new(RefCell::new(body))),
+      start,
+    ))
+  }
 
-    statements.push(Rc::new(RefCell::new(while_loop)));
+  fn labeled_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.peek().location;
+    let label = self.consume_identifier("Expected label name.")?;
+    self.consume(TokenKind::Colon, "Expected ':' after label.")?;
 
-    let full_block = Stmt::Block(statements);
+    let body = self.statement()?;
+    Ok(self.stmt(StmtKind::Labeled(label, Rc::new(RefCell::new(body))), start))
+  }
 
-    Ok(full_block)
+  fn break_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
+    let label = self.optional_label();
+    self.consume_semicolon("Expected ';' after break statement.")?;
+    Ok(self.stmt(StmtKind::Break(label), start))
   }
 
-  fn break_statement(&mut self) -> MewResult<Stmt> {
-    self.consume(TokenKind::Semicolon, "Expected ';' after break statement.")?;
-    Ok(Stmt::Break)
+  fn continue_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
+    let label = self.optional_label();
+    self.consume_semicolon("Expected ';' after continue statement.")?;
+    Ok(self.stmt(StmtKind::Continue(label), start))
   }
 
-  fn continue_statement(&mut self) -> MewResult<Stmt> {
-    self.consume(
-      TokenKind::Semicolon,
-      "Expected ';' after continue statement.",
-    )?;
-    Ok(Stmt::Continue)
+  fn optional_label(&mut self) -> Option<String> {
+    if let TokenKind::Identifier(name) = &self.peek().kind {
+      let name = name.clone();
+      self.advance();
+      Some(name)
+    } else {
+      None
+    }
   }
 
-  fn return_statement(&mut self) -> MewResult<Stmt> {
-    let _keyword = self.previous();
+  fn return_statement(&mut self) -> ParseResult<Stmt> {
+    let keyword = self.previous();
 
-    let value = if !self.check(TokenKind::Semicolon) {
+    let value = if !self.check(TokenKind::Semicolon) && !self.is_at_end() {
       Some(self.expression()?)
     } else {
       None
     };
 
-    self.consume(TokenKind::Semicolon, "Expected ';' after return value.")?;
-    Ok(Stmt::Return(value))
+    self.consume_semicolon("Expected ';' after return value.")?;
+    Ok(self.stmt(StmtKind::Return(value), keyword.location))
   }
 
-  fn switch_statement(&mut self) -> MewResult<Stmt> {
+  fn switch_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
     self.consume(TokenKind::LeftParen, "Expected '(' after 'catwalk'.")?;
     let value = self.expression()?;
     self.consume(TokenKind::RightParen, "Expected ')' after value.")?;
 
-    self.consume(TokenKind::LeftBrace, "Expected '{' after switch value.")?;
+    let open = self.consume(TokenKind::LeftBrace, "Expected '{' after switch value.")?;
 
     let mut cases = Vec::new();
 
@@ -522,40 +627,136 @@ impl Parser {
 
         cases.push((None, statements));
       } else {
-        return Err(MewError::syntax(
+        return Err(Diagnostic::new(
           "Expected 'claw' or 'default' in switch statement.",
+          self.error_span(),
         ));
       }
     }
 
-    self.consume(TokenKind::RightBrace, "Expected '}' after switch cases.")?;
+    self.consume_closing(
+      TokenKind::RightBrace,
+      "Expected '}' after switch cases.",
+      &open,
+      "cases opened here",
+    )?;
+
+    Ok(self.stmt(StmtKind::Switch(value, cases), start))
+  }
+
+  fn try_statement(&mut self) -> ParseResult<Stmt> {
+    let keyword = self.previous();
+    self.consume(TokenKind::LeftBrace, "Expected '{' after 'purrhaps'.")?;
+    let body = self.block()?;
+
+    let catch_clause = if self.match_tokens(&[TokenKind::Catch]) {
+      let binding = if self.match_tokens(&[TokenKind::LeftParen]) {
+        let name = self.consume_identifier("Expected error name in 'catch'.")?;
+        self.consume(TokenKind::RightParen, "Expected ')' after error name.")?;
+        Some(name)
+      } else {
+        None
+      };
+
+      self.consume(TokenKind::LeftBrace, "Expected '{' after 'catch'.")?;
+      Some((binding, self.block()?))
+    } else {
+      None
+    };
+
+    let finally_block = if self.match_tokens(&[TokenKind::Finally]) {
+      self.consume(TokenKind::LeftBrace, "Expected '{' after 'furever'.")?;
+      Some(self.block()?)
+    } else {
+      None
+    };
+
+    if catch_clause.is_none() && finally_block.is_none() {
+      return Err(
+        Diagnostic::new(
+          "Expected 'catch' or 'furever' after 'purrhaps' block.",
+          keyword.span(),
+        )
+        .with_help("a 'purrhaps' block needs a 'catch' block, a 'furever' block or both"),
+      );
+    }
+
+    Ok(self.stmt(
+      StmtKind::Try(body, catch_clause, finally_block),
+      keyword.location,
+    ))
+  }
 
-    Ok(Stmt::Switch(value, cases))
+  fn throw_statement(&mut self) -> ParseResult<Stmt> {
+    let start = self.previous().location;
+    let value = self.expression()?;
+    self.consume_semicolon("Expected ';' after hairball value.")?;
+    Ok(self.stmt(StmtKind::Throw(value), start))
   }
 
-  fn expression_statement(&mut self) -> MewResult<Stmt> {
+  fn expression_statement(&mut self) -> ParseResult<Stmt> {
     let expr = self.expression()?;
-    self.consume(TokenKind::Semicolon, "Expected ';' after expression.")?;
-    Ok(Stmt::Expression(expr))
+    let start = expr.span.start;
+
+    self.consume_semicolon("Expected ';' after expression.")?;
+    Ok(self.stmt(StmtKind::Expression(expr), start))
   }
 
-  fn expression(&mut self) -> MewResult<Expr> {
+  fn expression(&mut self) -> ParseResult<Expr> {
     self.assignment()
   }
 
-  fn assignment(&mut self) -> MewResult<Expr> {
-    let expr = self.or()?;
+  fn assignment(&mut self) -> ParseResult<Expr> {
+    self.nested(Self::parse_assignment)
+  }
+
+  fn parse_assignment(&mut self) -> ParseResult<Expr> {
+    let mut expr = self.nullish()?;
+    let start = expr.span.start;
 
     if self.match_tokens(&[TokenKind::Equal]) {
       let value = self.assignment()?;
 
-      if let Expr::Variable(name) = expr {
-        return Ok(Expr::Assignment(name, Box::new(value)));
-      } else if let Expr::Get(obj, name) = expr {
-        return Ok(Expr::Set(obj, name, Box::new(value)));
+      let target = expr.span;
+      if Self::is_optional_chain(&expr) {
+        return Err(invalid_target("assignment", target));
       }
 
-      return Err(MewError::syntax("Invalid assignment target."));
+      let kind = match std::mem::replace(&mut expr.kind, ExprKind::Literal(Value::Null)) {
+        ExprKind::Variable(name, _) => ExprKind::Assignment(name, Box::new(value), None),
+        ExprKind::Get(obj, name) => ExprKind::Set(obj, name, Box::new(value)),
+        ExprKind::Index(obj, index) => ExprKind::SetIndex(obj, index, Box::new(value)),
+        _ => return Err(invalid_target("assignment", target)),
+      };
+      return Ok(self.expr(kind, start));
+    }
+
+    if self.match_tokens(&[
+      TokenKind::PlusEqual,
+      TokenKind::MinusEqual,
+      TokenKind::StarEqual,
+      TokenKind::SlashEqual,
+      TokenKind::PercentEqual,
+    ]) {
+      let operator = match self.previous().kind {
+        TokenKind::PlusEqual => BinaryOp::Add,
+        TokenKind::MinusEqual => BinaryOp::Sub,
+        TokenKind::StarEqual => BinaryOp::Mul,
+        TokenKind::SlashEqual => BinaryOp::Div,
+        TokenKind::PercentEqual => BinaryOp::Mod,
+        _ => unreachable!(),
+      };
+      let value = self.assignment()?;
+
+      let target = expr.span;
+      return match expr.kind {
+        _ if Self::is_optional_chain(&expr) => Err(invalid_target("assignment", target)),
+        ExprKind::Variable(..) | ExprKind::Get(_, _) | ExprKind::Index(_, _) => Ok(self.expr(
+          ExprKind::CompoundAssignment(Box::new(expr), operator, Box::new(value)),
+          start,
+        )),
+        _ => Err(invalid_target("assignment", target)),
+      };
     }
 
     if self.match_tokens(&[TokenKind::Question]) {
@@ -568,42 +769,78 @@ impl Parser {
 
       let else_expr = self.assignment()?;
 
-      return Ok(Expr::Ternary(
-        Box::new(expr),
-        Box::new(then_expr),
-        Box::new(else_expr),
+      return Ok(self.expr(
+        ExprKind::Ternary(Box::new(expr), Box::new(then_expr), Box::new(else_expr)),
+        start,
       ));
     }
 
     Ok(expr)
   }
 
-  fn or(&mut self) -> MewResult<Expr> {
+  fn nullish(&mut self) -> ParseResult<Expr> {
+    let mut expr = self.or()?;
+    let start = expr.span.start;
+
+    while self.match_tokens(&[TokenKind::QuestionQuestion]) {
+      let right = self.or()?;
+      expr = self.expr(
+        ExprKind::Binary(Box::new(expr), BinaryOp::Nullish, Box::new(right)),
+        start,
+      );
+    }
+
+    Ok(expr)
+  }
+
+  // Whether an expression contains a `?.`, which can't be assigned to
+  fn is_optional_chain(mut expr: &Expr) -> bool {
+    loop {
+      match &expr.kind {
+        ExprKind::Optional(_) => return true,
+        ExprKind::Get(object, _) | ExprKind::Index(object, _) | ExprKind::Call(object, _) => {
+          expr = object
+        }
+        _ => return false,
+      }
+    }
+  }
+
+  fn or(&mut self) -> ParseResult<Expr> {
     let mut expr = self.and()?;
+    let start = expr.span.start;
 
     while self.match_tokens(&[TokenKind::Or]) {
       let operator = BinaryOp::Or;
       let right = self.and()?;
-      expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+      expr = self.expr(
+        ExprKind::Binary(Box::new(expr), operator, Box::new(right)),
+        start,
+      );
     }
 
     Ok(expr)
   }
 
-  fn and(&mut self) -> MewResult<Expr> {
+  fn and(&mut self) -> ParseResult<Expr> {
     let mut expr = self.equality()?;
+    let start = expr.span.start;
 
     while self.match_tokens(&[TokenKind::And]) {
       let operator = BinaryOp::And;
       let right = self.equality()?;
-      expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+      expr = self.expr(
+        ExprKind::Binary(Box::new(expr), operator, Box::new(right)),
+        start,
+      );
     }
 
     Ok(expr)
   }
 
-  fn equality(&mut self) -> MewResult<Expr> {
+  fn equality(&mut self) -> ParseResult<Expr> {
     let mut expr = self.comparison()?;
+    let start = expr.span.start;
 
     while self.match_tokens(&[TokenKind::BangEqual, TokenKind::EqualEqual]) {
       let operator = match self.previous().kind {
@@ -612,37 +849,47 @@ impl Parser {
         _ => unreachable!(),
       };
       let right = self.comparison()?;
-      expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+      expr = self.expr(
+        ExprKind::Binary(Box::new(expr), operator, Box::new(right)),
+        start,
+      );
     }
 
     Ok(expr)
   }
 
-  fn comparison(&mut self) -> MewResult<Expr> {
+  fn comparison(&mut self) -> ParseResult<Expr> {
     let mut expr = self.term()?;
+    let start = expr.span.start;
 
     while self.match_tokens(&[
       TokenKind::Greater,
       TokenKind::GreaterEqual,
       TokenKind::Less,
       TokenKind::LessEqual,
+      TokenKind::InstanceOf,
     ]) {
       let operator = match self.previous().kind {
         TokenKind::Greater => BinaryOp::Gt,
         TokenKind::GreaterEqual => BinaryOp::Gte,
         TokenKind::Less => BinaryOp::Lt,
         TokenKind::LessEqual => BinaryOp::Lte,
+        TokenKind::InstanceOf => BinaryOp::InstanceOf,
         _ => unreachable!(),
       };
       let right = self.term()?;
-      expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+      expr = self.expr(
+        ExprKind::Binary(Box::new(expr), operator, Box::new(right)),
+        start,
+      );
     }
 
     Ok(expr)
   }
 
-  fn term(&mut self) -> MewResult<Expr> {
+  fn term(&mut self) -> ParseResult<Expr> {
     let mut expr = self.factor()?;
+    let start = expr.span.start;
 
     while self.match_tokens(&[TokenKind::Minus, TokenKind::Plus]) {
       let operator = match self.previous().kind {
@@ -651,14 +898,18 @@ impl Parser {
         _ => unreachable!(),
       };
       let right = self.factor()?;
-      expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+      expr = self.expr(
+        ExprKind::Binary(Box::new(expr), operator, Box::new(right)),
+        start,
+      );
     }
 
     Ok(expr)
   }
 
-  fn factor(&mut self) -> MewResult<Expr> {
+  fn factor(&mut self) -> ParseResult<Expr> {
     let mut expr = self.unary()?;
+    let start = expr.span.start;
 
     while self.match_tokens(&[TokenKind::Slash, TokenKind::Star, TokenKind::Percent]) {
       let operator = match self.previous().kind {
@@ -668,13 +919,21 @@ impl Parser {
         _ => unreachable!(),
       };
       let right = self.unary()?;
-      expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+      expr = self.expr(
+        ExprKind::Binary(Box::new(expr), operator, Box::new(right)),
+        start,
+      );
     }
 
     Ok(expr)
   }
 
-  fn unary(&mut self) -> MewResult<Expr> {
+  fn unary(&mut self) -> ParseResult<Expr> {
+    self.nested(Self::parse_unary)
+  }
+
+  fn parse_unary(&mut self) -> ParseResult<Expr> {
+    let start = self.peek().location;
     if self.match_tokens(&[TokenKind::Bang, TokenKind::Minus]) {
       let operator = match self.previous().kind {
         TokenKind::Bang => UnaryOp::Not,
@@ -682,7 +941,7 @@ impl Parser {
         _ => unreachable!(),
       };
       let right = self.unary()?;
-      return Ok(Expr::Unary(operator, Box::new(right)));
+      return Ok(self.expr(ExprKind::Unary(operator, Box::new(right)), start));
     }
 
     if self.match_tokens(&[TokenKind::Increment, TokenKind::Decrement]) {
@@ -694,34 +953,53 @@ impl Parser {
 
       let right = self.unary()?;
 
-      match &right {
-        Expr::Variable(_) | Expr::Get(_, _) => {
-          if is_increment {
-            return Ok(Expr::Increment(Box::new(right), true));
+      match &right.kind {
+        _ if Self::is_optional_chain(&right) => {
+          return Err(invalid_target("increment/decrement", right.span))
+        }
+        ExprKind::Variable(..) | ExprKind::Get(_, _) | ExprKind::Index(_, _) => {
+          let kind = if is_increment {
+            ExprKind::Increment(Box::new(right), true)
           } else {
-            return Ok(Expr::Decrement(Box::new(right), true));
-          }
+            ExprKind::Decrement(Box::new(right), true)
+          };
+          return Ok(self.expr(kind, start));
         }
-        _ => return Err(MewError::syntax("Invalid increment/decrement target.")),
+        _ => return Err(invalid_target("increment/decrement", right.span)),
       }
     }
 
     self.call()
   }
 
-  fn call(&mut self) -> MewResult<Expr> {
+  fn call(&mut self) -> ParseResult<Expr> {
     let mut expr = self.primary()?;
+    let start = expr.span.start;
 
     loop {
       if self.match_tokens(&[TokenKind::LeftParen]) {
         expr = self.finish_call(expr)?;
       } else if self.match_tokens(&[TokenKind::Dot]) {
-        let name = self.consume_identifier("Expected property name after '.'.")?;
-        expr = Expr::Get(Box::new(expr), name);
+        let name = self.consume_property_name("Expected property name after '.'.")?;
+        expr = self.expr(ExprKind::Get(Box::new(expr), name), start);
       } else if self.match_tokens(&[TokenKind::LeftBracket]) {
         let index = self.expression()?;
         self.consume(TokenKind::RightBracket, "Expected ']' after array index.")?;
-        expr = Expr::Get(Box::new(expr), format!("[{}]", index));
+        expr = self.expr(ExprKind::Index(Box::new(expr), Box::new(index)), start);
+      } else if self.match_tokens(&[TokenKind::QuestionDot]) {
+        let span = expr.span;
+        let object = Expr::new(ExprKind::Optional(Box::new(expr)), span);
+
+        expr = if self.match_tokens(&[TokenKind::LeftParen]) {
+          self.finish_call(object)?
+        } else if self.match_tokens(&[TokenKind::LeftBracket]) {
+          let index = self.expression()?;
+          self.consume(TokenKind::RightBracket, "Expected ']' after array index.")?;
+          self.expr(ExprKind::Index(Box::new(object), Box::new(index)), start)
+        } else {
+          let name = self.consume_property_name("Expected property name after '?.'.")?;
+          self.expr(ExprKind::Get(Box::new(object), name), start)
+        };
       } else if self.match_tokens(&[TokenKind::Increment, TokenKind::Decrement]) {
         let is_increment = match self.previous().kind {
           TokenKind::Increment => true,
@@ -729,15 +1007,19 @@ impl Parser {
           _ => unreachable!(),
         };
 
-        match &expr {
-          Expr::Variable(_) | Expr::Get(_, _) => {
-            if is_increment {
-              expr = Expr::Increment(Box::new(expr), false);
+        match &expr.kind {
+          _ if Self::is_optional_chain(&expr) => {
+            return Err(invalid_target("increment/decrement", expr.span))
+          }
+          ExprKind::Variable(..) | ExprKind::Get(_, _) | ExprKind::Index(_, _) => {
+            let kind = if is_increment {
+              ExprKind::Increment(Box::new(expr), false)
             } else {
-              expr = Expr::Decrement(Box::new(expr), false);
-            }
+              ExprKind::Decrement(Box::new(expr), false)
+            };
+            expr = self.expr(kind, start);
           }
-          _ => return Err(MewError::syntax("Invalid increment/decrement target.")),
+          _ => return Err(invalid_target("increment/decrement", expr.span)),
         }
       } else {
         break;
@@ -747,15 +1029,16 @@ impl Parser {
     Ok(expr)
   }
 
-  fn finish_call(&mut self, callee: Expr) -> MewResult<Expr> {
+  fn finish_call(&mut self, callee: Expr) -> ParseResult<Expr> {
+    let open = self.previous();
     let mut arguments = Vec::new();
 
     if !self.check(TokenKind::RightParen) {
       loop {
         if arguments.len() >= 255 {
-          return Err(MewError::syntax_at(
+          return Err(Diagnostic::new(
             "Cannot have more than 255 arguments.",
-            self.peek().location,
+            self.error_span(),
           ));
         }
 
@@ -767,85 +1050,119 @@ impl Parser {
       }
     }
 
-    self.consume(TokenKind::RightParen, "Expected ')' after arguments.")?;
+    self.consume_closing(
+      TokenKind::RightParen,
+      "Expected ')' after arguments.",
+      &open,
+      "arguments start here",
+    )?;
 
-    Ok(Expr::Call(Box::new(callee), arguments))
+    let start = callee.span.start;
+    Ok(self.expr(ExprKind::Call(Box::new(callee), arguments), start))
   }
 
-  fn primary(&mut self) -> MewResult<Expr> {
+  fn primary(&mut self) -> ParseResult<Expr> {
+    let start = self.peek().location;
+
     if self.match_tokens(&[TokenKind::Boolean(true)]) {
-      return Ok(Expr::Literal(Value::Bool(true)));
+      return Ok(self.expr(ExprKind::Literal(Value::Bool(true)), start));
     }
 
     if self.match_tokens(&[TokenKind::Boolean(false)]) {
-      return Ok(Expr::Literal(Value::Bool(false)));
+      return Ok(self.expr(ExprKind::Literal(Value::Bool(false)), start));
     }
 
     if self.match_tokens(&[TokenKind::Null]) {
-      return Ok(Expr::Literal(Value::Null));
+      return Ok(self.expr(ExprKind::Literal(Value::Null), start));
     }
 
     if self.match_tokens(&[TokenKind::Undefined]) {
-      return Ok(Expr::Literal(Value::Undefined));
+      return Ok(self.expr(ExprKind::Literal(Value::Undefined), start));
     }
 
-    if self.check_type_variant::<f64>(&TokenKind::Number(0.0)) {
-      if self.match_tokens(&[TokenKind::Number(0.0)]) {
-        if let TokenKind::Number(n) = self.previous().kind {
-          return Ok(Expr::Literal(Value::Number(n)));
-        }
+    if self.check_type_variant::<f64>(&TokenKind::Number(0.0))
+      && self.match_tokens(&[TokenKind::Number(0.0)])
+    {
+      if let TokenKind::Number(n) = self.previous().kind {
+        return Ok(self.expr(ExprKind::Literal(Value::Number(n)), start));
       }
     }
 
-    if self.check_type_variant::<String>(&TokenKind::String(String::new())) {
-      if self.match_tokens(&[TokenKind::String(String::new())]) {
-        if let TokenKind::String(s) = &self.previous().kind {
-          return Ok(Expr::Literal(Value::String(s.clone())));
-        }
+    if self.check_type_variant::<String>(&TokenKind::String(String::new()))
+      && self.match_tokens(&[TokenKind::String(String::new())])
+    {
+      if let TokenKind::String(s) = &self.previous().kind {
+        return Ok(self.expr(ExprKind::Literal(Value::String(s.clone())), start));
       }
     }
 
     if self.match_tokens(&[TokenKind::Infinity]) {
-      return Ok(Expr::Literal(Value::Number(f64::INFINITY)));
+      return Ok(self.expr(ExprKind::Literal(Value::Number(f64::INFINITY)), start));
     }
 
     if self.match_tokens(&[TokenKind::NaN]) {
-      return Ok(Expr::Literal(Value::Number(f64::NAN)));
+      return Ok(self.expr(ExprKind::Literal(Value::Number(f64::NAN)), start));
     }
 
     if self.match_tokens(&[TokenKind::LeftParen]) {
+      let open = self.previous();
       let expr = self.expression()?;
-      self.consume(TokenKind::RightParen, "Expected ')' after expression.")?;
+      self.consume_closing(
+        TokenKind::RightParen,
+        "Expected ')' after expression.",
+        &open,
+        "'(' opened here",
+      )?;
+
+      // `(a?.b).c` fails when `a` is null instead of skipping `.c`
+      if Self::is_optional_chain(&expr) {
+        return Ok(self.expr(ExprKind::Grouping(Box::new(expr)), start));
+      }
       return Ok(expr);
     }
 
     if self.match_tokens(&[TokenKind::LeftBracket]) {
-      return self.array_literal();
+      return self.array_literal(start);
     }
 
     if self.match_tokens(&[TokenKind::LeftBrace]) {
-      return self.object_literal();
+      return self.object_literal(start);
     }
 
     if self.match_tokens(&[TokenKind::Function]) {
-      return self.function_expression();
+      return self.function_expression(start);
     }
 
-    if self.check_type_variant::<String>(&TokenKind::Identifier(String::new())) {
-      if self.match_tokens(&[TokenKind::Identifier(String::new())]) {
-        if let TokenKind::Identifier(name) = &self.previous().kind {
-          return Ok(Expr::Variable(name.clone()));
-        }
+    if self.match_tokens(&[TokenKind::SelfKw]) {
+      return Ok(self.expr(ExprKind::Variable("self".to_string(), None), start));
+    }
+
+    if self.match_tokens(&[TokenKind::Super]) {
+      // `super(...)` calls the parent's init, `super.name` any parent method
+      if self.check(TokenKind::LeftParen) {
+        return Ok(self.expr(ExprKind::Super("init".to_string()), start));
+      }
+      self.consume(TokenKind::Dot, "Expected '(' or '.' after 'super'.")?;
+      let name = self.consume_identifier("Expected method name after 'super.'.")?;
+      return Ok(self.expr(ExprKind::Super(name), start));
+    }
+
+    if self.check_type_variant::<String>(&TokenKind::Identifier(String::new()))
+      && self.match_tokens(&[TokenKind::Identifier(String::new())])
+    {
+      if let TokenKind::Identifier(name) = &self.previous().kind {
+        return Ok(self.expr(ExprKind::Variable(name.clone(), None), start));
       }
     }
 
-    Err(MewError::syntax(format!(
-      "Expected expression, got {:?}.",
-      self.peek().kind
-    )))
+    Err(Diagnostic::new(
+      format!("Expected expression, got {:?}.", self.peek().kind),
+      self.error_span(),
+    ))
   }
 
-  fn array_literal(&mut self) -> MewResult<Expr> {
+  fn array_literal(&mut self, start: Location) -> ParseResult<Expr> {
+    let open = self.previous();
     let mut elements = Vec::new();
 
     if !self.check(TokenKind::RightBracket) {
@@ -862,41 +1179,28 @@ impl Parser {
       }
     }
 
-    self.consume(
+    self.consume_closing(
       TokenKind::RightBracket,
       "Expected ']' after array elements.",
+      &open,
+      "array opened here",
     )?;
 
-    Ok(Expr::ArrayLiteral(elements))
+    Ok(self.expr(ExprKind::ArrayLiteral(elements), start))
   }
 
-  fn object_literal(&mut self) -> MewResult<Expr> {
+  fn object_literal(&mut self, start: Location) -> ParseResult<Expr> {
+    let open = self.previous();
     let mut properties = Vec::new();
 
     if !self.check(TokenKind::RightBrace) {
       loop {
-        let key = if self.check_type_variant::<String>(&TokenKind::Identifier(String::new())) {
-          if self.check_next(TokenKind::Colon) {
-            if let TokenKind::Identifier(name) = &self.advance().kind {
-              name.clone()
-            } else {
-              unreachable!()
-            }
-          } else {
-            return Err(MewError::syntax("Expected ':' after property name."));
+        let key = match self.peek().kind.clone() {
+          TokenKind::String(s) => {
+            self.advance();
+            s
           }
-        } else if self.check_type_variant::<String>(&TokenKind::String(String::new())) {
-          if self.match_tokens(&[TokenKind::String(String::new())]) {
-            if let TokenKind::String(s) = &self.previous().kind {
-              s.clone()
-            } else {
-              unreachable!()
-            }
-          } else {
-            unreachable!()
-          }
-        } else {
-          return Err(MewError::syntax("Expected property name or string."));
+          _ => self.consume_property_name("Expected property name or string.")?,
         };
 
         self.consume(TokenKind::Colon, "Expected ':' after property name.")?;
@@ -915,15 +1219,17 @@ impl Parser {
       }
     }
 
-    self.consume(
+    self.consume_closing(
       TokenKind::RightBrace,
       "Expected '}' after object properties.",
+      &open,
+      "object opened here",
     )?;
 
-    Ok(Expr::ObjectLiteral(properties))
+    Ok(self.expr(ExprKind::ObjectLiteral(properties), start))
   }
 
-  fn function_expression(&mut self) -> MewResult<Expr> {
+  fn function_expression(&mut self, start: Location) -> ParseResult<Expr> {
     let name = if self.check_type_variant::<String>(&TokenKind::Identifier(String::new())) {
       if let TokenKind::Identifier(name) = &self.advance().kind {
         Some(name.clone())
@@ -934,15 +1240,15 @@ impl Parser {
       None
     };
 
-    self.consume(TokenKind::LeftParen, "Expected '(' after function name.")?;
+    let open = self.consume(TokenKind::LeftParen, "Expected '(' after function name.")?;
 
     let mut parameters = Vec::new();
     if !self.check(TokenKind::RightParen) {
       loop {
         if parameters.len() >= 255 {
-          return Err(MewError::syntax_at(
+          return Err(Diagnostic::new(
             "Cannot have more than 255 parameters.",
-            self.peek().location,
+            self.error_span(),
           ));
         }
 
@@ -954,23 +1260,80 @@ impl Parser {
       }
     }
 
-    self.consume(TokenKind::RightParen, "Expected ')' after parameters.")?;
+    self.consume_closing(
+      TokenKind::RightParen,
+      "Expected ')' after parameters.",
+      &open,
+      "parameters start here",
+    )?;
 
     if self.match_tokens(&[TokenKind::Arrow]) {
       if self.match_tokens(&[TokenKind::LeftBrace]) {
         let body = self.block()?;
-        return Ok(Expr::Function(name, parameters, body));
+        return Ok(self.expr(
+          ExprKind::Function(name, parameters, body, Vec::new()),
+          start,
+        ));
       } else {
         let expr = self.expression()?;
-        let body = vec![Rc::new(RefCell::new(Stmt::Return(Some(expr))))];
-        return Ok(Expr::Function(name, parameters, body));
+        let span = expr.span;
+        let body = vec![Rc::new(RefCell::new(Stmt::new(
+          StmtKind::Return(Some(expr)),
+          span,
+        )))];
+        return Ok(self.expr(
+          ExprKind::Function(name, parameters, body, Vec::new()),
+          start,
+        ));
       }
     }
 
     self.consume(TokenKind::LeftBrace, "Expected '{' before function body.")?;
     let body = self.block()?;
 
-    Ok(Expr::Function(name, parameters, body))
+    Ok(self.expr(
+      ExprKind::Function(name, parameters, body, Vec::new()),
+      start,
+    ))
+  }
+
+  fn expr(&self, kind: ExprKind, start: Location) -> Expr {
+    Expr::new(kind, self.span_from(start))
+  }
+
+  fn stmt(&self, kind: StmtKind, start: Location) -> Stmt {
+    Stmt::new(kind, self.span_from(start))
+  }
+
+  // Statements and expressions can contain themselves, so parsing them
+  // recurses. Past MAX_NESTING levels the input is rejected rather than risk
+  // running out of stack here or when the tree is run.
+  fn nested<T>(&mut self, parse: fn(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+    if self.depth >= MAX_NESTING {
+      return Err(
+        Diagnostic::new("Code is nested too deeply.", self.error_span())
+          .with_help("move the inner parts into variables or functions"),
+      );
+    }
+    self.depth += 1;
+    let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || parse(self));
+    self.depth -= 1;
+    result
+  }
+
+  // The token the parser is stuck at. The end of the input is pointed at just
+  // past the last token, rather than on the line after it.
+  fn error_span(&self) -> Span {
+    if self.is_at_end() && self.current > 0 {
+      let end = self.previous().end();
+      return Span::new(end, end);
+    }
+    self.peek().span()
+  }
+
+  // From `start` to the end of the last consumed token
+  fn span_from(&self, start: Location) -> Span {
+    Span::new(start, self.tokens[self.current - 1].end())
   }
 
   fn match_tokens(&mut self, types: &[TokenKind]) -> bool {
@@ -984,29 +1347,83 @@ impl Parser {
     false
   }
 
-  fn consume(&mut self, kind: TokenKind, message: &str) -> MewResult<Token> {
+  fn consume(&mut self, kind: TokenKind, message: &str) -> ParseResult<Token> {
     if self.check(kind) {
       Ok(self.advance())
     } else {
       let token = self.peek();
-      Err(MewError::syntax_at(
+      Err(Diagnostic::new(
         format!("{} Got {:?}", message, token.kind),
-        token.location,
+        self.error_span(),
       ))
     }
   }
 
-  fn consume_identifier(&mut self, message: &str) -> MewResult<String> {
-    if self.check_type_variant::<String>(&TokenKind::Identifier(String::new())) {
-      if self.match_tokens(&[TokenKind::Identifier(String::new())]) {
-        if let TokenKind::Identifier(name) = &self.previous().kind {
-          return Ok(name.clone());
-        }
+  // The last statement of the input may omit its ';', so `eval("1 + 2")` and
+  // REPL lines like `catlt x = 1` work
+  fn consume_semicolon(&mut self, message: &str) -> ParseResult<()> {
+    if self.is_at_end() || self.check(TokenKind::Semicolon) {
+      self.advance();
+      return Ok(());
+    }
+
+    // Point just past the statement, where the ';' is missing, rather than
+    // at whatever follows it, which may be lines further on
+    let end = self.previous().end();
+    let next = self.peek().span();
+    let error = Diagnostic::new(
+      format!("{} Got {:?}", message, self.peek().kind),
+      Span::new(end, end),
+    )
+    .with_help("end the statement with ';'");
+
+    // A statement that ends its line is complete apart from the ';', so the
+    // parser can go on with the next one as it is
+    if next.start.line > end.line {
+      self.report(error.with_label(next, "the next statement starts here"));
+      return Ok(());
+    }
+    Err(error)
+  }
+
+  // Like `consume`, for the bracket closing `open`, which the error points
+  // back to
+  fn consume_closing(
+    &mut self,
+    kind: TokenKind,
+    message: &str,
+    open: &Token,
+    label: &str,
+  ) -> ParseResult<Token> {
+    self
+      .consume(kind, message)
+      .map_err(|error| error.with_label(open.span(), label))
+  }
+
+  fn consume_identifier(&mut self, message: &str) -> ParseResult<String> {
+    if self.check_type_variant::<String>(&TokenKind::Identifier(String::new()))
+      && self.match_tokens(&[TokenKind::Identifier(String::new())])
+    {
+      if let TokenKind::Identifier(name) = &self.previous().kind {
+        return Ok(name.clone());
       }
     }
 
-    let token = self.peek();
-    Err(MewError::syntax_at(message, token.location))
+    Err(Diagnostic::new(message, self.error_span()))
+  }
+
+  // Property names may be any word, including reserved ones like `breed`
+  fn consume_property_name(&mut self, message: &str) -> ParseResult<String> {
+    let token = self.peek().clone();
+    if token
+      .lexeme
+      .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+    {
+      self.advance();
+      return Ok(token.lexeme);
+    }
+
+    Err(Diagnostic::new(message, self.error_span()))
   }
 
   fn check(&self, kind: TokenKind) -> bool {
@@ -1081,16 +1498,47 @@ impl Parser {
     self.tokens[self.current - 1].clone()
   }
 
+  // Note a syntax error and skip what is left of the broken statement, making
+  // sure the parser moves on even when nothing could be parsed at all
+  fn recover(&mut self, error: Diagnostic, start: usize) {
+    self.report(error);
+
+    self.synchronize();
+    if self.current == start {
+      self.advance();
+      self.synchronize();
+    }
+  }
+
+  fn report(&mut self, mut error: Diagnostic) {
+    error.at_eof = self.is_at_end();
+
+    // Errors at the same place are knock-on effects of the first
+    let repeated = self
+      .errors
+      .last()
+      .is_some_and(|last| last.span.start == error.span.start);
+    if !repeated {
+      self.errors.push(error);
+    }
+  }
+
+  // Skip to the start of the next statement or to the '}' closing the
+  // enclosing block. Blocks opened along the way are skipped whole.
   fn synchronize(&mut self) {
-    self.advance();
+    let mut depth = 0;
 
     while !self.is_at_end() {
-      if matches!(self.previous().kind, TokenKind::Semicolon) {
-        return;
-      }
-
       match self.peek().kind {
+        TokenKind::LeftBrace => depth += 1,
+        TokenKind::RightBrace if depth == 0 => return,
+        TokenKind::RightBrace => depth -= 1,
+        TokenKind::Semicolon if depth == 0 => {
+          self.advance();
+          return;
+        }
         TokenKind::Function
+        | TokenKind::Class
         | TokenKind::Var
         | TokenKind::Let
         | TokenKind::Const
@@ -1098,7 +1546,13 @@ impl Parser {
         | TokenKind::If
         | TokenKind::While
         | TokenKind::Print
-        | TokenKind::Return => return,
+        | TokenKind::Try
+        | TokenKind::Throw
+        | TokenKind::Return
+          if depth == 0 =>
+        {
+          return
+        }
         _ => {}
       }
 
@@ -1106,3 +1560,17 @@ impl Parser {
     }
   }
 }
+
+fn invalid_target(operation: &str, target: Span) -> Diagnostic {
+  Diagnostic::new(format!("Invalid {} target.", operation), target).with_help(
+    "only a variable, a property like `cat.name` or an element like `list[0]` can be changed",
+  )
+}
+
+fn var_kind(keyword: &TokenKind) -> VarKind {
+  match keyword {
+    TokenKind::Let => VarKind::Let,
+    TokenKind::Const => VarKind::Const,
+    _ => VarKind::Var,
+  }
+}
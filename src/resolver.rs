@@ -0,0 +1,630 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::{MewError, MewResult};
+use crate::interpreter::{STACK_RED_ZONE, STACK_SEGMENT};
+use crate::value::{ClassMember, Expr, ExprKind, Slot, Stmt, StmtKind, VarKind};
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Works out which declaration each variable in a program refers to before
+/// it runs. Local variables are given the [`Slot`] they live in, so the
+/// interpreter reads them without looking up names; top-level variables
+/// stay globals. It also rejects what can be caught without running the
+/// code: variables used before their declaration, `catlt` or `catst`
+/// declared twice in one scope, assignments to constants and `clawt`,
+/// `meownext` or `return` with nothing to leave.
+///
+/// Scopes follow the environments the tree-walking interpreter creates at
+/// runtime one for one, which is what makes the slots line up. A scope
+/// holds, in this order, the parameters of a function, the `catv` variables
+/// hoisted out of its body, the `cat` functions declared in it and then its
+/// `catlt`, `catst` and breed declarations as they run.
+#[derive(Default)]
+pub struct Resolver {
+  scopes: Vec<Scope>,
+  // How many functions deep the resolver currently is
+  functions: usize,
+  // Loops, `catwalk` and labeled statements around the current statement,
+  // innermost last. Function bodies start with none.
+  targets: Vec<Target>,
+}
+
+// Something `clawt` or `meownext` can leave
+struct Target {
+  label: Option<String>,
+  is_loop: bool,
+  // Whether an unlabeled `clawt` leaves it, as it does loops and `catwalk`
+  takes_break: bool,
+}
+
+struct Scope {
+  variables: HashMap<String, Variable>,
+  // Top-level variables are looked up by name, as the REPL and modules add
+  // to the same scope over several runs
+  is_global: bool,
+  // Whether `catv` declarations inside it belong to it, as they do for the
+  // top level and function bodies
+  is_function: bool,
+  function: usize,
+}
+
+struct Variable {
+  // Position in the scope, in the order the declarations run
+  index: usize,
+  // Declared with `catlt`, `catst`, `breed` or an import, which can't be
+  // used before or declared again
+  lexical: bool,
+  is_const: bool,
+  defined: bool,
+}
+
+impl Resolver {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Check a program and record the slots of its local variables in it
+  pub fn resolve(mut self, statements: &[Rc<RefCell<Stmt>>]) -> MewResult<()> {
+    self.scopes.push(Scope {
+      variables: HashMap::new(),
+      is_global: true,
+      is_function: true,
+      function: 0,
+    });
+
+    for name in hoisted_vars(statements) {
+      self.bind(&name, false)?;
+    }
+    self.block(statements)
+  }
+
+  fn begin_scope(&mut self, is_function: bool) {
+    self.scopes.push(Scope {
+      variables: HashMap::new(),
+      is_global: false,
+      is_function,
+      function: self.functions,
+    });
+  }
+
+  fn end_scope(&mut self) {
+    self.scopes.pop();
+  }
+
+  // Everything a block declares is known before its statements are
+  // resolved, so using a name before its declaration can't silently pick
+  // up a variable from an outer scope. Functions come first, as they are
+  // defined before the block runs.
+  fn block(&mut self, statements: &[Rc<RefCell<Stmt>>]) -> MewResult<()> {
+    for statement in statements {
+      let statement = statement.borrow();
+      if statement.declares_function() {
+        self
+          .declare_statement(&statement)
+          .map_err(|e| e.at(statement.span))?;
+      }
+    }
+
+    for statement in statements {
+      let statement = statement.borrow();
+      if !statement.declares_function() {
+        self
+          .declare_statement(&statement)
+          .map_err(|e| e.at(statement.span))?;
+      }
+    }
+
+    for statement in statements {
+      self.statement(&mut statement.borrow_mut())?;
+    }
+
+    Ok(())
+  }
+
+  fn scoped_block(&mut self, statements: &[Rc<RefCell<Stmt>>]) -> MewResult<()> {
+    self.begin_scope(false);
+    self.block(statements)?;
+    self.end_scope();
+    Ok(())
+  }
+
+  // `catv` variables are declared with the function they belong to
+  fn declare_statement(&mut self, stmt: &Stmt) -> MewResult<()> {
+    match &stmt.kind {
+      StmtKind::VarDeclaration(_, _, VarKind::Var) => Ok(()),
+      StmtKind::VarDeclaration(name, _, kind) => self.declare(name, true, *kind == VarKind::Const),
+      StmtKind::Function(name, ..) => self.bind(name, false),
+      StmtKind::Class(name, _, _) => self.declare(name, true, false),
+      StmtKind::Import(names, _) => {
+        for name in names {
+          self.declare(name, true, true)?;
+        }
+        Ok(())
+      }
+      StmtKind::Export(declaration) => self.declare_statement(&declaration.borrow()),
+      _ => Ok(()),
+    }
+  }
+
+  fn declare(&mut self, name: &str, lexical: bool, is_const: bool) -> MewResult<()> {
+    let scope = self.scopes.last_mut().expect("no scope to declare in");
+    let index = scope.variables.len();
+
+    match scope.variables.entry(name.to_string()) {
+      // `catv` and functions may be declared again, and keep their slot
+      Entry::Occupied(entry) => {
+        if lexical || entry.get().lexical {
+          return Err(MewError::syntax(format!(
+            "'{}' is already declared in this scope",
+            name
+          )));
+        }
+      }
+      Entry::Vacant(entry) => {
+        entry.insert(Variable {
+          index,
+          lexical,
+          is_const,
+          defined: false,
+        });
+      }
+    }
+
+    Ok(())
+  }
+
+  fn define(&mut self, name: &str) {
+    let scope = self.scopes.last_mut().expect("no scope to define in");
+    if let Some(variable) = scope.variables.get_mut(name) {
+      variable.defined = true;
+    }
+  }
+
+  // Declare a variable that is defined as soon as its scope starts, like a
+  // parameter
+  fn bind(&mut self, name: &str, is_const: bool) -> MewResult<()> {
+    self.declare(name, false, is_const)?;
+    self.define(name);
+    Ok(())
+  }
+
+  fn look_up(&self, name: &str, is_assignment: bool) -> MewResult<Option<Slot>> {
+    for (depth, scope) in self.scopes.iter().rev().enumerate() {
+      if let Some(variable) = scope.variables.get(name) {
+        // Code inside a function may well run after the declaration
+        if variable.lexical && !variable.defined && scope.function == self.functions {
+          return Err(MewError::syntax(format!(
+            "Cannot use '{}' before its declaration",
+            name
+          )));
+        }
+
+        if is_assignment && variable.is_const {
+          return Err(MewError::syntax(format!(
+            "Cannot reassign to constant '{}'",
+            name
+          )));
+        }
+
+        if scope.is_global {
+          return Ok(None);
+        }
+
+        return Ok(Some(Slot {
+          depth,
+          index: variable.index,
+        }));
+      }
+    }
+
+    // Defined by the host, an earlier run or not at all
+    Ok(None)
+  }
+
+  fn statement(&mut self, stmt: &mut Stmt) -> MewResult<()> {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+      self.resolve_statement(stmt)
+    })
+  }
+
+  fn resolve_statement(&mut self, stmt: &mut Stmt) -> MewResult<()> {
+    let span = stmt.span;
+    match &mut stmt.kind {
+      StmtKind::Expression(expr) | StmtKind::Print(expr) | StmtKind::Throw(expr) => {
+        self.expression(expr)
+      }
+      StmtKind::VarDeclaration(name, initializer, kind) => {
+        if let Some(initializer) = initializer {
+          self.expression(initializer)?;
+        }
+        if *kind == VarKind::Var {
+          self.check_var(name).map_err(|e| e.at(span))
+        } else {
+          self.define(name);
+          Ok(())
+        }
+      }
+      StmtKind::Block(statements) => self.scoped_block(statements),
+      StmtKind::If(condition, then_branch, else_branch) => {
+        self.expression(condition)?;
+        self.statement(&mut then_branch.borrow_mut())?;
+        if let Some(else_branch) = else_branch {
+          self.statement(&mut else_branch.borrow_mut())?;
+        }
+        Ok(())
+      }
+      StmtKind::While(condition, body) => {
+        self.expression(condition)?;
+        self.loop_body(body)
+      }
+      StmtKind::DoWhile(body, condition) => {
+        self.loop_body(body)?;
+        self.expression(condition)
+      }
+      StmtKind::For(initializer, condition, increment, body) => {
+        self.begin_scope(false);
+        if let Some(initializer) = initializer {
+          self.declare_statement(&initializer.borrow())?;
+          self.statement(&mut initializer.borrow_mut())?;
+        }
+        self.expression(condition)?;
+        if let Some(increment) = increment {
+          self.expression(increment)?;
+        }
+        self.loop_body(body)?;
+        self.end_scope();
+        Ok(())
+      }
+      StmtKind::ForIn(name, is_const, _, iterable, body) => {
+        self.expression(iterable)?;
+        self.begin_scope(false);
+        self.bind(name, *is_const)?;
+        self.loop_body(body)?;
+        self.end_scope();
+        Ok(())
+      }
+      StmtKind::Labeled(label, body) => {
+        let is_loop = matches!(
+          body.borrow().kind,
+          StmtKind::While(..) | StmtKind::DoWhile(..) | StmtKind::For(..) | StmtKind::ForIn(..)
+        );
+        self.targets.push(Target {
+          label: Some(label.clone()),
+          is_loop,
+          takes_break: false,
+        });
+        let result = self.statement(&mut body.borrow_mut());
+        self.targets.pop();
+        result
+      }
+      StmtKind::Function(_, params, body, hoisted) => self.function(params, body, hoisted),
+      StmtKind::Return(value) => {
+        if self.functions == 0 {
+          return Err(MewError::syntax("'return' used outside of a function").at(span));
+        }
+        match value {
+          Some(value) => self.expression(value),
+          None => Ok(()),
+        }
+      }
+      StmtKind::Break(label) => self
+        .jump_target(label.as_deref(), false)
+        .map_err(|e| e.at(span)),
+      StmtKind::Continue(label) => self
+        .jump_target(label.as_deref(), true)
+        .map_err(|e| e.at(span)),
+      StmtKind::Switch(subject, cases) => {
+        self.expression(subject)?;
+        self.targets.push(Target {
+          label: None,
+          is_loop: false,
+          takes_break: true,
+        });
+        for (value, statements) in cases {
+          if let Some(value) = value {
+            self.expression(value)?;
+          }
+          self.scoped_block(statements)?;
+        }
+        self.targets.pop();
+        Ok(())
+      }
+      StmtKind::Import(names, _) => {
+        for name in names.iter() {
+          self.define(name);
+        }
+        Ok(())
+      }
+      StmtKind::Export(declaration) => self.statement(&mut declaration.borrow_mut()),
+      StmtKind::Class(name, parent, members) => {
+        if let Some(parent) = parent {
+          self.expression(parent)?;
+        }
+
+        // Methods and static fields see `self` and `super` in a scope
+        // between them and the one the breed is declared in
+        self.begin_scope(false);
+        self.bind("self", true)?;
+        if parent.is_some() {
+          self.bind("super", true)?;
+        }
+        for member in members {
+          match member {
+            ClassMember::Method(_, params, body, _, hoisted) => {
+              self.function(params, body, hoisted)?
+            }
+            ClassMember::StaticField(_, value) => self.expression(value)?,
+          }
+        }
+        self.end_scope();
+
+        self.define(name);
+        Ok(())
+      }
+      StmtKind::Try(body, catch_clause, finally_block) => {
+        self.scoped_block(body)?;
+        if let Some((binding, handler)) = catch_clause {
+          self.begin_scope(false);
+          if let Some(binding) = binding {
+            self.bind(binding, false)?;
+          }
+          self.block(handler)?;
+          self.end_scope();
+        }
+        if let Some(finally_block) = finally_block {
+          self.scoped_block(finally_block)?;
+        }
+        Ok(())
+      }
+    }
+  }
+
+  fn loop_body(&mut self, body: &Rc<RefCell<Stmt>>) -> MewResult<()> {
+    self.targets.push(Target {
+      label: None,
+      is_loop: true,
+      takes_break: true,
+    });
+    let result = self.statement(&mut body.borrow_mut());
+    self.targets.pop();
+    result
+  }
+
+  // `clawt` and `meownext` need an enclosing statement to leave, and
+  // `meownext` only continues loops
+  fn jump_target(&self, label: Option<&str>, is_continue: bool) -> MewResult<()> {
+    let found = self.targets.iter().any(|target| match label {
+      Some(label) => target.label.as_deref() == Some(label) && (target.is_loop || !is_continue),
+      None if is_continue => target.is_loop,
+      None => target.takes_break,
+    });
+    if found {
+      return Ok(());
+    }
+
+    Err(MewError::syntax(match (label, is_continue) {
+      (Some(label), _) => format!("Undefined label '{}'", label),
+      (None, false) => "'clawt' used outside of a loop or catwalk".to_string(),
+      (None, true) => "'meownext' used outside of a loop".to_string(),
+    }))
+  }
+
+  // `catv` can't redeclare a `catlt` or `catst` variable it would be
+  // hoisted past
+  fn check_var(&self, name: &str) -> MewResult<()> {
+    for scope in self.scopes.iter().rev() {
+      if let Some(variable) = scope.variables.get(name) {
+        if variable.lexical {
+          return Err(MewError::syntax(format!(
+            "'{}' is already declared in this scope",
+            name
+          )));
+        }
+      }
+
+      if scope.is_function {
+        break;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn function(
+    &mut self,
+    params: &[String],
+    body: &[Rc<RefCell<Stmt>>],
+    hoisted: &mut Vec<String>,
+  ) -> MewResult<()> {
+    self.functions += 1;
+    self.begin_scope(true);
+    let targets = std::mem::take(&mut self.targets);
+
+    for param in params {
+      self.bind(param, false)?;
+    }
+    *hoisted = hoisted_vars(body);
+    for name in hoisted.iter() {
+      self.bind(name, false)?;
+    }
+    let result = self.block(body);
+
+    self.targets = targets;
+    self.end_scope();
+    self.functions -= 1;
+    result
+  }
+
+  fn expression(&mut self, expr: &mut Expr) -> MewResult<()> {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+      self.resolve_expression(expr)
+    })
+  }
+
+  fn resolve_expression(&mut self, expr: &mut Expr) -> MewResult<()> {
+    let span = expr.span;
+    match &mut expr.kind {
+      ExprKind::Literal(_) | ExprKind::Super(_) => Ok(()),
+      ExprKind::Variable(name, slot) => {
+        *slot = self.look_up(name, false).map_err(|e| e.at(span))?;
+        Ok(())
+      }
+      ExprKind::Assignment(name, value, slot) => {
+        self.expression(value)?;
+        *slot = self.look_up(name, true).map_err(|e| e.at(span))?;
+        Ok(())
+      }
+      ExprKind::CompoundAssignment(target, _, value) => {
+        self.target(target)?;
+        self.expression(value)
+      }
+      ExprKind::Increment(target, _) | ExprKind::Decrement(target, _) => self.target(target),
+      ExprKind::Binary(left, _, right) => {
+        self.expression(left)?;
+        self.expression(right)
+      }
+      ExprKind::Unary(_, operand)
+      | ExprKind::Get(operand, _)
+      | ExprKind::Optional(operand)
+      | ExprKind::Grouping(operand) => self.expression(operand),
+      ExprKind::Call(callee, arguments) => {
+        self.expression(callee)?;
+        for argument in arguments {
+          self.expression(argument)?;
+        }
+        Ok(())
+      }
+      ExprKind::Set(object, _, value) => {
+        self.expression(object)?;
+        self.expression(value)
+      }
+      ExprKind::Index(object, index) => {
+        self.expression(object)?;
+        self.expression(index)
+      }
+      ExprKind::SetIndex(object, index, value) => {
+        self.expression(object)?;
+        self.expression(index)?;
+        self.expression(value)
+      }
+      ExprKind::ArrayLiteral(items) => {
+        for item in items {
+          self.expression(item)?;
+        }
+        Ok(())
+      }
+      ExprKind::ObjectLiteral(entries) => {
+        for (_, value) in entries {
+          self.expression(value)?;
+        }
+        Ok(())
+      }
+      ExprKind::Function(_, params, body, hoisted) => self.function(params, body, hoisted),
+      ExprKind::Ternary(condition, then_value, else_value) => {
+        self.expression(condition)?;
+        self.expression(then_value)?;
+        self.expression(else_value)
+      }
+    }
+  }
+
+  // The variable a compound assignment, `++` or `--` writes to
+  fn target(&mut self, target: &mut Expr) -> MewResult<()> {
+    let span = target.span;
+    match &mut target.kind {
+      ExprKind::Variable(name, slot) => {
+        *slot = self.look_up(name, true).map_err(|e| e.at(span))?;
+        Ok(())
+      }
+      _ => self.expression(target),
+    }
+  }
+}
+
+/// Names declared with `catv` in a function body or at the top level,
+/// including inside nested blocks but not nested functions, in the order
+/// they first appear
+pub(crate) fn hoisted_vars(statements: &[Rc<RefCell<Stmt>>]) -> Vec<String> {
+  let mut names = Vec::new();
+  nested(statements, &mut names);
+  names
+}
+
+/// Names declared with `catlt`, `catst` or `breed` directly at the top level
+pub(crate) fn top_level_lexicals(statements: &[Rc<RefCell<Stmt>>]) -> Vec<String> {
+  statements
+    .iter()
+    .filter_map(|statement| lexical_name(&statement.borrow()))
+    .collect()
+}
+
+fn lexical_name(stmt: &Stmt) -> Option<String> {
+  match &stmt.kind {
+    StmtKind::VarDeclaration(name, _, VarKind::Let | VarKind::Const)
+    | StmtKind::Class(name, _, _) => Some(name.clone()),
+    StmtKind::Export(declaration) => lexical_name(&declaration.borrow()),
+    _ => None,
+  }
+}
+
+fn nested(statements: &[Rc<RefCell<Stmt>>], names: &mut Vec<String>) {
+  for statement in statements {
+    collect_vars(&statement.borrow(), names);
+  }
+}
+
+fn collect_vars(stmt: &Stmt, names: &mut Vec<String>) {
+  match &stmt.kind {
+    StmtKind::VarDeclaration(name, _, VarKind::Var) if !names.contains(name) => {
+      names.push(name.clone());
+    }
+    StmtKind::Block(statements) => nested(statements, names),
+    StmtKind::If(_, then_branch, else_branch) => {
+      collect_vars(&then_branch.borrow(), names);
+      if let Some(else_branch) = else_branch {
+        collect_vars(&else_branch.borrow(), names);
+      }
+    }
+    StmtKind::While(_, body) | StmtKind::DoWhile(body, _) | StmtKind::Labeled(_, body) => {
+      collect_vars(&body.borrow(), names)
+    }
+    StmtKind::For(initializer, _, _, body) => {
+      if let Some(initializer) = initializer {
+        collect_vars(&initializer.borrow(), names);
+      }
+      collect_vars(&body.borrow(), names);
+    }
+    StmtKind::ForIn(_, _, _, _, body) => collect_vars(&body.borrow(), names),
+    StmtKind::Switch(_, cases) => {
+      for (_, statements) in cases {
+        nested(statements, names);
+      }
+    }
+    StmtKind::Try(body, catch_clause, finally_block) => {
+      nested(body, names);
+      if let Some((_, handler)) = catch_clause {
+        nested(handler, names);
+      }
+      if let Some(finally_block) = finally_block {
+        nested(finally_block, names);
+      }
+    }
+    StmtKind::Export(declaration) => collect_vars(&declaration.borrow(), names),
+    _ => {}
+  }
+}
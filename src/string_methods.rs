@@ -0,0 +1,359 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Methods available on string values, e.g. `"meow".toUpperCase()`.
+//!
+//! Every method receives the string it was called on as its first argument.
+//! Positions and lengths count Unicode characters, not bytes.
+
+use crate::error::{MewError, MewResult};
+use crate::interpreter::Interpreter;
+use crate::value::{NativeFunctionType, Value};
+
+/// String methods by name
+pub const METHODS: &[(&str, NativeFunctionType)] = &[
+  ("charAt", char_at),
+  ("substring", substring),
+  ("slice", slice),
+  ("indexOf", index_of),
+  ("includes", includes),
+  ("startsWith", starts_with),
+  ("endsWith", ends_with),
+  ("split", split),
+  ("join", join),
+  ("toUpperCase", to_upper_case),
+  ("toLowerCase", to_lower_case),
+  ("trim", trim),
+  ("trimStart", trim_start),
+  ("trimEnd", trim_end),
+  ("replace", replace),
+  ("replaceAll", replace_all),
+  ("repeat", repeat),
+  ("padStart", pad_start),
+  ("padEnd", pad_end),
+];
+
+/// Find the native implementing the string method `name`
+pub fn lookup(name: &str) -> Option<NativeFunctionType> {
+  METHODS
+    .iter()
+    .find(|(method, _)| *method == name)
+    .map(|(_, function)| *function)
+}
+
+/// Number of characters in a string
+pub fn length(s: &str) -> usize {
+  s.chars().count()
+}
+
+/// The character at `index` as a string, if there is one
+pub fn char_at_index(s: &str, index: usize) -> Option<Value> {
+  s.chars().nth(index).map(|c| Value::String(c.to_string()))
+}
+
+// Split the receiving string off the front of a method's arguments
+fn receiver(args: Vec<Value>, method: &str) -> MewResult<(String, Vec<Value>)> {
+  let mut args = args.into_iter();
+
+  match args.next() {
+    Some(Value::String(ref mut s)) => Ok((std::mem::take(s), args.collect())),
+    _ => Err(MewError::type_error(format!(
+      "String method '{}' must be called on a string",
+      method
+    ))),
+  }
+}
+
+fn string_arg(args: &[Value], index: usize, method: &str) -> MewResult<String> {
+  match args.get(index) {
+    Some(Value::String(s)) => Ok(s.clone()),
+    Some(other) => Err(MewError::type_error(format!(
+      "{} expects a string argument, got {}",
+      method,
+      other.type_name()
+    ))),
+    None => Err(MewError::runtime(format!(
+      "{} requires a string argument",
+      method
+    ))),
+  }
+}
+
+// A numeric argument, or `default` when it is missing or undefined
+fn number_arg(args: &[Value], index: usize, method: &str, default: f64) -> MewResult<f64> {
+  match args.get(index) {
+    None | Some(Value::Undefined) => Ok(default),
+    Some(Value::Number(n)) => Ok(if n.is_nan() { 0.0 } else { n.trunc() }),
+    Some(other) => Err(MewError::type_error(format!(
+      "{} expects a number argument, got {}",
+      method,
+      other.type_name()
+    ))),
+  }
+}
+
+// Clamp a position into `0..=len`
+fn clamp(position: f64, len: usize) -> usize {
+  position.max(0.0).min(len as f64) as usize
+}
+
+// Like `clamp`, but negative positions count back from the end
+fn relative(position: f64, len: usize) -> usize {
+  if position < 0.0 {
+    clamp(len as f64 + position, len)
+  } else {
+    clamp(position, len)
+  }
+}
+
+fn find(haystack: &[char], needle: &[char], from: usize) -> Option<usize> {
+  if needle.is_empty() {
+    return Some(from.min(haystack.len()));
+  }
+
+  (from..haystack.len())
+    .take_while(|&i| i + needle.len() <= haystack.len())
+    .find(|&i| haystack[i..i + needle.len()] == *needle)
+}
+
+fn char_at(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "charAt")?;
+  let index = number_arg(&args, 0, "charAt", 0.0)?;
+
+  if index < 0.0 {
+    return Ok(Value::String(String::new()));
+  }
+
+  Ok(char_at_index(&s, index as usize).unwrap_or_else(|| Value::String(String::new())))
+}
+
+fn substring(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "substring")?;
+  let chars: Vec<char> = s.chars().collect();
+
+  let start = clamp(number_arg(&args, 0, "substring", 0.0)?, chars.len());
+  let end = clamp(
+    number_arg(&args, 1, "substring", chars.len() as f64)?,
+    chars.len(),
+  );
+  let (start, end) = if start > end {
+    (end, start)
+  } else {
+    (start, end)
+  };
+
+  Ok(Value::String(chars[start..end].iter().collect()))
+}
+
+fn slice(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "slice")?;
+  let chars: Vec<char> = s.chars().collect();
+
+  let start = relative(number_arg(&args, 0, "slice", 0.0)?, chars.len());
+  let end = relative(
+    number_arg(&args, 1, "slice", chars.len() as f64)?,
+    chars.len(),
+  );
+
+  if start >= end {
+    return Ok(Value::String(String::new()));
+  }
+
+  Ok(Value::String(chars[start..end].iter().collect()))
+}
+
+fn index_of(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "indexOf")?;
+  let search: Vec<char> = string_arg(&args, 0, "indexOf")?.chars().collect();
+  let chars: Vec<char> = s.chars().collect();
+  let from = clamp(number_arg(&args, 1, "indexOf", 0.0)?, chars.len());
+
+  Ok(Value::Number(match find(&chars, &search, from) {
+    Some(index) => index as f64,
+    None => -1.0,
+  }))
+}
+
+fn includes(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "includes")?;
+  let search: Vec<char> = string_arg(&args, 0, "includes")?.chars().collect();
+  let chars: Vec<char> = s.chars().collect();
+  let from = clamp(number_arg(&args, 1, "includes", 0.0)?, chars.len());
+
+  Ok(Value::Bool(find(&chars, &search, from).is_some()))
+}
+
+fn starts_with(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "startsWith")?;
+  let search: Vec<char> = string_arg(&args, 0, "startsWith")?.chars().collect();
+  let chars: Vec<char> = s.chars().collect();
+  let position = clamp(number_arg(&args, 1, "startsWith", 0.0)?, chars.len());
+
+  Ok(Value::Bool(chars[position..].starts_with(&search)))
+}
+
+fn ends_with(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "endsWith")?;
+  let search: Vec<char> = string_arg(&args, 0, "endsWith")?.chars().collect();
+  let chars: Vec<char> = s.chars().collect();
+  let end = clamp(
+    number_arg(&args, 1, "endsWith", chars.len() as f64)?,
+    chars.len(),
+  );
+
+  Ok(Value::Bool(chars[..end].ends_with(&search)))
+}
+
+fn split(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "split")?;
+  let limit = number_arg(&args, 1, "split", f64::INFINITY)?.max(0.0);
+
+  let parts: Vec<Value> = match args.first() {
+    None | Some(Value::Undefined) => vec![Value::String(s)],
+    Some(Value::String(separator)) if separator.is_empty() => {
+      s.chars().map(|c| Value::String(c.to_string())).collect()
+    }
+    Some(Value::String(separator)) => s
+      .split(separator.as_str())
+      .map(|part| Value::String(part.to_string()))
+      .collect(),
+    Some(other) => {
+      return Err(MewError::type_error(format!(
+        "split expects a string separator, got {}",
+        other.type_name()
+      )))
+    }
+  };
+
+  Ok(Value::array(
+    parts.into_iter().take(limit as usize).collect(),
+  ))
+}
+
+// `", ".join(items)` joins the items of an array with the string as separator
+fn join(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (separator, args) = receiver(args, "join")?;
+
+  match args.first() {
+    Some(Value::Array(items)) => {
+      let items: Vec<String> = items
+        .borrow()
+        .iter()
+        .map(|item| match item {
+          Value::Null | Value::Undefined => String::new(),
+          other => other.to_string(),
+        })
+        .collect();
+      Ok(Value::String(items.join(&separator)))
+    }
+    Some(other) => Err(MewError::type_error(format!(
+      "join expects an array argument, got {}",
+      other.type_name()
+    ))),
+    None => Err(MewError::runtime("join requires an array argument")),
+  }
+}
+
+fn to_upper_case(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, _) = receiver(args, "toUpperCase")?;
+  Ok(Value::String(s.to_uppercase()))
+}
+
+fn to_lower_case(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, _) = receiver(args, "toLowerCase")?;
+  Ok(Value::String(s.to_lowercase()))
+}
+
+fn trim(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, _) = receiver(args, "trim")?;
+  Ok(Value::String(s.trim().to_string()))
+}
+
+fn trim_start(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, _) = receiver(args, "trimStart")?;
+  Ok(Value::String(s.trim_start().to_string()))
+}
+
+fn trim_end(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, _) = receiver(args, "trimEnd")?;
+  Ok(Value::String(s.trim_end().to_string()))
+}
+
+fn replace(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "replace")?;
+  let pattern = string_arg(&args, 0, "replace")?;
+  let replacement = string_arg(&args, 1, "replace")?;
+
+  Ok(Value::String(s.replacen(&pattern, &replacement, 1)))
+}
+
+fn replace_all(_interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "replaceAll")?;
+  let pattern = string_arg(&args, 0, "replaceAll")?;
+  let replacement = string_arg(&args, 1, "replaceAll")?;
+
+  Ok(Value::String(s.replace(&pattern, &replacement)))
+}
+
+fn repeat(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "repeat")?;
+  let count = number_arg(&args, 0, "repeat", 0.0)?;
+
+  if count < 0.0 || count.is_infinite() {
+    return Err(MewError::runtime(format!(
+      "Invalid repeat count: {}",
+      Value::Number(count)
+    )));
+  }
+
+  // Refuse before allocating, since the result can be huge
+  let count = count as usize;
+  interpreter.check_string_length(s.len().saturating_mul(count), || {
+    length(&s).saturating_mul(count)
+  })?;
+
+  Ok(Value::String(s.repeat(count)))
+}
+
+// Build the padding needed to grow `s` to `target` characters
+fn padding(interpreter: &Interpreter, s: &str, args: &[Value], method: &str) -> MewResult<String> {
+  let target = number_arg(args, 0, method, 0.0)?;
+  let fill = match args.get(1) {
+    None | Some(Value::Undefined) => " ".to_string(),
+    Some(_) => string_arg(args, 1, method)?,
+  };
+
+  let missing = (target - length(s) as f64).max(0.0) as usize;
+  if missing == 0 || fill.is_empty() {
+    return Ok(String::new());
+  }
+
+  let padded = length(s).saturating_add(missing);
+  interpreter.check_string_length(padded, || padded)?;
+
+  Ok(fill.chars().cycle().take(missing).collect())
+}
+
+fn pad_start(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "padStart")?;
+  let padding = padding(interpreter, &s, &args, "padStart")?;
+  Ok(Value::String(padding + &s))
+}
+
+fn pad_end(interpreter: &mut Interpreter, args: Vec<Value>) -> MewResult<Value> {
+  let (s, args) = receiver(args, "padEnd")?;
+  let padding = padding(interpreter, &s, &args, "padEnd")?;
+  Ok(Value::String(s + &padding))
+}
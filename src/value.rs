@@ -13,10 +13,13 @@
 // You should have received a copy of the GNU General Public License
 // along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::{MewError, MewResult};
+use crate::error::{MewError, MewResult, Span};
+use crate::interpreter::{Interpreter, STACK_RED_ZONE, STACK_SEGMENT};
+use crate::vm::Closure;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::path::Path;
 use std::rc::Rc;
 
 #[derive(Debug, Clone)]
@@ -26,10 +29,84 @@ pub enum Value {
   Number(f64),
   Bool(bool),
   String(String),
-  Array(Vec<Value>),
-  Object(HashMap<String, Value>),
+  Array(Rc<RefCell<Vec<Value>>>),
+  Object(Rc<RefCell<Object>>),
   Function(Rc<Function>),
   NativeFunction(Rc<NativeFunction>),
+  /// A function compiled for the bytecode VM
+  Closure(Rc<Closure>),
+}
+
+/// The storage behind [`Value::Object`]. It dereferences to the object's
+/// properties; what makes it a breed or an instance of one is kept apart
+/// from them, so scripts can't read or forge it.
+#[derive(Debug, Default)]
+pub struct Object {
+  properties: HashMap<String, Value>,
+  pub kind: ObjectKind,
+}
+
+#[derive(Debug, Default)]
+pub enum ObjectKind {
+  #[default]
+  Plain,
+  /// A breed (class). Its static fields are ordinary properties.
+  Breed(Breed),
+  /// An instance of the breed it holds
+  Instance(Value),
+}
+
+#[derive(Debug)]
+pub struct Breed {
+  pub name: String,
+  /// Instance methods, not yet bound to an instance
+  pub methods: HashMap<String, Value>,
+  /// Static methods, bound to the breed they are called on when accessed
+  pub statics: HashMap<String, Value>,
+  /// The breed this one inherits from
+  pub parent: Option<Value>,
+}
+
+impl Object {
+  pub fn new(properties: HashMap<String, Value>, kind: ObjectKind) -> Self {
+    Self { properties, kind }
+  }
+
+  pub fn breed(&self) -> Option<&Breed> {
+    match &self.kind {
+      ObjectKind::Breed(breed) => Some(breed),
+      _ => None,
+    }
+  }
+
+  pub fn breed_mut(&mut self) -> Option<&mut Breed> {
+    match &mut self.kind {
+      ObjectKind::Breed(breed) => Some(breed),
+      _ => None,
+    }
+  }
+
+  /// The breed an instance was created from
+  pub fn instance_of(&self) -> Option<&Value> {
+    match &self.kind {
+      ObjectKind::Instance(breed) => Some(breed),
+      _ => None,
+    }
+  }
+}
+
+impl std::ops::Deref for Object {
+  type Target = HashMap<String, Value>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.properties
+  }
+}
+
+impl std::ops::DerefMut for Object {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.properties
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -37,14 +114,37 @@ pub struct Function {
   pub name: Option<String>,
   pub parameters: Vec<String>,
   pub body: Vec<Rc<RefCell<Stmt>>>,
+  /// Variables declared with `catv` anywhere in the body, which exist from
+  /// the start of a call
+  pub hoisted: Vec<String>,
   pub closure: Rc<RefCell<Environment>>,
+  /// The file the function was defined in, for tracebacks
+  pub file: Option<Rc<Path>>,
 }
 
-pub type NativeFunctionType = fn(Vec<Value>) -> MewResult<Value>;
+/// Signature of built-in functions. They get the interpreter so that they can
+/// call back into Mew functions passed as arguments.
+pub type NativeFunctionType = fn(&mut Interpreter, Vec<Value>) -> MewResult<Value>;
+
+/// Implementation of a native function. Unlike [`NativeFunctionType`] this
+/// can be a closure holding host state.
+pub type NativeFn = Rc<dyn Fn(&mut Interpreter, Vec<Value>) -> MewResult<Value>>;
 
 pub struct NativeFunction {
   pub name: String,
-  pub function: NativeFunctionType,
+  pub function: NativeFn,
+}
+
+impl NativeFunction {
+  pub fn new<F>(name: &str, function: F) -> Self
+  where
+    F: Fn(&mut Interpreter, Vec<Value>) -> MewResult<Value> + 'static,
+  {
+    Self {
+      name: name.to_string(),
+      function: Rc::new(function),
+    }
+  }
 }
 
 impl fmt::Debug for NativeFunction {
@@ -54,14 +154,36 @@ impl fmt::Debug for NativeFunction {
 }
 
 impl Value {
+  /// Allocate a new shared array. Arrays are reference values: every copy of
+  /// the returned `Value` points at the same storage.
+  pub fn array(elements: Vec<Value>) -> Self {
+    Value::Array(Rc::new(RefCell::new(elements)))
+  }
+
+  /// Allocate a new shared object, see [`Value::array`].
+  pub fn object(properties: HashMap<String, Value>) -> Self {
+    Value::Object(Rc::new(RefCell::new(Object::new(
+      properties,
+      ObjectKind::Plain,
+    ))))
+  }
+
+  /// Wrap a Rust function or closure as a callable Mew value.
+  pub fn native_function<F>(name: &str, function: F) -> Self
+  where
+    F: Fn(&mut Interpreter, Vec<Value>) -> MewResult<Value> + 'static,
+  {
+    Value::NativeFunction(Rc::new(NativeFunction::new(name, function)))
+  }
+
   pub fn is_truthy(&self) -> bool {
     match self {
       Value::Null | Value::Undefined => false,
       Value::Bool(b) => *b,
       Value::Number(n) => *n != 0.0 && !n.is_nan(),
       Value::String(s) => !s.is_empty(),
-      Value::Array(a) => !a.is_empty(),
-      Value::Object(_) | Value::Function(_) | Value::NativeFunction(_) => true,
+      Value::Array(a) => !a.borrow().is_empty(),
+      Value::Object(_) | Value::Function(_) | Value::NativeFunction(_) | Value::Closure(_) => true,
     }
   }
 
@@ -74,13 +196,21 @@ impl Value {
       Value::String(_) => "string",
       Value::Array(_) => "array",
       Value::Object(_) => "object",
-      Value::Function(_) | Value::NativeFunction(_) => "function",
+      Value::Function(_) | Value::NativeFunction(_) | Value::Closure(_) => "function",
     }
   }
 }
 
 impl fmt::Display for Value {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.fmt_nested(f, &mut HashSet::new())
+  }
+}
+
+impl Value {
+  // `seen` holds the containers currently being printed so that
+  // self-referencing arrays and objects don't recurse forever.
+  fn fmt_nested(&self, f: &mut fmt::Formatter<'_>, seen: &mut HashSet<*const ()>) -> fmt::Result {
     match self {
       Value::Null => write!(f, "null"),
       Value::Undefined => write!(f, "undefined"),
@@ -93,8 +223,11 @@ impl fmt::Display for Value {
           } else {
             write!(f, "-Infinity")
           }
-        } else if n.fract() == 0.0 {
+        } else if n.fract() == 0.0 && n.abs() < 9007199254740992.0 {
           write!(f, "{}", *n as i64)
+        } else if n.abs() >= 1e21 {
+          // Like JavaScript, huge numbers switch to exponent notation
+          write!(f, "{}", format!("{:e}", n).replace('e', "e+"))
         } else {
           write!(f, "{}", n)
         }
@@ -102,23 +235,46 @@ impl fmt::Display for Value {
       Value::Bool(b) => write!(f, "{}", b),
       Value::String(s) => write!(f, "{}", s),
       Value::Array(arr) => {
+        let ptr = Rc::as_ptr(arr) as *const ();
+        if !seen.insert(ptr) {
+          return write!(f, "[Circular]");
+        }
+
         write!(f, "[")?;
-        for (i, val) in arr.iter().enumerate() {
+        for (i, val) in arr.borrow().iter().enumerate() {
           if i > 0 {
             write!(f, ", ")?;
           }
-          write!(f, "{}", val)?;
+          stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || val.fmt_nested(f, seen))?;
         }
+        seen.remove(&ptr);
         write!(f, "]")
       }
       Value::Object(obj) => {
+        let ptr = Rc::as_ptr(obj) as *const ();
+        if seen.contains(&ptr) {
+          return write!(f, "[Circular]");
+        }
+        let obj = obj.borrow();
+        if let Some(breed) = obj.breed() {
+          return write!(f, "breed {}", breed.name);
+        }
+        if let Some(Value::Object(breed)) = obj.instance_of() {
+          if let Some(breed) = breed.borrow().breed() {
+            write!(f, "{} ", breed.name)?;
+          }
+        }
+        seen.insert(ptr);
+
         write!(f, "{{")?;
         for (i, (key, val)) in obj.iter().enumerate() {
           if i > 0 {
             write!(f, ", ")?;
           }
-          write!(f, "{}: {}", key, val)?;
+          write!(f, "{}: ", key)?;
+          stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || val.fmt_nested(f, seen))?;
         }
+        seen.remove(&ptr);
         write!(f, "}}")
       }
       Value::Function(func) => {
@@ -131,41 +287,224 @@ impl fmt::Display for Value {
       Value::NativeFunction(func) => {
         write!(f, "function {}(...) [native]", func.name)
       }
+      Value::Closure(closure) => {
+        if let Some(name) = closure.name() {
+          write!(f, "function {}(...)", name)
+        } else {
+          write!(f, "function(...)")
+        }
+      }
     }
   }
 }
 
+// Freeing the last reference to a deeply nested array or object would
+// recurse once per level and could overflow the stack, so the contents of
+// containers about to be freed are moved out and dropped one at a time.
+impl Drop for Value {
+  fn drop(&mut self) {
+    if !matches!(self, Value::Array(_) | Value::Object(_)) {
+      return;
+    }
+    let mut pending = Vec::new();
+    take_contents(self, &mut pending);
+    while let Some(mut value) = pending.pop() {
+      take_contents(&mut value, &mut pending);
+    }
+  }
+}
+
+// Move out the contents of a container that nothing else refers to
+fn take_contents(value: &mut Value, pending: &mut Vec<Value>) {
+  match value {
+    Value::Array(items) if Rc::strong_count(items) == 1 => {
+      if let Ok(mut items) = items.try_borrow_mut() {
+        pending.append(&mut items);
+      }
+    }
+    Value::Object(obj) if Rc::strong_count(obj) == 1 => {
+      if let Ok(mut obj) = obj.try_borrow_mut() {
+        pending.extend(obj.drain().map(|(_, value)| value));
+      }
+    }
+    _ => {}
+  }
+}
+
+/// A statement together with the part of the source it was parsed from
+#[derive(Debug, Clone)]
+pub struct Stmt {
+  pub kind: StmtKind,
+  pub span: Span,
+}
+
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub enum StmtKind {
   Expression(Expr),
   Print(Expr),
-  VarDeclaration(String, Option<Expr>, bool), // name, initializer, is_const
+  VarDeclaration(String, Option<Expr>, VarKind), // name, initializer, declaring keyword
   Block(Vec<Rc<RefCell<Stmt>>>),
   If(Expr, Rc<RefCell<Stmt>>, Option<Rc<RefCell<Stmt>>>),
   While(Expr, Rc<RefCell<Stmt>>),
-  Function(String, Vec<String>, Vec<Rc<RefCell<Stmt>>>),
+  DoWhile(Rc<RefCell<Stmt>>, Expr),
+  For(
+    Option<Rc<RefCell<Stmt>>>,
+    Expr,
+    Option<Expr>,
+    Rc<RefCell<Stmt>>,
+  ), // initializer, condition, increment, body
+  ForIn(String, bool, bool, Expr, Rc<RefCell<Stmt>>), // name, is_const, is_of, iterable, body
+  Labeled(String, Rc<RefCell<Stmt>>),
+  Function(String, Vec<String>, Vec<Rc<RefCell<Stmt>>>, Vec<String>), // name, params, body, hoisted `catv` names
   Return(Option<Expr>),
-  Break,
-  Continue,
-  Switch(Expr, Vec<(Option<Expr>, Vec<Rc<RefCell<Stmt>>>)>),
+  Break(Option<String>),
+  Continue(Option<String>),
+  Switch(Expr, Vec<SwitchCase>),
+  Import(Vec<String>, String), // imported names, module path
+  Export(Rc<RefCell<Stmt>>),
+  Class(String, Option<Expr>, Vec<ClassMember>), // name, parent breed, members
+  Try(
+    Vec<Rc<RefCell<Stmt>>>,
+    Option<CatchClause>,
+    Option<Vec<Rc<RefCell<Stmt>>>>,
+  ), // body, catch clause, finally block
+  Throw(Expr),
+}
+
+impl Stmt {
+  pub fn new(kind: StmtKind, span: Span) -> Self {
+    Self { kind, span }
+  }
+
+  /// Whether this is a `cat` declaration, which runs before the other
+  /// statements of its block
+  pub fn declares_function(&self) -> bool {
+    match &self.kind {
+      StmtKind::Function(..) => true,
+      StmtKind::Export(declaration) => {
+        matches!(declaration.borrow().kind, StmtKind::Function(..))
+      }
+      _ => false,
+    }
+  }
+}
+
+/// The keyword a variable was declared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarKind {
+  /// `catv`
+  Var,
+  /// `catlt`
+  Let,
+  /// `catst`
+  Const,
+}
+
+/// Where the resolver found a local variable: how many scopes out from the
+/// one it is used in, and its position among that scope's variables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+  pub depth: usize,
+  pub index: usize,
+}
+
+pub type SwitchCase = (Option<Expr>, Vec<Rc<RefCell<Stmt>>>);
+pub type CatchClause = (Option<String>, Vec<Rc<RefCell<Stmt>>>); // error binding, body
+
+#[derive(Debug, Clone)]
+pub enum ClassMember {
+  Method(
+    String,
+    Vec<String>,
+    Vec<Rc<RefCell<Stmt>>>,
+    bool,
+    Vec<String>,
+  ), // name, params, body, is_static, hoisted `catv` names
+  StaticField(String, Expr),
+}
+
+/// An expression together with the part of the source it was parsed from
+#[derive(Debug, Clone)]
+pub struct Expr {
+  pub kind: ExprKind,
+  pub span: Span,
+}
+
+impl Expr {
+  pub fn new(kind: ExprKind, span: Span) -> Self {
+    Self { kind, span }
+  }
+}
+
+// Operators chain without nesting in the source, as in `1 + 1 + ... + 1`, so
+// an expression can be far deeper than the parser's nesting limit. Its
+// operands are dropped one at a time instead of recursively.
+impl Drop for Expr {
+  fn drop(&mut self) {
+    let mut pending = Vec::new();
+    take_operands(self, &mut pending);
+    while let Some(mut expr) = pending.pop() {
+      take_operands(&mut expr, &mut pending);
+    }
+  }
+}
+
+fn take_operands(expr: &mut Expr, pending: &mut Vec<Expr>) {
+  match std::mem::replace(&mut expr.kind, ExprKind::Literal(Value::Null)) {
+    ExprKind::Assignment(_, value, _)
+    | ExprKind::Unary(_, value)
+    | ExprKind::Get(value, _)
+    | ExprKind::Increment(value, _)
+    | ExprKind::Decrement(value, _)
+    | ExprKind::Optional(value)
+    | ExprKind::Grouping(value) => pending.push(*value),
+    ExprKind::CompoundAssignment(left, _, right)
+    | ExprKind::Binary(left, _, right)
+    | ExprKind::Set(left, _, right)
+    | ExprKind::Index(left, right) => pending.extend([*left, *right]),
+    ExprKind::SetIndex(first, second, third) | ExprKind::Ternary(first, second, third) => {
+      pending.extend([*first, *second, *third])
+    }
+    ExprKind::Call(callee, arguments) => {
+      pending.push(*callee);
+      pending.extend(arguments);
+    }
+    ExprKind::ArrayLiteral(items) => pending.extend(items),
+    ExprKind::ObjectLiteral(properties) => {
+      pending.extend(properties.into_iter().map(|(_, value)| value))
+    }
+    ExprKind::Literal(_) | ExprKind::Variable(..) | ExprKind::Function(..) | ExprKind::Super(_) => {
+    }
+  }
 }
 
 #[derive(Debug, Clone)]
-pub enum Expr {
+pub enum ExprKind {
   Literal(Value),
-  Variable(String),
-  Assignment(String, Box<Expr>),
+  Variable(String, Option<Slot>), // name, slot when it is a local variable
+  Assignment(String, Box<Expr>, Option<Slot>),
+  CompoundAssignment(Box<Expr>, BinaryOp, Box<Expr>), // target, operator, value
   Binary(Box<Expr>, BinaryOp, Box<Expr>),
   Unary(UnaryOp, Box<Expr>),
   Call(Box<Expr>, Vec<Expr>),
   Get(Box<Expr>, String),
   Set(Box<Expr>, String, Box<Expr>),
+  Index(Box<Expr>, Box<Expr>),
+  SetIndex(Box<Expr>, Box<Expr>, Box<Expr>), // object, index, value
   ArrayLiteral(Vec<Expr>),
   ObjectLiteral(Vec<(String, Expr)>),
-  Function(Option<String>, Vec<String>, Vec<Rc<RefCell<Stmt>>>),
+  Function(
+    Option<String>,
+    Vec<String>,
+    Vec<Rc<RefCell<Stmt>>>,
+    Vec<String>,
+  ), // name, params, body, hoisted `catv` names
   Increment(Box<Expr>, bool),
   Decrement(Box<Expr>, bool),
   Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+  Super(String),       // method name looked up on the parent breed
+  Optional(Box<Expr>), // object of a `?.` access, skips the rest of the chain when nullish
+  Grouping(Box<Expr>), // parenthesized `?.` chain, whose skipping stops at the `)`
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -183,6 +522,8 @@ pub enum BinaryOp {
   Gte,
   And,
   Or,
+  Nullish,
+  InstanceOf,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -193,39 +534,61 @@ pub enum UnaryOp {
 
 #[derive(Debug, Clone)]
 pub struct Environment {
-  values: HashMap<String, (Value, bool)>, // (value, is_const)
+  values: Vec<(Value, bool)>, // (value, is_const), in order of definition
+  names: HashMap<String, usize>,
   enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
+impl Default for Environment {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl Environment {
   pub fn new() -> Self {
     Self {
-      values: HashMap::new(),
+      values: Vec::new(),
+      names: HashMap::new(),
       enclosing: None,
     }
   }
 
   pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
     Self {
-      values: HashMap::new(),
+      values: Vec::new(),
+      names: HashMap::new(),
       enclosing: Some(enclosing),
     }
   }
 
   pub fn define(&mut self, name: &str, value: Value, is_const: bool) {
-    self.values.insert(name.to_string(), (value, is_const));
+    if let Some(&index) = self.names.get(name) {
+      self.values[index] = (value, is_const);
+    } else {
+      self.names.insert(name.to_string(), self.values.len());
+      self.values.push((value, is_const));
+    }
+  }
+
+  /// Define a `catv` variable as undefined, unless this scope has it already
+  pub fn hoist(&mut self, name: &str) {
+    if !self.names.contains_key(name) {
+      self.define(name, Value::Undefined, false);
+    }
+  }
+
+  /// Reserve a `catlt`, `catst` or breed variable that can't be used until
+  /// its declaration runs, unless this scope has it already
+  pub fn reserve(&mut self, name: &str) {
+    if !self.names.contains_key(name) {
+      self.define(name, uninitialized_marker(), false);
+    }
   }
 
   pub fn assign(&mut self, name: &str, value: Value) -> MewResult<()> {
-    if let Some((ref mut val, is_const)) = self.values.get_mut(name) {
-      if *is_const {
-        return Err(MewError::runtime(format!(
-          "Cannot reassign to constant '{}'",
-          name
-        )));
-      }
-      *val = value;
-      return Ok(());
+    if let Some(&index) = self.names.get(name) {
+      return self.assign_slot(index, name, value);
     }
 
     if let Some(enclosing) = &self.enclosing {
@@ -235,8 +598,17 @@ impl Environment {
     Err(MewError::name(format!("Undefined variable '{}'", name)))
   }
 
+  /// Names defined directly in this scope, not in enclosing ones
+  pub fn names(&self) -> Vec<String> {
+    self.names.keys().cloned().collect()
+  }
+
   pub fn get(&self, name: &str) -> MewResult<Value> {
-    if let Some((value, _)) = self.values.get(name) {
+    if let Some(&index) = self.names.get(name) {
+      let value = &self.values[index].0;
+      if is_uninitialized(value) {
+        return Err(uninitialized(name));
+      }
       return Ok(value.clone());
     }
 
@@ -246,4 +618,80 @@ impl Environment {
 
     Err(MewError::name(format!("Undefined variable '{}'", name)))
   }
+
+  /// Read a variable the resolver located, without looking up its name
+  pub fn get_at(&self, slot: Slot, name: &str) -> MewResult<Value> {
+    if slot.depth > 0 {
+      return match &self.enclosing {
+        Some(enclosing) => enclosing.borrow().get_at(
+          Slot {
+            depth: slot.depth - 1,
+            ..slot
+          },
+          name,
+        ),
+        None => Err(MewError::name(format!("Undefined variable '{}'", name))),
+      };
+    }
+
+    match self.values.get(slot.index) {
+      Some((value, _)) => Ok(value.clone()),
+      None => Err(uninitialized(name)),
+    }
+  }
+
+  pub fn assign_at(&mut self, slot: Slot, name: &str, value: Value) -> MewResult<()> {
+    if slot.depth > 0 {
+      return match &self.enclosing {
+        Some(enclosing) => enclosing.borrow_mut().assign_at(
+          Slot {
+            depth: slot.depth - 1,
+            ..slot
+          },
+          name,
+          value,
+        ),
+        None => Err(MewError::name(format!("Undefined variable '{}'", name))),
+      };
+    }
+
+    self.assign_slot(slot.index, name, value)
+  }
+
+  fn assign_slot(&mut self, index: usize, name: &str, value: Value) -> MewResult<()> {
+    match self.values.get_mut(index) {
+      Some((val, _)) if is_uninitialized(val) => Err(uninitialized(name)),
+      Some((_, true)) => Err(MewError::runtime(format!(
+        "Cannot reassign to constant '{}'",
+        name
+      ))),
+      Some((val, false)) => {
+        *val = value;
+        Ok(())
+      }
+      None => Err(uninitialized(name)),
+    }
+  }
+}
+
+thread_local! {
+  // Held by a `catlt`, `catst` or breed variable until its declaration runs
+  static UNINITIALIZED: Value = Value::native_function("uninitialized", |_, _| Ok(Value::Undefined));
+}
+
+pub(crate) fn uninitialized_marker() -> Value {
+  UNINITIALIZED.with(Value::clone)
+}
+
+pub(crate) fn is_uninitialized(value: &Value) -> bool {
+  match value {
+    Value::NativeFunction(function) => UNINITIALIZED.with(
+      |marker| matches!(marker, Value::NativeFunction(marker) if Rc::ptr_eq(function, marker)),
+    ),
+    _ => false,
+  }
+}
+
+pub(crate) fn uninitialized(name: &str) -> MewError {
+  MewError::name(format!("Cannot access '{}' before its declaration", name))
 }
@@ -0,0 +1,197 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::Span;
+use crate::value::{BinaryOp, Environment, Value};
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+/// One VM instruction. Operands index the constant, name or function
+/// tables of the chunk, stack slots relative to the current call frame, or
+/// other instructions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+  Constant(u32),
+  Undefined,
+  Null,
+  /// Fill the slot of a variable whose declaration hasn't run yet
+  Uninitialized,
+  True,
+  False,
+  Pop,
+  Dup,
+  /// Duplicate the top two values
+  Dup2,
+
+  GetLocal(u32),
+  SetLocal(u32),
+  GetUpvalue(u32),
+  SetUpvalue(u32),
+  /// Globals are looked up by name in the scope the code was compiled for
+  GetGlobal(u32),
+  SetGlobal(u32),
+  DefineGlobal(u32, bool),
+  /// Fail assigning to the named constant
+  ConstError(u32),
+
+  GetProperty(u32),
+  SetProperty(u32),
+  GetIndex,
+  SetIndex,
+  /// Look up a method on the parent breed, given `super` and `self`
+  SuperGet(u32),
+
+  Binary(BinaryOp),
+  Negate,
+  Not,
+
+  // `++` and `--` on a variable, property or index
+  StepLocal(u32, bool, bool),
+  StepUpvalue(u32, bool, bool),
+  StepGlobal(u32, bool, bool),
+  StepProperty(u32, bool, bool),
+  StepIndex(bool, bool),
+
+  Jump(u32),
+  JumpIfFalse(u32),
+  JumpIfTrue(u32),
+  // Short-circuit operators leave the deciding operand on the stack
+  JumpIfFalseKeep(u32),
+  JumpIfTrueKeep(u32),
+  JumpIfNotNullishKeep(u32),
+  /// Replace a null or undefined value with undefined and skip the rest of
+  /// a `?.` chain
+  JumpIfNullish(u32),
+
+  Call(u32),
+  Return,
+  Closure(u32),
+  /// Close the upvalues of and drop every value from a slot up
+  Unwind(u32),
+
+  MakeArray(u32),
+  MakeObject(u32),
+  /// Build a breed from its parent (if any) and instance methods
+  MakeBreed(u32, u32, bool),
+  /// Bind a static method to the breed below it and store it there
+  StaticMethod(u32),
+  /// Run a static field initializer with the breed below it as `self`
+  StaticField(u32),
+
+  /// Account for one statement, see [`crate::interpreter::Limits`]
+  Step,
+  Print,
+  Throw,
+  /// Fail with the message in the name table
+  Fail(u32),
+
+  /// Catch errors in the following code by jumping to the target
+  TryBegin(u32),
+  TryEnd,
+  /// Push the value of the error being handled
+  CaughtError,
+  /// Raise the error being handled again
+  Rethrow,
+  /// Forget the error being handled, when a jump leaves a `furever` block
+  /// that runs because of it
+  DiscardError,
+
+  Keys,
+  Values,
+  /// Push the next item of the array in a slot, whose index is kept in the
+  /// slot after it, or jump once it is exhausted
+  ForInNext(u32, u32),
+
+  Import(u32, u32),
+  Export(u32),
+}
+
+/// Instructions together with the values they refer to
+#[derive(Debug, Default)]
+pub struct Chunk {
+  pub code: Vec<Op>,
+  /// Where in the source each instruction came from
+  pub spans: Vec<Span>,
+  pub constants: Vec<Value>,
+  pub names: Vec<String>,
+  pub functions: Vec<Rc<Proto>>,
+}
+
+/// Where a closure gets one of its upvalues from when it is created
+#[derive(Debug, Clone, Copy)]
+pub struct UpvalueSource {
+  /// A local of the enclosing function, rather than one of its upvalues
+  pub is_local: bool,
+  pub index: u32,
+}
+
+/// A compiled function
+#[derive(Debug)]
+pub struct Proto {
+  pub name: Option<String>,
+  pub arity: usize,
+  /// Methods receive `self` and `super` in the two slots before their
+  /// parameters
+  pub is_method: bool,
+  pub upvalues: Vec<UpvalueSource>,
+  /// Names of the captured variables, for error messages
+  pub upvalue_names: Vec<String>,
+  pub chunk: Chunk,
+}
+
+/// A variable captured by a closure. It lives on the VM stack while its
+/// scope is active and moves into the upvalue when the scope ends.
+#[derive(Debug)]
+pub enum Upvalue {
+  Open(usize),
+  Closed(Value),
+}
+
+/// A compiled function together with the variables it captured
+pub struct Closure {
+  pub proto: Rc<Proto>,
+  pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+  /// The global (or module) scope the function was defined in
+  pub globals: Rc<RefCell<Environment>>,
+  /// `self` and `super` of a method bound to a receiver
+  pub receiver: Option<(Value, Value)>,
+  /// The file the function was defined in, for tracebacks
+  pub file: Option<Rc<Path>>,
+}
+
+impl Closure {
+  pub fn name(&self) -> Option<&str> {
+    self.proto.name.as_deref()
+  }
+
+  /// A copy of this method with `self` and `super` bound
+  pub fn bind(&self, receiver: Value, parent: Value) -> Self {
+    Self {
+      proto: self.proto.clone(),
+      upvalues: self.upvalues.clone(),
+      globals: self.globals.clone(),
+      receiver: Some((receiver, parent)),
+      file: self.file.clone(),
+    }
+  }
+}
+
+impl fmt::Debug for Closure {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Closure({})", self.name().unwrap_or("<anonymous>"))
+  }
+}
@@ -0,0 +1,1197 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::chunk::{Chunk, Op, Proto, UpvalueSource};
+use crate::error::{MewError, MewResult, Span};
+use crate::interpreter::{STACK_RED_ZONE, STACK_SEGMENT};
+use crate::value::{
+  BinaryOp, CatchClause, ClassMember, Expr, ExprKind, Stmt, StmtKind, UnaryOp, Value, VarKind,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Block = [Rc<RefCell<Stmt>>];
+
+// Hidden locals have names no identifier can have
+const COMPLETION: &str = " completion";
+const ITEMS: &str = " items";
+const INDEX: &str = " index";
+const SUBJECT: &str = " subject";
+const RETURN_VALUE: &str = " return";
+
+/// Compile a program or module. The result takes no arguments and returns
+/// the value of the last statement, like [`Interpreter::interpret`].
+///
+/// [`Interpreter::interpret`]: crate::interpreter::Interpreter::interpret
+pub fn compile(statements: &Block) -> MewResult<Rc<Proto>> {
+  let mut compiler = Compiler {
+    functions: vec![FunctionState::new(FunctionKind::Script, None)],
+    span: Span::unknown(),
+  };
+
+  compiler.emit(Op::Null);
+  compiler.add_local(COMPLETION, false);
+  compiler.statements(statements)?;
+  compiler.emit(Op::GetLocal(0));
+  compiler.emit(Op::Return);
+
+  let script = compiler.functions.pop().expect("script");
+  Ok(Rc::new(script.finish()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+  Script,
+  Function,
+  Method,
+}
+
+struct Local {
+  name: String,
+  depth: usize,
+  is_const: bool,
+}
+
+// A loop, `catwalk` or labeled statement that `clawt` can leave
+struct Breakable {
+  label: Option<String>,
+  is_loop: bool,
+  // Whether an unlabeled `clawt` leaves it
+  takes_break: bool,
+  // Locals, `purrhaps` blocks and pending errors there were when it started
+  locals: usize,
+  tries: usize,
+  pending_errors: usize,
+  breaks: Vec<usize>,
+  continues: Vec<usize>,
+}
+
+// A `purrhaps` body (or `catch` block followed by `furever`) being compiled.
+// Jumping out of it has to remove its error handler and run its `furever`
+// block first.
+struct TryRegion {
+  finally: Option<Vec<Rc<RefCell<Stmt>>>>,
+  breakables: usize,
+}
+
+enum Variable {
+  Local(u32, bool),
+  Upvalue(u32, bool),
+  Global,
+}
+
+struct FunctionState {
+  kind: FunctionKind,
+  name: Option<String>,
+  arity: usize,
+  chunk: Chunk,
+  name_indexes: HashMap<String, u32>,
+  upvalues: Vec<(UpvalueSource, bool, String)>, // source, is_const, name
+  locals: Vec<Local>,
+  scope_depth: usize,
+  breakables: Vec<Breakable>,
+  tries: Vec<TryRegion>,
+  // Set while compiling `furever` blocks, which don't change the value of
+  // the statement they belong to
+  hide_completion: usize,
+  // `furever` blocks being compiled that run because of an error, which is
+  // raised again at their end
+  pending_errors: usize,
+}
+
+impl FunctionState {
+  fn new(kind: FunctionKind, name: Option<String>) -> Self {
+    Self {
+      kind,
+      name,
+      arity: 0,
+      chunk: Chunk::default(),
+      name_indexes: HashMap::new(),
+      upvalues: Vec::new(),
+      locals: Vec::new(),
+      scope_depth: 0,
+      breakables: Vec::new(),
+      tries: Vec::new(),
+      hide_completion: 0,
+      pending_errors: 0,
+    }
+  }
+
+  fn finish(self) -> Proto {
+    Proto {
+      name: self.name,
+      arity: self.arity,
+      is_method: self.kind == FunctionKind::Method,
+      upvalues: self.upvalues.iter().map(|(source, ..)| *source).collect(),
+      upvalue_names: self.upvalues.into_iter().map(|(.., name)| name).collect(),
+      chunk: self.chunk,
+    }
+  }
+
+  fn resolve_local(&self, name: &str) -> Option<(u32, bool)> {
+    self
+      .locals
+      .iter()
+      .rposition(|local| local.name == name)
+      .map(|slot| (slot as u32, self.locals[slot].is_const))
+  }
+
+  fn add_upvalue(&mut self, source: UpvalueSource, is_const: bool, name: &str) -> u32 {
+    let existing = self
+      .upvalues
+      .iter()
+      .position(|(s, ..)| s.is_local == source.is_local && s.index == source.index);
+
+    match existing {
+      Some(index) => index as u32,
+      None => {
+        self.upvalues.push((source, is_const, name.to_string()));
+        (self.upvalues.len() - 1) as u32
+      }
+    }
+  }
+
+  // Declarations at the top level of a script define globals
+  fn at_global_scope(&self) -> bool {
+    self.kind == FunctionKind::Script && self.scope_depth == 0
+  }
+}
+
+struct Compiler {
+  functions: Vec<FunctionState>,
+  // The node being compiled, recorded for each instruction
+  span: Span,
+}
+
+impl Compiler {
+  fn current(&mut self) -> &mut FunctionState {
+    self.functions.last_mut().expect("function being compiled")
+  }
+
+  fn emit(&mut self, op: Op) -> usize {
+    let span = self.span;
+    let chunk = &mut self.current().chunk;
+    chunk.code.push(op);
+    chunk.spans.push(span);
+    chunk.code.len() - 1
+  }
+
+  fn here(&mut self) -> usize {
+    self.current().chunk.code.len()
+  }
+
+  fn constant(&mut self, value: Value) -> u32 {
+    let constants = &mut self.current().chunk.constants;
+    constants.push(value);
+    (constants.len() - 1) as u32
+  }
+
+  fn name(&mut self, name: &str) -> u32 {
+    let function = self.current();
+    if let Some(&index) = function.name_indexes.get(name) {
+      return index;
+    }
+
+    let index = function.chunk.names.len() as u32;
+    function.chunk.names.push(name.to_string());
+    function.name_indexes.insert(name.to_string(), index);
+    index
+  }
+
+  // Point the jump at `at` to `target`
+  fn patch(&mut self, at: usize, target: usize) {
+    let target = target as u32;
+    let op = &mut self.current().chunk.code[at];
+    *op = match *op {
+      Op::Jump(_) => Op::Jump(target),
+      Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+      Op::JumpIfTrue(_) => Op::JumpIfTrue(target),
+      Op::JumpIfFalseKeep(_) => Op::JumpIfFalseKeep(target),
+      Op::JumpIfTrueKeep(_) => Op::JumpIfTrueKeep(target),
+      Op::JumpIfNotNullishKeep(_) => Op::JumpIfNotNullishKeep(target),
+      Op::JumpIfNullish(_) => Op::JumpIfNullish(target),
+      Op::TryBegin(_) => Op::TryBegin(target),
+      Op::ForInNext(slot, _) => Op::ForInNext(slot, target),
+      other => unreachable!("{:?} is not a jump", other),
+    };
+  }
+
+  fn patch_here(&mut self, at: usize) {
+    let here = self.here();
+    self.patch(at, here);
+  }
+
+  fn tracks_completion(&mut self) -> bool {
+    let function = self.current();
+    function.kind == FunctionKind::Script && function.hide_completion == 0
+  }
+
+  fn set_completion_undefined(&mut self) {
+    self.emit(Op::Undefined);
+    self.emit(Op::SetLocal(0));
+    self.emit(Op::Pop);
+  }
+
+  // Scopes
+
+  fn begin_scope(&mut self) {
+    self.current().scope_depth += 1;
+  }
+
+  fn end_scope(&mut self) {
+    let function = self.current();
+    function.scope_depth -= 1;
+
+    let depth = function.scope_depth;
+    let first = function
+      .locals
+      .iter()
+      .position(|local| local.depth > depth)
+      .unwrap_or(function.locals.len());
+
+    if first < function.locals.len() {
+      function.locals.truncate(first);
+      self.emit(Op::Unwind(first as u32));
+    }
+  }
+
+  // The value on top of the stack becomes the local
+  fn add_local(&mut self, name: &str, is_const: bool) -> u32 {
+    let function = self.current();
+    function.locals.push(Local {
+      name: name.to_string(),
+      depth: function.scope_depth,
+      is_const,
+    });
+    (function.locals.len() - 1) as u32
+  }
+
+  // Bind the value on top of the stack to `name` in the current scope
+  // The slot of a variable declared in the innermost scope
+  fn local_in_scope(&mut self, name: &str) -> Option<usize> {
+    let function = self.current();
+    let depth = function.scope_depth;
+    function
+      .locals
+      .iter()
+      .rposition(|local| local.depth == depth && local.name == name)
+  }
+
+  fn declare(&mut self, name: &str, is_const: bool) {
+    if self.current().at_global_scope() {
+      let index = self.name(name);
+      self.emit(Op::DefineGlobal(index, is_const));
+      return;
+    }
+
+    // Declaring a name again in the same scope replaces the variable
+    match self.local_in_scope(name) {
+      Some(slot) => {
+        self.current().locals[slot].is_const = is_const;
+        self.emit(Op::SetLocal(slot as u32));
+        self.emit(Op::Pop);
+      }
+      None => {
+        self.add_local(name, is_const);
+      }
+    }
+  }
+
+  fn resolve(&mut self, name: &str) -> Variable {
+    let level = self.functions.len() - 1;
+    if let Some((slot, is_const)) = self.functions[level].resolve_local(name) {
+      return Variable::Local(slot, is_const);
+    }
+
+    match self.resolve_upvalue(level, name) {
+      Some((index, is_const)) => Variable::Upvalue(index, is_const),
+      None => Variable::Global,
+    }
+  }
+
+  fn resolve_upvalue(&mut self, level: usize, name: &str) -> Option<(u32, bool)> {
+    if level == 0 {
+      return None;
+    }
+
+    let (source, is_const) = match self.functions[level - 1].resolve_local(name) {
+      Some((slot, is_const)) => (
+        UpvalueSource {
+          is_local: true,
+          index: slot,
+        },
+        is_const,
+      ),
+      None => {
+        let (index, is_const) = self.resolve_upvalue(level - 1, name)?;
+        (
+          UpvalueSource {
+            is_local: false,
+            index,
+          },
+          is_const,
+        )
+      }
+    };
+
+    Some((
+      self.functions[level].add_upvalue(source, is_const, name),
+      is_const,
+    ))
+  }
+
+  fn get_variable(&mut self, name: &str) {
+    let op = match self.resolve(name) {
+      Variable::Local(slot, _) => Op::GetLocal(slot),
+      Variable::Upvalue(index, _) => Op::GetUpvalue(index),
+      Variable::Global => Op::GetGlobal(self.name(name)),
+    };
+    self.emit(op);
+  }
+
+  // Push a variable that only exists inside methods, or undefined
+  fn get_method_variable(&mut self, name: &str) {
+    if let Variable::Global = self.resolve(name) {
+      self.emit(Op::Undefined);
+    } else {
+      self.get_variable(name);
+    }
+  }
+
+  fn set_variable(&mut self, name: &str) {
+    let op = match self.resolve(name) {
+      Variable::Local(_, true) | Variable::Upvalue(_, true) => Op::ConstError(self.name(name)),
+      Variable::Local(slot, false) => Op::SetLocal(slot),
+      Variable::Upvalue(index, false) => Op::SetUpvalue(index),
+      Variable::Global => Op::SetGlobal(self.name(name)),
+    };
+    self.emit(op);
+  }
+
+  // Statements
+
+  fn statements(&mut self, statements: &Block) -> MewResult<()> {
+    self.hoist(statements)?;
+    for statement in statements {
+      let statement = statement.borrow();
+      if statement.declares_function() {
+        if self.tracks_completion() {
+          self.set_completion_undefined();
+        }
+        continue;
+      }
+
+      self.statement(&statement)?;
+    }
+    Ok(())
+  }
+
+  fn block(&mut self, statements: &Block) -> MewResult<()> {
+    self.begin_scope();
+    self.statements(statements)?;
+    self.end_scope();
+    Ok(())
+  }
+
+  // Define the functions of a block before it runs. Slots for everything
+  // the block declares are reserved first, so the functions can capture
+  // them; those of `catlt`, `catst` and breeds hold a marker until their
+  // declaration runs.
+  fn hoist(&mut self, statements: &Block) -> MewResult<()> {
+    if !self.current().at_global_scope() {
+      for statement in statements {
+        let initial = match &statement.borrow().kind {
+          StmtKind::Function(name, ..) => Some((name.clone(), Op::Undefined)),
+          StmtKind::VarDeclaration(name, _, VarKind::Let | VarKind::Const)
+          | StmtKind::Class(name, ..) => Some((name.clone(), Op::Uninitialized)),
+          _ => None,
+        };
+
+        if let Some((name, op)) = initial {
+          if self.local_in_scope(&name).is_none() {
+            self.emit(op);
+            self.add_local(&name, false);
+          }
+        }
+      }
+    }
+
+    for statement in statements {
+      let statement = statement.borrow();
+      if statement.declares_function() {
+        self.statement(&statement)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn statement(&mut self, stmt: &Stmt) -> MewResult<()> {
+    let enclosing = std::mem::replace(&mut self.span, stmt.span);
+    let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+      self.compile_statement(stmt)
+    });
+    self.span = enclosing;
+    result.map_err(|error| error.at(stmt.span))
+  }
+
+  fn compile_statement(&mut self, stmt: &Stmt) -> MewResult<()> {
+    self.emit(Op::Step);
+
+    let tracks_completion = self.tracks_completion();
+    if tracks_completion && !matches!(stmt.kind, StmtKind::Expression(_)) {
+      self.set_completion_undefined();
+    }
+
+    match &stmt.kind {
+      StmtKind::Expression(expr) => {
+        self.expression(expr)?;
+        if tracks_completion {
+          self.emit(Op::SetLocal(0));
+        }
+        self.emit(Op::Pop);
+      }
+      StmtKind::Print(expr) => {
+        self.expression(expr)?;
+        self.emit(Op::Print);
+      }
+      // `catv` variables were hoisted, so the declaration only sets them
+      StmtKind::VarDeclaration(name, initializer, VarKind::Var) => {
+        if let Some(expr) = initializer {
+          self.expression(expr)?;
+          self.set_variable(name);
+          self.emit(Op::Pop);
+        }
+      }
+      StmtKind::VarDeclaration(name, initializer, kind) => {
+        match initializer {
+          Some(expr) => self.expression(expr)?,
+          None => {
+            self.emit(Op::Undefined);
+          }
+        }
+        self.declare(name, *kind == VarKind::Const);
+      }
+      StmtKind::Block(statements) => self.block(statements)?,
+      StmtKind::If(condition, then_branch, else_branch) => {
+        self.expression(condition)?;
+        let to_else = self.emit(Op::JumpIfFalse(0));
+        self.statement(&then_branch.borrow())?;
+
+        match else_branch {
+          Some(else_branch) => {
+            let to_end = self.emit(Op::Jump(0));
+            self.patch_here(to_else);
+            self.statement(&else_branch.borrow())?;
+            self.patch_here(to_end);
+          }
+          None => self.patch_here(to_else),
+        }
+      }
+      StmtKind::While(..) | StmtKind::DoWhile(..) | StmtKind::For(..) | StmtKind::ForIn(..) => {
+        self.loop_statement(stmt, None)?
+      }
+      StmtKind::Labeled(label, body) => {
+        let body = body.borrow();
+        match &body.kind {
+          StmtKind::While(..) | StmtKind::DoWhile(..) | StmtKind::For(..) | StmtKind::ForIn(..) => {
+            self.loop_statement(&body, Some(label))?
+          }
+          _ => {
+            self.begin_breakable(Some(label), false, false);
+            self.statement(&body)?;
+            self.end_breakable(None);
+          }
+        }
+      }
+      StmtKind::Function(name, params, body, hoisted) => {
+        self.function(Some(name), params, body, hoisted, FunctionKind::Function)?;
+        self.declare(name, false);
+      }
+      StmtKind::Class(name, parent, members) => self.class(name, parent, members)?,
+      StmtKind::Return(value) => self.return_statement(value)?,
+      StmtKind::Break(label) => self.jump_out(label.as_deref(), false)?,
+      StmtKind::Continue(label) => self.jump_out(label.as_deref(), true)?,
+      StmtKind::Switch(subject, cases) => {
+        self.begin_scope();
+        self.expression(subject)?;
+        let subject = self.add_local(SUBJECT, true);
+
+        let mut to_cases = Vec::new();
+        let mut default_case = None;
+        for (i, (case_value, _)) in cases.iter().enumerate() {
+          match case_value {
+            Some(case_value) => {
+              self.emit(Op::GetLocal(subject));
+              self.expression(case_value)?;
+              self.emit(Op::Binary(BinaryOp::Eq));
+              to_cases.push((i, self.emit(Op::JumpIfTrue(0))));
+            }
+            None => default_case = Some(i),
+          }
+        }
+        let no_match = self.emit(Op::Jump(0));
+
+        // Only the selected case runs, and an unlabeled `clawt` leaves it
+        self.begin_breakable(None, false, true);
+        let mut case_starts = Vec::new();
+        let mut to_end = Vec::new();
+        for (_, statements) in cases {
+          case_starts.push(self.here());
+          self.block(statements)?;
+          to_end.push(self.emit(Op::Jump(0)));
+        }
+
+        for (i, jump) in to_cases {
+          self.patch(jump, case_starts[i]);
+        }
+        match default_case {
+          Some(i) => self.patch(no_match, case_starts[i]),
+          None => to_end.push(no_match),
+        }
+        for jump in to_end {
+          self.patch_here(jump);
+        }
+
+        self.end_breakable(None);
+        self.end_scope();
+      }
+      StmtKind::Import(names, specifier) => {
+        let module = self.name(specifier);
+        for name in names {
+          let index = self.name(name);
+          self.emit(Op::Import(module, index));
+          self.declare(name, true);
+        }
+      }
+      StmtKind::Export(declaration) => {
+        let declaration = declaration.borrow();
+        self.statement(&declaration)?;
+
+        let name = match &declaration.kind {
+          StmtKind::VarDeclaration(name, _, _)
+          | StmtKind::Function(name, ..)
+          | StmtKind::Class(name, _, _) => name,
+          _ => unreachable!(),
+        };
+        let index = self.name(name);
+        self.emit(Op::Export(index));
+      }
+      StmtKind::Try(body, catch_clause, finally_block) => {
+        self.try_statement(body, catch_clause, finally_block)?
+      }
+      StmtKind::Throw(expr) => {
+        self.expression(expr)?;
+        self.emit(Op::Throw);
+      }
+    }
+
+    Ok(())
+  }
+
+  fn begin_breakable(&mut self, label: Option<&str>, is_loop: bool, takes_break: bool) {
+    let function = self.current();
+    let breakable = Breakable {
+      label: label.map(str::to_string),
+      is_loop,
+      takes_break,
+      locals: function.locals.len(),
+      tries: function.tries.len(),
+      pending_errors: function.pending_errors,
+      breaks: Vec::new(),
+      continues: Vec::new(),
+    };
+    function.breakables.push(breakable);
+  }
+
+  // Patch the `meownext` jumps to `continue_target` and the `clawt` jumps to
+  // the current position
+  fn end_breakable(&mut self, continue_target: Option<usize>) {
+    let breakable = self.current().breakables.pop().expect("breakable");
+
+    if let Some(target) = continue_target {
+      for jump in breakable.continues {
+        self.patch(jump, target);
+      }
+    }
+    for jump in breakable.breaks {
+      self.patch_here(jump);
+    }
+  }
+
+  fn loop_statement(&mut self, stmt: &Stmt, label: Option<&str>) -> MewResult<()> {
+    match &stmt.kind {
+      StmtKind::While(condition, body) => {
+        let start = self.here();
+        self.expression(condition)?;
+        let exit = self.emit(Op::JumpIfFalse(0));
+
+        self.begin_breakable(label, true, true);
+        self.statement(&body.borrow())?;
+        self.emit(Op::Jump(start as u32));
+
+        self.patch_here(exit);
+        self.end_breakable(Some(start));
+      }
+      StmtKind::DoWhile(body, condition) => {
+        let start = self.here();
+        self.begin_breakable(label, true, true);
+        self.statement(&body.borrow())?;
+
+        let condition_start = self.here();
+        self.expression(condition)?;
+        self.emit(Op::JumpIfTrue(start as u32));
+        self.end_breakable(Some(condition_start));
+      }
+      StmtKind::For(initializer, condition, increment, body) => {
+        self.begin_scope();
+        if let Some(initializer) = initializer {
+          self.statement(&initializer.borrow())?;
+        }
+
+        let start = self.here();
+        self.expression(condition)?;
+        let exit = self.emit(Op::JumpIfFalse(0));
+
+        self.begin_breakable(label, true, true);
+        self.statement(&body.borrow())?;
+
+        let increment_start = self.here();
+        if let Some(increment) = increment {
+          self.expression(increment)?;
+          self.emit(Op::Pop);
+        }
+        self.emit(Op::Jump(start as u32));
+
+        self.patch_here(exit);
+        self.end_breakable(Some(increment_start));
+        self.end_scope();
+      }
+      StmtKind::ForIn(name, is_const, is_of, iterable, body) => {
+        self.expression(iterable)?;
+        self.emit(if *is_of { Op::Values } else { Op::Keys });
+
+        self.begin_scope();
+        let items = self.add_local(ITEMS, true);
+        let zero = self.constant(Value::Number(0.0));
+        self.emit(Op::Constant(zero));
+        self.add_local(INDEX, false);
+
+        let start = self.here();
+        let exit = self.emit(Op::ForInNext(items, 0));
+
+        self.begin_breakable(label, true, true);
+        self.begin_scope();
+        self.add_local(name, *is_const);
+        self.statement(&body.borrow())?;
+        self.end_scope();
+        self.emit(Op::Jump(start as u32));
+
+        self.patch_here(exit);
+        self.end_breakable(Some(start));
+        self.end_scope();
+      }
+      _ => unreachable!(),
+    }
+
+    Ok(())
+  }
+
+  // Leave `purrhaps` blocks opened after the first `to` of them, running
+  // their `furever` blocks
+  fn exit_tries(&mut self, to: usize) -> MewResult<()> {
+    for i in (to..self.current().tries.len()).rev() {
+      self.emit(Op::TryEnd);
+
+      let function = self.current();
+      let Some(finally) = function.tries[i].finally.clone() else {
+        continue;
+      };
+
+      // Inside the `furever` block, jumps only see what surrounds the
+      // `purrhaps` statement
+      let tries = function.tries.split_off(i);
+      let breakables = function.breakables.split_off(tries[0].breakables);
+
+      function.hide_completion += 1;
+      let result = self.block(&finally);
+
+      let function = self.current();
+      function.hide_completion -= 1;
+      function.tries.extend(tries);
+      function.breakables.extend(breakables);
+      result?;
+    }
+
+    Ok(())
+  }
+
+  fn jump_out(&mut self, label: Option<&str>, is_continue: bool) -> MewResult<()> {
+    let function = self.current();
+    let target = function.breakables.iter().rposition(|b| match label {
+      Some(label) => b.label.as_deref() == Some(label) && (b.is_loop || !is_continue),
+      None if is_continue => b.is_loop,
+      None => b.takes_break,
+    });
+
+    let Some(target) = target else {
+      let message = match (label, is_continue) {
+        (Some(label), _) => format!("Undefined label '{}'", label),
+        (None, false) => "'clawt' used outside of a loop or catwalk".to_string(),
+        (None, true) => "'meownext' used outside of a loop".to_string(),
+      };
+      let index = self.name(&message);
+      self.emit(Op::Fail(index));
+      return Ok(());
+    };
+
+    let (locals, tries, pending_errors) = {
+      let breakable = &function.breakables[target];
+      (breakable.locals, breakable.tries, breakable.pending_errors)
+    };
+    for _ in pending_errors..function.pending_errors {
+      self.emit(Op::DiscardError);
+    }
+    self.exit_tries(tries)?;
+    if self.current().locals.len() > locals {
+      self.emit(Op::Unwind(locals as u32));
+    }
+
+    let jump = self.emit(Op::Jump(0));
+    let breakable = &mut self.current().breakables[target];
+    if is_continue {
+      breakable.continues.push(jump);
+    } else {
+      breakable.breaks.push(jump);
+    }
+
+    Ok(())
+  }
+
+  fn return_statement(&mut self, value: &Option<Expr>) -> MewResult<()> {
+    if self.current().kind == FunctionKind::Script {
+      let index = self.name("'return' used outside of a function");
+      self.emit(Op::Fail(index));
+      return Ok(());
+    }
+
+    match value {
+      Some(expr) => self.expression(expr)?,
+      None => {
+        self.emit(Op::Undefined);
+      }
+    }
+
+    if self.current().tries.is_empty() {
+      self.emit(Op::Return);
+      return Ok(());
+    }
+
+    // Keep the value in a local while `furever` blocks run
+    self.begin_scope();
+    let slot = self.add_local(RETURN_VALUE, true);
+    self.exit_tries(0)?;
+    self.emit(Op::GetLocal(slot));
+    self.emit(Op::Return);
+
+    let function = self.current();
+    function.locals.pop();
+    function.scope_depth -= 1;
+    Ok(())
+  }
+
+  fn try_statement(
+    &mut self,
+    body: &Block,
+    catch_clause: &Option<CatchClause>,
+    finally_block: &Option<Vec<Rc<RefCell<Stmt>>>>,
+  ) -> MewResult<()> {
+    let to_handler = self.emit(Op::TryBegin(0));
+    self.begin_try(finally_block);
+    self.block(body)?;
+    self.current().tries.pop();
+    self.emit(Op::TryEnd);
+    let mut to_after = vec![self.emit(Op::Jump(0))];
+
+    self.patch_here(to_handler);
+    if let Some((binding, handler)) = catch_clause {
+      // Errors in the `catch` block still run the `furever` block
+      let to_finally = match finally_block {
+        Some(_) => {
+          let jump = self.emit(Op::TryBegin(0));
+          self.begin_try(finally_block);
+          Some(jump)
+        }
+        None => None,
+      };
+
+      self.begin_scope();
+      self.emit(Op::CaughtError);
+      match binding {
+        Some(name) => {
+          self.add_local(name, false);
+        }
+        None => {
+          self.emit(Op::Pop);
+        }
+      }
+      self.statements(handler)?;
+      self.end_scope();
+
+      if let Some(jump) = to_finally {
+        self.current().tries.pop();
+        self.emit(Op::TryEnd);
+        to_after.push(self.emit(Op::Jump(0)));
+        self.patch_here(jump);
+      } else {
+        to_after.push(self.emit(Op::Jump(0)));
+      }
+    }
+
+    if let Some(finally) = finally_block {
+      // The error that got here is raised again afterwards
+      let function = self.current();
+      function.hide_completion += 1;
+      function.pending_errors += 1;
+      self.block(finally)?;
+      let function = self.current();
+      function.hide_completion -= 1;
+      function.pending_errors -= 1;
+      self.emit(Op::Rethrow);
+    }
+
+    for jump in to_after {
+      self.patch_here(jump);
+    }
+
+    if let Some(finally) = finally_block {
+      self.current().hide_completion += 1;
+      self.block(finally)?;
+      self.current().hide_completion -= 1;
+    }
+
+    Ok(())
+  }
+
+  fn begin_try(&mut self, finally_block: &Option<Vec<Rc<RefCell<Stmt>>>>) {
+    let function = self.current();
+    let region = TryRegion {
+      finally: finally_block.clone(),
+      breakables: function.breakables.len(),
+    };
+    function.tries.push(region);
+  }
+
+  // Compile a function and push a closure over it
+  fn function(
+    &mut self,
+    name: Option<&String>,
+    params: &[String],
+    body: &Block,
+    hoisted: &[String],
+    kind: FunctionKind,
+  ) -> MewResult<()> {
+    self.functions.push(FunctionState::new(kind, name.cloned()));
+    if kind == FunctionKind::Method {
+      self.add_local("self", true);
+      self.add_local("super", true);
+    }
+    for param in params {
+      self.add_local(param, false);
+    }
+    self.current().arity = params.len();
+
+    for name in hoisted {
+      if self.local_in_scope(name).is_none() {
+        self.emit(Op::Undefined);
+        self.add_local(name, false);
+      }
+    }
+
+    self.statements(body)?;
+    self.emit(Op::Undefined);
+    self.emit(Op::Return);
+
+    self.push_closure()
+  }
+
+  fn push_closure(&mut self) -> MewResult<()> {
+    let function = self.functions.pop().expect("function being compiled");
+    let functions = &mut self.current().chunk.functions;
+    functions.push(Rc::new(function.finish()));
+    let index = (functions.len() - 1) as u32;
+    self.emit(Op::Closure(index));
+    Ok(())
+  }
+
+  fn class(&mut self, name: &str, parent: &Option<Expr>, members: &[ClassMember]) -> MewResult<()> {
+    if let Some(parent) = parent {
+      self.expression(parent)?;
+    }
+
+    let mut methods = 0;
+    for member in members {
+      if let ClassMember::Method(method, params, body, false, hoisted) = member {
+        self.function(Some(method), params, body, hoisted, FunctionKind::Method)?;
+        methods += 1;
+      }
+    }
+
+    let index = self.name(name);
+    self.emit(Op::MakeBreed(index, methods, parent.is_some()));
+
+    // Static methods come first, so static fields can call them
+    for member in members {
+      if let ClassMember::Method(method, params, body, true, hoisted) = member {
+        self.function(Some(method), params, body, hoisted, FunctionKind::Method)?;
+        let index = self.name(method);
+        self.emit(Op::StaticMethod(index));
+      }
+    }
+
+    // Static fields see the breed itself as `self`
+    for member in members {
+      if let ClassMember::StaticField(field, expr) = member {
+        self.functions.push(FunctionState::new(
+          FunctionKind::Method,
+          Some(field.clone()),
+        ));
+        self.add_local("self", true);
+        self.add_local("super", true);
+        self.expression(expr)?;
+        self.emit(Op::Return);
+        self.push_closure()?;
+
+        let index = self.name(field);
+        self.emit(Op::StaticField(index));
+      }
+    }
+
+    self.declare(name, false);
+    Ok(())
+  }
+
+  // Expressions
+
+  fn expression(&mut self, expr: &Expr) -> MewResult<()> {
+    let enclosing = std::mem::replace(&mut self.span, expr.span);
+    let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+      self.compile_expression(expr)
+    });
+    self.span = enclosing;
+    result.map_err(|error| error.at(expr.span))
+  }
+
+  fn compile_expression(&mut self, expr: &Expr) -> MewResult<()> {
+    match &expr.kind {
+      ExprKind::Literal(value) => {
+        let op = match value {
+          Value::Null => Op::Null,
+          Value::Undefined => Op::Undefined,
+          Value::Bool(true) => Op::True,
+          Value::Bool(false) => Op::False,
+          value => Op::Constant(self.constant(value.clone())),
+        };
+        self.emit(op);
+      }
+      ExprKind::Variable(name, _) => self.get_variable(name),
+      ExprKind::Assignment(name, value, _) => {
+        self.expression(value)?;
+        self.set_variable(name);
+      }
+      ExprKind::CompoundAssignment(target, op, value) => match &target.kind {
+        ExprKind::Variable(name, _) => {
+          self.get_variable(name);
+          self.expression(value)?;
+          self.emit(Op::Binary(*op));
+          self.set_variable(name);
+        }
+        ExprKind::Get(object, name) => {
+          self.expression(object)?;
+          self.emit(Op::Dup);
+          let index = self.name(name);
+          self.emit(Op::GetProperty(index));
+          self.expression(value)?;
+          self.emit(Op::Binary(*op));
+          self.emit(Op::SetProperty(index));
+        }
+        ExprKind::Index(object, index) => {
+          self.expression(object)?;
+          self.expression(index)?;
+          self.emit(Op::Dup2);
+          self.emit(Op::GetIndex);
+          self.expression(value)?;
+          self.emit(Op::Binary(*op));
+          self.emit(Op::SetIndex);
+        }
+        _ => return Err(MewError::syntax("Invalid assignment target")),
+      },
+      ExprKind::Binary(left, op, right) => {
+        self.expression(left)?;
+
+        // Logical operators short-circuit and yield the deciding operand
+        let jump = match op {
+          BinaryOp::And => Some(Op::JumpIfFalseKeep(0)),
+          BinaryOp::Or => Some(Op::JumpIfTrueKeep(0)),
+          BinaryOp::Nullish => Some(Op::JumpIfNotNullishKeep(0)),
+          _ => None,
+        };
+
+        match jump {
+          Some(jump) => {
+            let jump = self.emit(jump);
+            self.expression(right)?;
+            self.patch_here(jump);
+          }
+          None => {
+            self.expression(right)?;
+            self.emit(Op::Binary(*op));
+          }
+        }
+      }
+      ExprKind::Ternary(condition, then_expr, else_expr) => {
+        self.expression(condition)?;
+        let to_else = self.emit(Op::JumpIfFalse(0));
+        self.expression(then_expr)?;
+        let to_end = self.emit(Op::Jump(0));
+        self.patch_here(to_else);
+        self.expression(else_expr)?;
+        self.patch_here(to_end);
+      }
+      ExprKind::Unary(op, operand) => {
+        self.expression(operand)?;
+        self.emit(match op {
+          UnaryOp::Minus => Op::Negate,
+          UnaryOp::Not => Op::Not,
+        });
+      }
+      ExprKind::Call(..) | ExprKind::Get(..) | ExprKind::Index(..) | ExprKind::Optional(..) => {
+        let mut exits = Vec::new();
+        self.chain(expr, &mut exits)?;
+        for exit in exits {
+          self.patch_here(exit);
+        }
+      }
+      ExprKind::Grouping(inner) => self.expression(inner)?,
+      ExprKind::Set(object, name, value) => {
+        self.expression(object)?;
+        self.expression(value)?;
+        let index = self.name(name);
+        self.emit(Op::SetProperty(index));
+      }
+      ExprKind::SetIndex(object, index, value) => {
+        self.expression(object)?;
+        self.expression(index)?;
+        self.expression(value)?;
+        self.emit(Op::SetIndex);
+      }
+      ExprKind::ArrayLiteral(elements) => {
+        for element in elements {
+          self.expression(element)?;
+        }
+        self.emit(Op::MakeArray(elements.len() as u32));
+      }
+      ExprKind::ObjectLiteral(properties) => {
+        for (key, value) in properties {
+          let key = self.constant(Value::String(key.clone()));
+          self.emit(Op::Constant(key));
+          self.expression(value)?;
+        }
+        self.emit(Op::MakeObject(properties.len() as u32));
+      }
+      ExprKind::Function(name, params, body, hoisted) => {
+        self.function(name.as_ref(), params, body, hoisted, FunctionKind::Function)?
+      }
+      ExprKind::Increment(target, is_prefix) => self.step(target, true, *is_prefix)?,
+      ExprKind::Decrement(target, is_prefix) => self.step(target, false, *is_prefix)?,
+      ExprKind::Super(name) => {
+        self.get_method_variable("super");
+        self.get_method_variable("self");
+        let index = self.name(name);
+        self.emit(Op::SuperGet(index));
+      }
+    }
+
+    Ok(())
+  }
+
+  // Compile a property, index or call chain. A `?.` that meets null or
+  // undefined jumps to the end of the whole chain, collected in `exits`.
+  fn chain(&mut self, expr: &Expr, exits: &mut Vec<usize>) -> MewResult<()> {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+      self.compile_chain(expr, exits)
+    })
+  }
+
+  fn compile_chain(&mut self, expr: &Expr, exits: &mut Vec<usize>) -> MewResult<()> {
+    match &expr.kind {
+      ExprKind::Optional(object) => {
+        self.chain(object, exits)?;
+        exits.push(self.emit(Op::JumpIfNullish(0)));
+      }
+      ExprKind::Get(object, name) => {
+        self.chain(object, exits)?;
+        let index = self.name(name);
+        self.emit(Op::GetProperty(index));
+      }
+      ExprKind::Index(object, index) => {
+        self.chain(object, exits)?;
+        self.expression(index)?;
+        self.emit(Op::GetIndex);
+      }
+      ExprKind::Call(callee, arguments) => {
+        self.chain(callee, exits)?;
+        for argument in arguments {
+          self.expression(argument)?;
+        }
+        self.emit(Op::Call(arguments.len() as u32));
+      }
+      _ => self.expression(expr)?,
+    }
+
+    Ok(())
+  }
+
+  // `++` or `--` on an assignment target
+  fn step(&mut self, target: &Expr, increment: bool, is_prefix: bool) -> MewResult<()> {
+    match &target.kind {
+      ExprKind::Variable(name, _) => {
+        let op = match self.resolve(name) {
+          Variable::Local(_, true) | Variable::Upvalue(_, true) => {
+            self.get_variable(name);
+            Op::ConstError(self.name(name))
+          }
+          Variable::Local(slot, false) => Op::StepLocal(slot, increment, is_prefix),
+          Variable::Upvalue(index, false) => Op::StepUpvalue(index, increment, is_prefix),
+          Variable::Global => Op::StepGlobal(self.name(name), increment, is_prefix),
+        };
+        self.emit(op);
+      }
+      ExprKind::Get(object, name) => {
+        self.expression(object)?;
+        let index = self.name(name);
+        self.emit(Op::StepProperty(index, increment, is_prefix));
+      }
+      ExprKind::Index(object, index) => {
+        self.expression(object)?;
+        self.expression(index)?;
+        self.emit(Op::StepIndex(increment, is_prefix));
+      }
+      _ => return Err(MewError::syntax("Invalid assignment target")),
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,759 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::chunk::{Closure, Op, Proto, Upvalue};
+use crate::error::{Limit, MewError, MewResult, Span};
+use crate::interpreter::{CallFrame, Code, Interpreter, STACK_RED_ZONE, STACK_SEGMENT};
+use crate::value::{is_uninitialized, uninitialized, uninitialized_marker, BinaryOp, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Stack and call frames of the VM. Mew functions called from native code,
+/// like an `arr.map` callback, run on the same stack as their caller so
+/// that upvalues can point into it.
+#[derive(Default)]
+pub(crate) struct Machine {
+  stack: Vec<Value>,
+  frames: Vec<Frame>,
+  // Upvalues still pointing into the stack, ordered by slot
+  open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+  // Errors being handled by `catch` and `furever` blocks
+  errors: Vec<MewError>,
+}
+
+struct Frame {
+  closure: Rc<Closure>,
+  // Kept up to date while the frame runs, so errors can be located
+  ip: usize,
+  // Stack index of slot 0. The function being called sits just below it.
+  base: usize,
+  handlers: Vec<Handler>,
+  errors: usize,
+  // Whether the frame counts towards the call depth limit, which scripts
+  // don't
+  is_call: bool,
+}
+
+// Where to continue when an error is raised inside a `purrhaps` block
+struct Handler {
+  target: usize,
+  stack_len: usize,
+  errors: usize,
+}
+
+impl Interpreter {
+  /// Run a compiled program in the current scope
+  pub(crate) fn run_script(&mut self, script: Rc<Proto>) -> MewResult<Value> {
+    let closure = Rc::new(Closure {
+      proto: script,
+      upvalues: Vec::new(),
+      globals: self.environment.clone(),
+      receiver: None,
+      file: self.current_file(),
+    });
+    self.enter(closure, Vec::new(), false)
+  }
+
+  /// Call a compiled function from Rust, or from native functions
+  pub(crate) fn call_closure(
+    &mut self,
+    closure: Rc<Closure>,
+    arguments: Vec<Value>,
+  ) -> MewResult<Value> {
+    self.enter(closure, arguments, true)
+  }
+
+  fn enter(
+    &mut self,
+    closure: Rc<Closure>,
+    arguments: Vec<Value>,
+    is_call: bool,
+  ) -> MewResult<Value> {
+    let depth = self.vm.frames.len();
+    let argument_count = arguments.len();
+
+    self.vm.stack.push(Value::Closure(closure.clone()));
+    self.vm.stack.extend(arguments);
+    if let Err(error) = self.push_frame(closure, argument_count, is_call) {
+      let len = self.vm.stack.len();
+      self.vm.stack.truncate(len - argument_count - 1);
+      return Err(error);
+    }
+
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.run(depth))
+  }
+
+  // Start a call to `closure`, whose arguments are on top of the stack
+  fn push_frame(
+    &mut self,
+    closure: Rc<Closure>,
+    argument_count: usize,
+    is_call: bool,
+  ) -> MewResult<()> {
+    let proto = &closure.proto;
+    if argument_count != proto.arity {
+      return Err(MewError::runtime(format!(
+        "Expected {} arguments but got {}",
+        proto.arity, argument_count
+      )));
+    }
+
+    if is_call {
+      if let Some(max) = self.limits().max_call_depth {
+        if self.call_depth >= max {
+          self.record_traceback(self.call_site);
+          return Err(MewError::LimitExceeded(Limit::CallDepth(max)));
+        }
+      }
+      self.call_depth += 1;
+      self.calls.push(CallFrame::new(
+        Code::Closure(closure.clone()),
+        self.call_site,
+      ));
+    }
+
+    let base = self.vm.stack.len() - argument_count;
+    if proto.is_method {
+      let (receiver, parent) = closure
+        .receiver
+        .clone()
+        .unwrap_or((Value::Undefined, Value::Undefined));
+      self.vm.stack.splice(base..base, [receiver, parent]);
+    }
+
+    self.vm.frames.push(Frame {
+      closure,
+      ip: 0,
+      base,
+      handlers: Vec::new(),
+      errors: self.vm.errors.len(),
+      is_call,
+    });
+    Ok(())
+  }
+
+  // Execute until the frame at `depth` returns
+  fn run(&mut self, depth: usize) -> MewResult<Value> {
+    loop {
+      match self.execute_frames(depth) {
+        Ok(value) => return Ok(value),
+        Err(error) => {
+          let error = self.locate_error(error);
+          self.handle_error(depth, error)?
+        }
+      }
+    }
+  }
+
+  // Give an error the span of the instruction that raised it, like the
+  // tree-walker does with the failing node
+  fn locate_error(&mut self, error: MewError) -> MewError {
+    if error.span() != Some(Span::unknown()) {
+      return error;
+    }
+
+    let span = self.current_span();
+    self.record_traceback(span.start);
+    error.at(span)
+  }
+
+  fn current_span(&self) -> Span {
+    let frame = self.vm.frames.last().expect("frame");
+    frame.closure.proto.chunk.spans[frame.ip - 1]
+  }
+
+  // Continue at the innermost `purrhaps` handler above `depth`, or remove
+  // the frames and fail
+  fn handle_error(&mut self, depth: usize, error: MewError) -> MewResult<()> {
+    while self.vm.frames.len() > depth {
+      let frame = self.vm.frames.last_mut().expect("frame");
+      if let Some(handler) = frame.handlers.pop() {
+        frame.ip = handler.target;
+        self.close_upvalues(handler.stack_len);
+        self.vm.stack.truncate(handler.stack_len);
+        self.vm.errors.truncate(handler.errors);
+        self.vm.errors.push(error);
+        return Ok(());
+      }
+
+      self.pop_frame();
+    }
+
+    Err(error)
+  }
+
+  fn pop_frame(&mut self) {
+    let frame = self.vm.frames.pop().expect("frame");
+    self.close_upvalues(frame.base);
+    self.vm.stack.truncate(frame.base - 1);
+    self.vm.errors.truncate(frame.errors);
+    if frame.is_call {
+      self.call_depth -= 1;
+      self.calls.pop();
+    }
+  }
+
+  fn current_frame(&self) -> (Rc<Closure>, usize, usize) {
+    let frame = self.vm.frames.last().expect("frame");
+    (frame.closure.clone(), frame.ip, frame.base)
+  }
+
+  fn pop(&mut self) -> Value {
+    self.vm.stack.pop().expect("value on the stack")
+  }
+
+  fn peek(&self) -> &Value {
+    self.vm.stack.last().expect("value on the stack")
+  }
+
+  fn execute_frames(&mut self, depth: usize) -> MewResult<Value> {
+    let (mut closure, mut ip, mut base) = self.current_frame();
+
+    loop {
+      let op = closure.proto.chunk.code[ip];
+      ip += 1;
+      self.vm.frames.last_mut().expect("frame").ip = ip;
+
+      match op {
+        Op::Constant(index) => {
+          let value = closure.proto.chunk.constants[index as usize].clone();
+          self.vm.stack.push(value);
+        }
+        Op::Undefined => self.vm.stack.push(Value::Undefined),
+        Op::Null => self.vm.stack.push(Value::Null),
+        Op::Uninitialized => self.vm.stack.push(uninitialized_marker()),
+        Op::True => self.vm.stack.push(Value::Bool(true)),
+        Op::False => self.vm.stack.push(Value::Bool(false)),
+        Op::Pop => {
+          self.pop();
+        }
+        Op::Dup => {
+          let value = self.peek().clone();
+          self.vm.stack.push(value);
+        }
+        Op::Dup2 => {
+          let len = self.vm.stack.len();
+          self.vm.stack.extend_from_within(len - 2..);
+        }
+
+        Op::GetLocal(slot) => {
+          let value = self.vm.stack[base + slot as usize].clone();
+          self.vm.stack.push(value);
+        }
+        Op::SetLocal(slot) => {
+          let value = self.peek().clone();
+          self.vm.stack[base + slot as usize] = value;
+        }
+        Op::GetUpvalue(index) => {
+          let value = self.read_upvalue(&closure, index)?;
+          self.vm.stack.push(value);
+        }
+        Op::SetUpvalue(index) => {
+          let value = self.peek().clone();
+          self.write_upvalue(&closure, index, value)?;
+        }
+        Op::GetGlobal(name) => {
+          let name = &closure.proto.chunk.names[name as usize];
+          let value = closure.globals.borrow().get(name)?;
+          self.vm.stack.push(value);
+        }
+        Op::SetGlobal(name) => {
+          let name = &closure.proto.chunk.names[name as usize];
+          let value = self.peek().clone();
+          closure.globals.borrow_mut().assign(name, value)?;
+        }
+        Op::DefineGlobal(name, is_const) => {
+          let name = &closure.proto.chunk.names[name as usize];
+          let value = self.pop();
+          closure.globals.borrow_mut().define(name, value, is_const);
+        }
+        Op::ConstError(name) => {
+          return Err(MewError::runtime(format!(
+            "Cannot reassign to constant '{}'",
+            closure.proto.chunk.names[name as usize]
+          )));
+        }
+
+        Op::GetProperty(name) => {
+          let object = self.pop();
+          let value = self.get_property(&object, &closure.proto.chunk.names[name as usize])?;
+          self.vm.stack.push(value);
+        }
+        Op::SetProperty(name) => {
+          let value = self.pop();
+          let object = self.pop();
+          let name = &closure.proto.chunk.names[name as usize];
+          self.set_property(&object, name, value.clone())?;
+          self.vm.stack.push(value);
+        }
+        Op::GetIndex => {
+          let index = self.pop();
+          let object = self.pop();
+          let value = self.get_index(&object, &index)?;
+          self.vm.stack.push(value);
+        }
+        Op::SetIndex => {
+          let value = self.pop();
+          let index = self.pop();
+          let object = self.pop();
+          self.set_index(&object, &index, value.clone())?;
+          self.vm.stack.push(value);
+        }
+        Op::SuperGet(name) => {
+          let receiver = self.pop();
+          let parent = self.pop();
+          if let Value::Undefined = parent {
+            return Err(MewError::runtime(
+              "'super' used outside of a breed with a parent",
+            ));
+          }
+
+          let name = &closure.proto.chunk.names[name as usize];
+          let value = self.get_super(&parent, receiver, name)?;
+          self.vm.stack.push(value);
+        }
+
+        Op::Binary(op) => {
+          let right = self.pop();
+          let left = self.pop();
+
+          let result = match (op, &left, &right) {
+            (BinaryOp::Add, Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+            (BinaryOp::Sub, Value::Number(a), Value::Number(b)) => Value::Number(a - b),
+            (BinaryOp::Mul, Value::Number(a), Value::Number(b)) => Value::Number(a * b),
+            (BinaryOp::Div, Value::Number(a), Value::Number(b)) if *b != 0.0 => {
+              Value::Number(a / b)
+            }
+            (BinaryOp::Mod, Value::Number(a), Value::Number(b)) if *b != 0.0 => {
+              Value::Number(a % b)
+            }
+            (BinaryOp::Lt, Value::Number(a), Value::Number(b)) => Value::Bool(a < b),
+            (BinaryOp::Lte, Value::Number(a), Value::Number(b)) => Value::Bool(a <= b),
+            (BinaryOp::Gt, Value::Number(a), Value::Number(b)) => Value::Bool(a > b),
+            (BinaryOp::Gte, Value::Number(a), Value::Number(b)) => Value::Bool(a >= b),
+            _ => {
+              let result = self.apply_binary(op, &left, &right)?;
+              self.check_size(&result)?;
+              result
+            }
+          };
+          self.vm.stack.push(result);
+        }
+        Op::Negate => match self.pop() {
+          Value::Number(n) => self.vm.stack.push(Value::Number(-n)),
+          value => {
+            return Err(MewError::type_error(format!(
+              "Cannot apply operator Minus to {}",
+              value.type_name()
+            )))
+          }
+        },
+        Op::Not => {
+          let value = self.pop();
+          self.vm.stack.push(Value::Bool(!value.is_truthy()));
+        }
+
+        Op::StepLocal(slot, increment, is_prefix) => {
+          let slot = base + slot as usize;
+          let (new, result) = step(&self.vm.stack[slot], increment, is_prefix)?;
+          self.vm.stack[slot] = new;
+          self.vm.stack.push(result);
+        }
+        Op::StepUpvalue(index, increment, is_prefix) => {
+          let (new, result) = step(&self.read_upvalue(&closure, index)?, increment, is_prefix)?;
+          self.write_upvalue(&closure, index, new)?;
+          self.vm.stack.push(result);
+        }
+        Op::StepGlobal(name, increment, is_prefix) => {
+          let name = &closure.proto.chunk.names[name as usize];
+          let current = closure.globals.borrow().get(name)?;
+          let (new, result) = step(&current, increment, is_prefix)?;
+          closure.globals.borrow_mut().assign(name, new)?;
+          self.vm.stack.push(result);
+        }
+        Op::StepProperty(name, increment, is_prefix) => {
+          let object = self.pop();
+          let name = &closure.proto.chunk.names[name as usize];
+          let current = self.get_property(&object, name)?;
+          let (new, result) = step(&current, increment, is_prefix)?;
+          self.set_property(&object, name, new)?;
+          self.vm.stack.push(result);
+        }
+        Op::StepIndex(increment, is_prefix) => {
+          let index = self.pop();
+          let object = self.pop();
+          let current = self.get_index(&object, &index)?;
+          let (new, result) = step(&current, increment, is_prefix)?;
+          self.set_index(&object, &index, new)?;
+          self.vm.stack.push(result);
+        }
+
+        Op::Jump(target) => ip = target as usize,
+        Op::JumpIfFalse(target) => {
+          if !self.pop().is_truthy() {
+            ip = target as usize;
+          }
+        }
+        Op::JumpIfTrue(target) => {
+          if self.pop().is_truthy() {
+            ip = target as usize;
+          }
+        }
+        Op::JumpIfFalseKeep(target) => {
+          if self.peek().is_truthy() {
+            self.pop();
+          } else {
+            ip = target as usize;
+          }
+        }
+        Op::JumpIfTrueKeep(target) => {
+          if self.peek().is_truthy() {
+            ip = target as usize;
+          } else {
+            self.pop();
+          }
+        }
+        Op::JumpIfNotNullishKeep(target) => {
+          if let Value::Null | Value::Undefined = self.peek() {
+            self.pop();
+          } else {
+            ip = target as usize;
+          }
+        }
+        Op::JumpIfNullish(target) => {
+          if let Value::Null | Value::Undefined = self.peek() {
+            self.pop();
+            self.vm.stack.push(Value::Undefined);
+            ip = target as usize;
+          }
+        }
+
+        Op::Call(argument_count) => {
+          self.call_site = self.current_span().start;
+          let argument_count = argument_count as usize;
+          let callee_slot = self.vm.stack.len() - argument_count - 1;
+
+          match &self.vm.stack[callee_slot] {
+            Value::Closure(callee) => {
+              let callee = callee.clone();
+              self.push_frame(callee, argument_count, true)?;
+              (closure, ip, base) = self.current_frame();
+            }
+            _ => {
+              let arguments = self.vm.stack.split_off(callee_slot + 1);
+              let callee = self.pop();
+              let result = self.call_function(callee, arguments)?;
+              self.vm.stack.push(result);
+            }
+          }
+        }
+        Op::Return => {
+          let result = self.pop();
+          self.pop_frame();
+
+          if self.vm.frames.len() == depth {
+            return Ok(result);
+          }
+
+          self.vm.stack.push(result);
+          (closure, ip, base) = self.current_frame();
+        }
+        Op::Closure(index) => {
+          let proto = closure.proto.chunk.functions[index as usize].clone();
+          let upvalues = proto
+            .upvalues
+            .iter()
+            .map(|source| {
+              if source.is_local {
+                self.capture_upvalue(base + source.index as usize)
+              } else {
+                closure.upvalues[source.index as usize].clone()
+              }
+            })
+            .collect();
+
+          self.vm.stack.push(Value::Closure(Rc::new(Closure {
+            proto,
+            upvalues,
+            globals: closure.globals.clone(),
+            receiver: None,
+            file: closure.file.clone(),
+          })));
+        }
+        Op::Unwind(slot) => {
+          let len = base + slot as usize;
+          self.close_upvalues(len);
+          self.vm.stack.truncate(len);
+        }
+
+        Op::MakeArray(count) => {
+          let len = self.vm.stack.len();
+          let elements = self.vm.stack.split_off(len - count as usize);
+          self.check_collection_size(elements.len())?;
+          self.vm.stack.push(Value::array(elements));
+        }
+        Op::MakeObject(count) => {
+          let len = self.vm.stack.len();
+          let mut entries = self
+            .vm
+            .stack
+            .split_off(len - 2 * count as usize)
+            .into_iter();
+
+          let mut properties = HashMap::new();
+          while let (Some(Value::String(ref mut key)), Some(value)) =
+            (entries.next(), entries.next())
+          {
+            properties.insert(std::mem::take(key), value);
+          }
+
+          self.check_collection_size(properties.len())?;
+          self.vm.stack.push(Value::object(properties));
+        }
+        Op::MakeBreed(name, method_count, has_parent) => {
+          let len = self.vm.stack.len();
+          let methods: HashMap<String, Value> = self
+            .vm
+            .stack
+            .split_off(len - method_count as usize)
+            .into_iter()
+            .map(|method| {
+              let name = match &method {
+                Value::Closure(method) => method.name().unwrap_or_default().to_string(),
+                _ => unreachable!(),
+              };
+              (name, method)
+            })
+            .collect();
+          let parent = if has_parent { Some(self.pop()) } else { None };
+
+          let name = &closure.proto.chunk.names[name as usize];
+          let breed = Self::make_breed(name, parent, methods)?;
+          self.vm.stack.push(breed);
+        }
+        Op::StaticMethod(name) => {
+          // Bound to the breed it is called on when accessed
+          let method = self.pop();
+          if let Value::Object(breed) = self.peek() {
+            if let Some(breed) = breed.borrow_mut().breed_mut() {
+              let name = closure.proto.chunk.names[name as usize].clone();
+              breed.statics.insert(name, method);
+            }
+          }
+        }
+        Op::StaticField(name) => {
+          let initializer = self.pop();
+          let initializer = self.bind_static(initializer);
+          let Value::Closure(initializer) = &initializer else {
+            unreachable!()
+          };
+          let value = self.call_closure(initializer.clone(), Vec::new())?;
+          if let Value::Object(breed) = self.peek() {
+            let name = closure.proto.chunk.names[name as usize].clone();
+            breed.borrow_mut().insert(name, value);
+          }
+        }
+
+        Op::Step => {
+          if let Err(error) = self.step() {
+            self.record_traceback(self.current_span().start);
+            return Err(error);
+          }
+        }
+        Op::Print => {
+          let value = self.pop();
+          writeln!(self.stdout(), "{}", value)?;
+        }
+        Op::Throw => {
+          let value = self.pop();
+          return Err(MewError::Thrown(value, Span::unknown()));
+        }
+        Op::Fail(message) => {
+          return Err(MewError::runtime(
+            closure.proto.chunk.names[message as usize].clone(),
+          ));
+        }
+
+        Op::TryBegin(target) => {
+          let handler = Handler {
+            target: target as usize,
+            stack_len: self.vm.stack.len(),
+            errors: self.vm.errors.len(),
+          };
+          self
+            .vm
+            .frames
+            .last_mut()
+            .expect("frame")
+            .handlers
+            .push(handler);
+        }
+        Op::TryEnd => {
+          self.vm.frames.last_mut().expect("frame").handlers.pop();
+        }
+        Op::CaughtError => {
+          let error = self.vm.errors.pop().expect("error being handled");
+          // The user or host asked to stop, so the program doesn't get a say
+          if !error.is_catchable() {
+            return Err(error);
+          }
+          self.vm.stack.push(Self::error_to_value(error));
+        }
+        Op::Rethrow => return Err(self.vm.errors.pop().expect("error being handled")),
+        Op::DiscardError => {
+          self.vm.errors.pop();
+        }
+
+        Op::Keys => {
+          let value = self.pop();
+          let keys = Self::native_object_keys(self, vec![value])?;
+          self.vm.stack.push(keys);
+        }
+        Op::Values => {
+          let value = self.pop();
+          let values = Self::native_object_values(self, vec![value])?;
+          self.vm.stack.push(values);
+        }
+        Op::ForInNext(slot, exit) => {
+          let slot = base + slot as usize;
+          let Value::Number(index) = self.vm.stack[slot + 1] else {
+            unreachable!()
+          };
+
+          let item = match &self.vm.stack[slot] {
+            Value::Array(items) => items.borrow().get(index as usize).cloned(),
+            _ => None,
+          };
+          match item {
+            Some(item) => {
+              self.vm.stack[slot + 1] = Value::Number(index + 1.0);
+              self.vm.stack.push(item);
+            }
+            None => ip = exit as usize,
+          }
+        }
+
+        Op::Import(module, name) => {
+          let specifier = &closure.proto.chunk.names[module as usize];
+          let name = &closure.proto.chunk.names[name as usize];
+          self.call_site = self.current_span().start;
+          let value = self.import_binding(specifier, name)?;
+          self.vm.stack.push(value);
+        }
+        Op::Export(name) => {
+          self.export(&closure.proto.chunk.names[name as usize]);
+        }
+      }
+    }
+  }
+
+  // Bind a static field initializer to the breed on top of the stack
+  fn bind_static(&self, member: Value) -> Value {
+    let Value::Closure(member) = &member else {
+      unreachable!()
+    };
+
+    let breed = self.peek().clone();
+    let parent = match &breed {
+      Value::Object(obj) => obj.borrow().breed().and_then(|b| b.parent.clone()),
+      _ => None,
+    };
+    Value::Closure(Rc::new(
+      member.bind(breed, parent.unwrap_or(Value::Undefined)),
+    ))
+  }
+
+  // Captured variables are the only way to reach a variable before its
+  // declaration has run, so they are checked for it
+  fn read_upvalue(&self, closure: &Closure, index: u32) -> MewResult<Value> {
+    let value = match &*closure.upvalues[index as usize].borrow() {
+      Upvalue::Open(slot) => self.vm.stack[*slot].clone(),
+      Upvalue::Closed(value) => value.clone(),
+    };
+
+    if is_uninitialized(&value) {
+      return Err(uninitialized(&closure.proto.upvalue_names[index as usize]));
+    }
+    Ok(value)
+  }
+
+  fn write_upvalue(&mut self, closure: &Closure, index: u32, value: Value) -> MewResult<()> {
+    let mut upvalue = closure.upvalues[index as usize].borrow_mut();
+    let current = match &mut *upvalue {
+      Upvalue::Open(slot) => &mut self.vm.stack[*slot],
+      Upvalue::Closed(closed) => closed,
+    };
+
+    if is_uninitialized(current) {
+      return Err(uninitialized(&closure.proto.upvalue_names[index as usize]));
+    }
+    *current = value;
+    Ok(())
+  }
+
+  // The upvalue for a stack slot, shared by every closure capturing it
+  fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+    let open = &mut self.vm.open_upvalues;
+    let position = open.partition_point(|upvalue| open_slot(upvalue) < slot);
+
+    if let Some(upvalue) = open.get(position) {
+      if open_slot(upvalue) == slot {
+        return upvalue.clone();
+      }
+    }
+
+    let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+    open.insert(position, upvalue.clone());
+    upvalue
+  }
+
+  // Move the values of slots from `from` up into their upvalues, before the
+  // slots are dropped
+  fn close_upvalues(&mut self, from: usize) {
+    while let Some(upvalue) = self.vm.open_upvalues.last() {
+      let slot = open_slot(upvalue);
+      if slot < from {
+        break;
+      }
+
+      let value = self.vm.stack[slot].clone();
+      *upvalue.borrow_mut() = Upvalue::Closed(value);
+      self.vm.open_upvalues.pop();
+    }
+  }
+}
+
+fn open_slot(upvalue: &RefCell<Upvalue>) -> usize {
+  match &*upvalue.borrow() {
+    Upvalue::Open(slot) => *slot,
+    Upvalue::Closed(_) => unreachable!("closed upvalues aren't tracked"),
+  }
+}
+
+// The new value of a `++` or `--` target, and the value of the expression
+fn step(current: &Value, increment: bool, is_prefix: bool) -> MewResult<(Value, Value)> {
+  let Value::Number(n) = current else {
+    return Err(MewError::type_error(format!(
+      "Cannot {} a non-number value: {}",
+      if increment { "increment" } else { "decrement" },
+      current.type_name()
+    )));
+  };
+
+  let new = if increment { n + 1.0 } else { n - 1.0 };
+  let result = if is_prefix { new } else { *n };
+  Ok((Value::Number(new), Value::Number(result)))
+}
@@ -0,0 +1,27 @@
+// This file is part of Mew Language.
+//
+// Mew Language is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Mew Language is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Mew Language.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Bytecode engine, used with [`Engine::Vm`](crate::interpreter::Engine).
+//! Programs are compiled to one [`Proto`] per function, whose locals live in
+//! numbered stack slots instead of environments. Variables captured by
+//! closures become upvalues, and globals are still looked up by name.
+
+mod chunk;
+mod compiler;
+mod machine;
+
+pub use chunk::{Chunk, Closure, Op, Proto, Upvalue, UpvalueSource};
+pub use compiler::compile;
+pub(crate) use machine::Machine;
@@ -0,0 +1,102 @@
+/* Exercises the C API of mew_lib. Built and run by tests/ffi.rs. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "mew.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+  do {                                                                \
+    if (!(cond)) {                                                    \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+              #cond);                                                 \
+      failures++;                                                     \
+    }                                                                 \
+  } while (0)
+
+static int streq(const char *a, const char *b) {
+  return a != NULL && b != NULL && strcmp(a, b) == 0;
+}
+
+/* Returns the number of arguments it was called with, counting calls in
+ * user_data */
+static const char *count_args(const char *args_json, void *user_data) {
+  static char buffer[32];
+  int *calls = (int *)user_data;
+  int count = 0;
+
+  (*calls)++;
+  if (strcmp(args_json, "[]") != 0) {
+    count = 1;
+    for (const char *p = args_json; *p; p++) {
+      if (*p == ',') {
+        count++;
+      }
+    }
+  }
+
+  snprintf(buffer, sizeof buffer, "%d", count);
+  return buffer;
+}
+
+static const char *always_fail(const char *args_json, void *user_data) {
+  (void)args_json;
+  (void)user_data;
+  return NULL;
+}
+
+int main(void) {
+  MewInterpreter *mew = mew_interpreter_new();
+  CHECK(mew != NULL);
+
+  /* Results come back as JSON, and globals persist between calls */
+  CHECK(mew_eval(mew, "catlt x = 20; x + 22") == MEW_STATUS_OK);
+  CHECK(streq(mew_result_json(mew), "42"));
+  CHECK(mew_error_message(mew) == NULL);
+
+  CHECK(mew_eval(mew, "catlt tom = { name: \"Tom\", toys: [1, 2.5, null] }; tom") == MEW_STATUS_OK);
+  CHECK(streq(mew_result_json(mew), "{\"name\":\"Tom\",\"toys\":[1,2.5,null]}"));
+
+  CHECK(mew_eval(mew, "x * 2") == MEW_STATUS_OK);
+  CHECK(streq(mew_result_json(mew), "40"));
+
+  /* Errors report their kind, message and location */
+  CHECK(mew_eval(mew, "missing + 1") == MEW_STATUS_ERROR);
+  CHECK(mew_result_json(mew) == NULL);
+  CHECK(streq(mew_error_kind(mew), "NameError"));
+  CHECK(strstr(mew_error_message(mew), "missing") != NULL);
+
+  CHECK(mew_eval(mew, "catlt y = 1;\ncatlt = ;") == MEW_STATUS_ERROR);
+  CHECK(streq(mew_error_kind(mew), "SyntaxError"));
+  CHECK(mew_error_line(mew) == 2);
+  CHECK(mew_error_column(mew) > 0);
+
+  /* Callbacks receive their arguments as JSON and return JSON */
+  int calls = 0;
+  CHECK(mew_register_function(mew, "countArgs", count_args, &calls) == MEW_STATUS_OK);
+  CHECK(mew_eval(mew, "countArgs(1, \"a\", [true]) + countArgs()") == MEW_STATUS_OK);
+  CHECK(streq(mew_result_json(mew), "3"));
+  CHECK(calls == 2);
+
+  CHECK(mew_register_function(mew, "fail", always_fail, NULL) == MEW_STATUS_OK);
+  CHECK(mew_eval(mew, "fail()") == MEW_STATUS_ERROR);
+  CHECK(strstr(mew_error_message(mew), "fail") != NULL);
+
+  /* Failures inside callbacks can be caught by Mew code */
+  CHECK(mew_eval(mew, "catlt kind; purrhaps { fail(); } catch (e) { kind = e.kind; } kind") == MEW_STATUS_OK);
+  CHECK(streq(mew_result_json(mew), "\"RuntimeError\""));
+
+  mew_interpreter_free(mew);
+  mew_interpreter_free(NULL);
+
+  if (failures > 0) {
+    fprintf(stderr, "%d check(s) failed\n", failures);
+    return EXIT_FAILURE;
+  }
+
+  printf("ok\n");
+  return EXIT_SUCCESS;
+}
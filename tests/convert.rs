@@ -0,0 +1,51 @@
+// Converting Mew values to Rust types
+
+use mew_lib::{from_value, Interpreter, Value};
+use std::collections::HashMap;
+
+fn eval(source: &str) -> Value {
+  Interpreter::new()
+    .eval(source)
+    .expect("the program should run")
+}
+
+#[test]
+fn circular_structures_fail() {
+  for source in [
+    "catlt c = {}; c.me = c; c",
+    "catlt a = [1]; a.push({ list: a }); a",
+  ] {
+    let error = from_value::<serde_json::Value>(eval(source)).expect_err(source);
+    assert_eq!(error.message(), "Cannot deserialize a circular structure");
+  }
+
+  let error = from_value::<HashMap<String, serde_json::Value>>(eval("catlt c = {}; c.me = c; c"))
+    .expect_err("a circular object should fail");
+  assert_eq!(error.message(), "Cannot deserialize a circular structure");
+}
+
+#[test]
+fn shared_values_convert() {
+  let value = eval("catlt shared = [1, 2]; ({ a: shared, b: [shared, shared] })");
+  let converted: serde_json::Value = from_value(value).expect("shared arrays aren't cycles");
+  assert_eq!(
+    converted,
+    serde_json::json!({ "a": [1, 2], "b": [[1, 2], [1, 2]] })
+  );
+}
+
+#[test]
+fn json_integers_round() {
+  let value = eval(
+    r#"[MewJ.sniff("9007199254740993"), MewJ.sniff("9007199254740993.0"), MewJ.sniff("-12345678901234567890")]"#,
+  );
+  let numbers: Vec<f64> = from_value(value).expect("numbers");
+  assert_eq!(
+    numbers,
+    [
+      9007199254740992.0,
+      9007199254740992.0,
+      -12345678901234567890.0
+    ]
+  );
+}
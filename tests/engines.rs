@@ -0,0 +1,107 @@
+// Runs the examples on both engines and checks they print the same thing
+
+use mew_lib::{Engine, Interpreter, MewError};
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+// These print the current time or random numbers
+const SKIPPED: &[&str] = &["cattime_examples", "mewth_examples"];
+
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.borrow_mut().extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+fn run(engine: Engine, path: &Path) -> String {
+  let output = Output::default();
+  let mut interpreter = Interpreter::new();
+  interpreter.set_engine(engine);
+  interpreter.set_stdout(output.clone());
+  interpreter.set_stderr(io::sink());
+
+  if let Err(error) = interpreter.run_file(path) {
+    panic!("{} failed on {:?}: {}", path.display(), engine, error);
+  }
+
+  let printed = output.0.borrow();
+  String::from_utf8_lossy(&printed).into_owned()
+}
+
+fn eval(engine: Engine, source: &str) -> MewError {
+  let mut interpreter = Interpreter::new();
+  interpreter.set_engine(engine);
+  interpreter.set_stdout(io::sink());
+  interpreter.set_stderr(io::sink());
+
+  interpreter
+    .eval(source)
+    .expect_err("the program should be rejected")
+}
+
+#[test]
+fn examples_match() {
+  let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+  let mut ran = 0;
+
+  for entry in fs::read_dir(examples).expect("examples directory") {
+    let path = entry.expect("example entry").path();
+    let name = path
+      .file_stem()
+      .and_then(|stem| stem.to_str())
+      .unwrap_or("");
+    if path.extension().is_none_or(|ext| ext != "mew") || SKIPPED.contains(&name) {
+      continue;
+    }
+
+    let tree = run(Engine::Tree, &path);
+    assert!(!tree.is_empty(), "{} printed nothing", path.display());
+    assert_eq!(tree, run(Engine::Vm, &path), "{} differs", path.display());
+    ran += 1;
+  }
+
+  assert!(ran > 0, "no examples found");
+}
+
+#[test]
+fn resolver_errors() {
+  let cases = [
+    (
+      "cat f() { purr(x); catlt x = 1; }",
+      "Cannot use 'x' before its declaration",
+    ),
+    (
+      "catlt a = 1; catlt a = 2;",
+      "'a' is already declared in this scope",
+    ),
+    (
+      "cat f() { catst c = 1; c++; }",
+      "Cannot reassign to constant 'c'",
+    ),
+  ];
+
+  for (source, message) in cases {
+    for engine in [Engine::Tree, Engine::Vm] {
+      let error = eval(engine, source);
+      assert_eq!(
+        error.kind_name(),
+        "SyntaxError",
+        "{} on {:?}",
+        source,
+        engine
+      );
+      assert_eq!(error.message(), message, "{} on {:?}", source, engine);
+    }
+  }
+}
@@ -0,0 +1,72 @@
+// Builds tests/c/ffi_test.c against the mew_lib shared library and runs it
+
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// The shared library is built next to this test executable
+fn library_dir() -> PathBuf {
+  let exe = env::current_exe().expect("test executable path");
+  let deps = exe.parent().expect("deps directory");
+
+  [deps, deps.parent().expect("target directory")]
+    .iter()
+    .find(|dir| dir.join("libmew_lib.so").exists() || dir.join("libmew_lib.dylib").exists())
+    .map(|dir| dir.to_path_buf())
+    .expect("libmew_lib shared library should be built")
+}
+
+// build.rs regenerates the header on every build, so a stale checked-in copy
+// means someone forgot MEW_UPDATE_HEADER=1
+#[test]
+fn header_up_to_date() {
+  let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let generated = Path::new(env!("MEW_HEADER_DIR")).join("mew.h");
+
+  assert_eq!(
+    fs::read_to_string(root.join("include/mew.h")).expect("include/mew.h"),
+    fs::read_to_string(&generated).expect("generated mew.h"),
+    "include/mew.h is stale, refresh it with `MEW_UPDATE_HEADER=1 cargo build`"
+  );
+}
+
+#[test]
+fn c_api() {
+  let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+  if Command::new(&compiler).arg("--version").output().is_err() {
+    eprintln!("skipping: no C compiler ({}) found", compiler);
+    return;
+  }
+
+  let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let lib_dir = library_dir();
+  let exe = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("ffi_test");
+
+  let status = Command::new(&compiler)
+    .arg(root.join("tests/c/ffi_test.c"))
+    .arg("-std=c99")
+    .arg("-Wall")
+    .arg("-Werror")
+    .arg("-I")
+    .arg(env!("MEW_HEADER_DIR"))
+    .arg("-L")
+    .arg(&lib_dir)
+    .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+    .arg("-lmew_lib")
+    .arg("-o")
+    .arg(&exe)
+    .status()
+    .expect("C compiler should run");
+  assert!(status.success(), "compiling ffi_test.c failed");
+
+  let output = Command::new(&exe).output().expect("ffi_test should run");
+  assert!(
+    output.status.success(),
+    "ffi_test failed:\n{}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}